@@ -482,9 +482,19 @@ export type JackpotLottery = {
     },
     {
       "name": "vrfLock",
+      "docs": [
+        "Pending VRF request of a round, closed on fulfillment"
+      ],
       "type": {
         "kind": "struct",
-        "fields": []
+        "fields": [
+          {
+            "name": "request",
+            "type": {
+              "defined": "VrfRequest"
+            }
+          }
+        ]
       }
     }
   ],
@@ -505,6 +515,57 @@ export type JackpotLottery = {
           }
         ]
       }
+    },
+    {
+      "name": "VrfRequest",
+      "type": {
+        "kind": "struct",
+        "fields": [
+          {
+            "name": "ixSighash",
+            "type": {
+              "array": [
+                "u8",
+                8
+              ]
+            }
+          },
+          {
+            "name": "ixData",
+            "docs": [
+              "Callback arguments with a default `VrfResult` placeholder"
+            ],
+            "type": "bytes"
+          },
+          {
+            "name": "accounts",
+            "docs": [
+              "Callback accounts, without the oracle signer"
+            ],
+            "type": {
+              "vec": {
+                "defined": "AccountMetaRef"
+              }
+            }
+          }
+        ]
+      }
+    },
+    {
+      "name": "AccountMetaRef",
+      "type": {
+        "kind": "struct",
+        "fields": [
+          {
+            "name": "pubkey",
+            "type": "publicKey"
+          },
+          {
+            "name": "isWritable",
+            "type": "bool"
+          }
+        ]
+      }
     }
   ],
   "errors": [
@@ -572,6 +633,11 @@ export type JackpotLottery = {
       "code": 6012,
       "name": "ViolatedLotteryTicketConstraint",
       "msg": "Violated lottery ticket constraint"
+    },
+    {
+      "code": 6013,
+      "name": "InvalidLotteryConfig",
+      "msg": "Lottery length must be between 1 and 6 and not exceed the max number"
     }
   ]
 };
@@ -1060,9 +1126,19 @@ export const IDL: JackpotLottery = {
     },
     {
       "name": "vrfLock",
+      "docs": [
+        "Pending VRF request of a round, closed on fulfillment"
+      ],
       "type": {
         "kind": "struct",
-        "fields": []
+        "fields": [
+          {
+            "name": "request",
+            "type": {
+              "defined": "VrfRequest"
+            }
+          }
+        ]
       }
     }
   ],
@@ -1083,6 +1159,57 @@ export const IDL: JackpotLottery = {
           }
         ]
       }
+    },
+    {
+      "name": "VrfRequest",
+      "type": {
+        "kind": "struct",
+        "fields": [
+          {
+            "name": "ixSighash",
+            "type": {
+              "array": [
+                "u8",
+                8
+              ]
+            }
+          },
+          {
+            "name": "ixData",
+            "docs": [
+              "Callback arguments with a default `VrfResult` placeholder"
+            ],
+            "type": "bytes"
+          },
+          {
+            "name": "accounts",
+            "docs": [
+              "Callback accounts, without the oracle signer"
+            ],
+            "type": {
+              "vec": {
+                "defined": "AccountMetaRef"
+              }
+            }
+          }
+        ]
+      }
+    },
+    {
+      "name": "AccountMetaRef",
+      "type": {
+        "kind": "struct",
+        "fields": [
+          {
+            "name": "pubkey",
+            "type": "publicKey"
+          },
+          {
+            "name": "isWritable",
+            "type": "bool"
+          }
+        ]
+      }
     }
   ],
   "errors": [
@@ -1150,6 +1277,11 @@ export const IDL: JackpotLottery = {
       "code": 6012,
       "name": "ViolatedLotteryTicketConstraint",
       "msg": "Violated lottery ticket constraint"
+    },
+    {
+      "code": 6013,
+      "name": "InvalidLotteryConfig",
+      "msg": "Lottery length must be between 1 and 6 and not exceed the max number"
     }
   ]
 };
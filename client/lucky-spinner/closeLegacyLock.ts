@@ -0,0 +1,36 @@
+import BN from 'bn.js';
+import { TOKEN_PROGRAM_ID } from '@solana/spl-token';
+import { PublicKey } from '@solana/web3.js';
+import { CONFIG, program, provider, getProgramState, getPdaAuthority } from './config';
+
+// Stake of the spin, the `amount` of its `RequestVrf` event
+const REFUND = 0;
+
+(async () => {
+	const statePubkey = await getProgramState();
+	const pdaAuthority = await getPdaAuthority();
+
+	const [vrfLock, bump] = await PublicKey.findProgramAddress([Buffer.from('vrf-lock', 'utf-8'), CONFIG.USER.publicKey.toBuffer()], program.programId);
+
+	console.log('Executing...');
+	const tx = await program.methods
+		.closeLegacyLock(new BN(REFUND))
+		.accounts({
+			owner: CONFIG.OWNER.publicKey,
+			programState: statePubkey,
+			user: CONFIG.USER.publicKey,
+			userToken: CONFIG.USER_TOKEN,
+			pool: CONFIG.POOL,
+			tokenProgram: TOKEN_PROGRAM_ID,
+			pdaAuthority,
+			vrfLock,
+		})
+		.signers([CONFIG.OWNER])
+		.rpc({ commitment: 'confirmed' });
+
+	console.log('Fetching transaction logs...');
+	const trans = await provider.connection.getTransaction(tx, {
+		commitment: 'confirmed',
+	});
+	console.log(trans?.meta?.logMessages);
+})();
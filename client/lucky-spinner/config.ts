@@ -25,6 +25,8 @@ export const CONFIG = {
 	TAX_PERCENTAGE: 0.01 * DECIMAL,
 	BURN_PERCENTAGE: 0.5 * DECIMAL,
 	MIN_BET_AMOUNT: 1 * LAMPORTS_PER_SOL,
+	// Seconds before an unfulfilled spin can be refunded, at least 60
+	SPIN_TIMEOUT: 300,
 	// User
 	USER: Keypair.fromSecretKey(
 		new Uint8Array([
@@ -11,6 +11,7 @@ import { CONFIG, getProgramState, program, provider, DECIMAL } from './config';
 			new BN(CONFIG.TAX_PERCENTAGE),
 			new BN(CONFIG.BURN_PERCENTAGE),
 			new BN(CONFIG.MIN_BET_AMOUNT),
+			new BN(CONFIG.SPIN_TIMEOUT),
 			[
 				[new BN(15), new BN(35 * DECIMAL)],
 				[new BN(40), new BN(20 * DECIMAL)],
@@ -0,0 +1,34 @@
+import { TOKEN_PROGRAM_ID } from '@solana/spl-token';
+import { PublicKey } from '@solana/web3.js';
+import { CONFIG, program, provider, getProgramState, getPdaAuthority } from './config';
+
+(async () => {
+	const statePubkey = await getProgramState();
+	const pdaAuthority = await getPdaAuthority();
+
+	const [vrfLock, bump] = await PublicKey.findProgramAddress([Buffer.from('vrf-lock', 'utf-8'), CONFIG.USER.publicKey.toBuffer()], program.programId);
+
+	const lock = await program.account.vrfLock.fetch(vrfLock, 'confirmed');
+	console.log('Pending spin:', lock.amount.toString(), 'requested at slot', lock.slot.toString());
+
+	console.log('Executing...');
+	const tx = await program.methods
+		.refundExpiredSpin()
+		.accounts({
+			user: CONFIG.USER.publicKey,
+			userToken: CONFIG.USER_TOKEN,
+			programState: statePubkey,
+			pool: CONFIG.POOL,
+			tokenProgram: TOKEN_PROGRAM_ID,
+			pdaAuthority,
+			vrfLock,
+		})
+		.signers([CONFIG.USER])
+		.rpc({ commitment: 'confirmed' });
+
+	console.log('Fetching transaction logs...');
+	const trans = await provider.connection.getTransaction(tx, {
+		commitment: 'confirmed',
+	});
+	console.log(trans?.meta?.logMessages);
+})();
@@ -9,6 +9,7 @@ import { CONFIG, program, provider, getProgramState, getPdaAuthority } from './c
 	const pdaAuthority = await getPdaAuthority();
 
 	const [vrfLock, bump] = await PublicKey.findProgramAddress([Buffer.from('vrf-lock', 'utf-8'), CONFIG.USER.publicKey.toBuffer()], program.programId);
+	const [eventAuthority] = await PublicKey.findProgramAddress([Buffer.from('__event_authority', 'utf-8')], program.programId);
 
 	console.log('Executing...');
 	const tx = await program.methods
@@ -25,6 +26,8 @@ import { CONFIG, program, provider, getProgramState, getPdaAuthority } from './c
 			tokenProgram: TOKEN_PROGRAM_ID,
 			pdaAuthority,
 			vrfLock,
+			eventAuthority,
+			program: program.programId,
 		})
 		.signers([CONFIG.USER])
 		.rpc({ commitment: 'confirmed' });
@@ -54,6 +54,10 @@ export type LuckySpinner = {
           "name": "minBetAmount",
           "type": "u64"
         },
+        {
+          "name": "spinTimeout",
+          "type": "i64"
+        },
         {
           "name": "rates",
           "type": {
@@ -94,6 +98,10 @@ export type LuckySpinner = {
           "name": "minBetAmount",
           "type": "u64"
         },
+        {
+          "name": "spinTimeout",
+          "type": "i64"
+        },
         {
           "name": "rates",
           "type": {
@@ -155,13 +163,23 @@ export type LuckySpinner = {
           "isMut": true,
           "isSigner": false,
           "docs": [
-            "CHECK"
+            "Only one pending spin per user"
           ]
         },
         {
           "name": "systemProgram",
           "isMut": false,
           "isSigner": false
+        },
+        {
+          "name": "eventAuthority",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "program",
+          "isMut": false,
+          "isSigner": false
         }
       ],
       "args": [
@@ -175,8 +193,8 @@ export type LuckySpinner = {
       "name": "onVrfFulfilled",
       "accounts": [
         {
-          "name": "owner",
-          "isMut": true,
+          "name": "oracle",
+          "isMut": false,
           "isSigner": true
         },
         {
@@ -229,6 +247,11 @@ export type LuckySpinner = {
           "docs": [
             "CHECK"
           ]
+        },
+        {
+          "name": "oracleRegistry",
+          "isMut": false,
+          "isSigner": false
         }
       ],
       "args": [
@@ -241,6 +264,109 @@ export type LuckySpinner = {
         {
           "name": "amount",
           "type": "u64"
+        },
+        {
+          "name": "requestSlot",
+          "type": "u64"
+        }
+      ]
+    },
+    {
+      "name": "refundExpiredSpin",
+      "docs": [
+        "Give the stake back to the user when the spin is still not fulfilled after `spin_timeout` seconds"
+      ],
+      "accounts": [
+        {
+          "name": "user",
+          "isMut": true,
+          "isSigner": true
+        },
+        {
+          "name": "userToken",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "programState",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "pool",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "tokenProgram",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "pdaAuthority",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "vrfLock",
+          "isMut": true,
+          "isSigner": false
+        }
+      ],
+      "args": []
+    },
+    {
+      "name": "closeLegacyLock",
+      "docs": [
+        "Close a lock left by a spin made before `VrfLock` recorded the stake, which can be neither fulfilled nor",
+        "refunded. The stake is not on-chain, the owner refunds the amount of the spin's `RequestVrf` event"
+      ],
+      "accounts": [
+        {
+          "name": "owner",
+          "isMut": false,
+          "isSigner": true
+        },
+        {
+          "name": "programState",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "user",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "userToken",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "pool",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "tokenProgram",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "pdaAuthority",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "vrfLock",
+          "isMut": true,
+          "isSigner": false
+        }
+      ],
+      "args": [
+        {
+          "name": "refund",
+          "type": "u64"
         }
       ]
     }
@@ -289,15 +415,42 @@ export type LuckySpinner = {
                 ]
               }
             }
+          },
+          {
+            "name": "spinTimeout",
+            "docs": [
+              "Seconds after which an unfulfilled spin can be refunded.",
+              "",
+              "Appended so deployed states keep their layout: it takes the 8 bytes the first `SPACE` reserved for a fourth",
+              "u64, and reads whatever follows the rates until `update` sets it."
+            ],
+            "type": "i64"
           }
         ]
       }
     },
     {
       "name": "vrfLock",
+      "docs": [
+        "Pending spin of a user, closed on fulfillment or refund. Locks of the spins made before it had fields are empty,",
+        "see `close_legacy_lock`"
+      ],
       "type": {
         "kind": "struct",
-        "fields": []
+        "fields": [
+          {
+            "name": "amount",
+            "type": "u64"
+          },
+          {
+            "name": "slot",
+            "type": "u64"
+          },
+          {
+            "name": "unixTimestamp",
+            "type": "i64"
+          }
+        ]
       }
     }
   ],
@@ -336,6 +489,26 @@ export type LuckySpinner = {
           "index": false
         }
       ]
+    },
+    {
+      "name": "SpinRefunded",
+      "fields": [
+        {
+          "name": "user",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "betAmount",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "requestSlot",
+          "type": "u64",
+          "index": false
+        }
+      ]
     }
   ],
   "errors": [
@@ -378,6 +551,26 @@ export type LuckySpinner = {
       "code": 6007,
       "name": "ViolatedTreasuryConstraint",
       "msg": "Violated treasury constraint"
+    },
+    {
+      "code": 6008,
+      "name": "SpinNotExpired",
+      "msg": "Spin can not be refunded before the timeout"
+    },
+    {
+      "code": 6009,
+      "name": "StaleRequest",
+      "msg": "Fulfillment does not match the pending spin"
+    },
+    {
+      "code": 6010,
+      "name": "InvalidSpinTimeout",
+      "msg": "Spin timeout is shorter than allowed"
+    },
+    {
+      "code": 6011,
+      "name": "NotLegacyLock",
+      "msg": "Lock is not of the previous empty layout"
     }
   ]
 };
@@ -438,6 +631,10 @@ export const IDL: LuckySpinner = {
           "name": "minBetAmount",
           "type": "u64"
         },
+        {
+          "name": "spinTimeout",
+          "type": "i64"
+        },
         {
           "name": "rates",
           "type": {
@@ -478,6 +675,10 @@ export const IDL: LuckySpinner = {
           "name": "minBetAmount",
           "type": "u64"
         },
+        {
+          "name": "spinTimeout",
+          "type": "i64"
+        },
         {
           "name": "rates",
           "type": {
@@ -539,13 +740,23 @@ export const IDL: LuckySpinner = {
           "isMut": true,
           "isSigner": false,
           "docs": [
-            "CHECK"
+            "Only one pending spin per user"
           ]
         },
         {
           "name": "systemProgram",
           "isMut": false,
           "isSigner": false
+        },
+        {
+          "name": "eventAuthority",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "program",
+          "isMut": false,
+          "isSigner": false
         }
       ],
       "args": [
@@ -559,8 +770,8 @@ export const IDL: LuckySpinner = {
       "name": "onVrfFulfilled",
       "accounts": [
         {
-          "name": "owner",
-          "isMut": true,
+          "name": "oracle",
+          "isMut": false,
           "isSigner": true
         },
         {
@@ -613,6 +824,11 @@ export const IDL: LuckySpinner = {
           "docs": [
             "CHECK"
           ]
+        },
+        {
+          "name": "oracleRegistry",
+          "isMut": false,
+          "isSigner": false
         }
       ],
       "args": [
@@ -625,6 +841,109 @@ export const IDL: LuckySpinner = {
         {
           "name": "amount",
           "type": "u64"
+        },
+        {
+          "name": "requestSlot",
+          "type": "u64"
+        }
+      ]
+    },
+    {
+      "name": "refundExpiredSpin",
+      "docs": [
+        "Give the stake back to the user when the spin is still not fulfilled after `spin_timeout` seconds"
+      ],
+      "accounts": [
+        {
+          "name": "user",
+          "isMut": true,
+          "isSigner": true
+        },
+        {
+          "name": "userToken",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "programState",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "pool",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "tokenProgram",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "pdaAuthority",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "vrfLock",
+          "isMut": true,
+          "isSigner": false
+        }
+      ],
+      "args": []
+    },
+    {
+      "name": "closeLegacyLock",
+      "docs": [
+        "Close a lock left by a spin made before `VrfLock` recorded the stake, which can be neither fulfilled nor",
+        "refunded. The stake is not on-chain, the owner refunds the amount of the spin's `RequestVrf` event"
+      ],
+      "accounts": [
+        {
+          "name": "owner",
+          "isMut": false,
+          "isSigner": true
+        },
+        {
+          "name": "programState",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "user",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "userToken",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "pool",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "tokenProgram",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "pdaAuthority",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "vrfLock",
+          "isMut": true,
+          "isSigner": false
+        }
+      ],
+      "args": [
+        {
+          "name": "refund",
+          "type": "u64"
         }
       ]
     }
@@ -673,15 +992,42 @@ export const IDL: LuckySpinner = {
                 ]
               }
             }
+          },
+          {
+            "name": "spinTimeout",
+            "docs": [
+              "Seconds after which an unfulfilled spin can be refunded.",
+              "",
+              "Appended so deployed states keep their layout: it takes the 8 bytes the first `SPACE` reserved for a fourth",
+              "u64, and reads whatever follows the rates until `update` sets it."
+            ],
+            "type": "i64"
           }
         ]
       }
     },
     {
       "name": "vrfLock",
+      "docs": [
+        "Pending spin of a user, closed on fulfillment or refund. Locks of the spins made before it had fields are empty,",
+        "see `close_legacy_lock`"
+      ],
       "type": {
         "kind": "struct",
-        "fields": []
+        "fields": [
+          {
+            "name": "amount",
+            "type": "u64"
+          },
+          {
+            "name": "slot",
+            "type": "u64"
+          },
+          {
+            "name": "unixTimestamp",
+            "type": "i64"
+          }
+        ]
       }
     }
   ],
@@ -720,6 +1066,26 @@ export const IDL: LuckySpinner = {
           "index": false
         }
       ]
+    },
+    {
+      "name": "SpinRefunded",
+      "fields": [
+        {
+          "name": "user",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "betAmount",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "requestSlot",
+          "type": "u64",
+          "index": false
+        }
+      ]
     }
   ],
   "errors": [
@@ -762,6 +1128,26 @@ export const IDL: LuckySpinner = {
       "code": 6007,
       "name": "ViolatedTreasuryConstraint",
       "msg": "Violated treasury constraint"
+    },
+    {
+      "code": 6008,
+      "name": "SpinNotExpired",
+      "msg": "Spin can not be refunded before the timeout"
+    },
+    {
+      "code": 6009,
+      "name": "StaleRequest",
+      "msg": "Fulfillment does not match the pending spin"
+    },
+    {
+      "code": 6010,
+      "name": "InvalidSpinTimeout",
+      "msg": "Spin timeout is shorter than allowed"
+    },
+    {
+      "code": 6011,
+      "name": "NotLegacyLock",
+      "msg": "Lock is not of the previous empty layout"
     }
   ]
 };
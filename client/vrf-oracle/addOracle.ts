@@ -0,0 +1,30 @@
+import { PublicKey } from '@solana/web3.js';
+import { CONFIG, getRegistry, program, provider } from './config';
+
+(async () => {
+	const registry = await getRegistry();
+	const vrfPublicKey = Buffer.from(CONFIG.ORACLE_VRF_PUBLIC_KEY, 'base64');
+	if (vrfPublicKey.length != 33) {
+		console.log('ORACLE_VRF_PUBLIC_KEY must be a base64 compressed secp256k1 point');
+		return;
+	}
+
+	console.log('Executing...');
+	const tx = await program.methods
+		.addOracle(new PublicKey(CONFIG.ORACLE_AUTHORITY), [...vrfPublicKey])
+		.accounts({
+			admin: CONFIG.ADMIN.publicKey,
+			registry,
+		})
+		.signers([CONFIG.ADMIN])
+		.rpc({ commitment: 'confirmed' });
+
+	console.log('Fetching transaction logs...');
+	const trans = await provider.connection.getTransaction(tx, {
+		commitment: 'confirmed',
+	});
+	console.log(trans?.meta?.logMessages);
+
+	const { oracles } = await program.account.oracleRegistry.fetch(registry, 'confirmed');
+	console.log('Oracles:', oracles.map((oracle) => oracle.authority.toBase58()));
+})();
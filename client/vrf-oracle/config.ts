@@ -0,0 +1,43 @@
+import { Program, AnchorProvider, setProvider } from '@project-serum/anchor';
+import NodeWallet from '@project-serum/anchor/dist/cjs/nodewallet';
+import { Keypair, PublicKey, Connection } from '@solana/web3.js';
+import { VrfOracle } from '../vrf_oracle';
+
+const idl = require('../vrf_oracle.json');
+
+//////// CONFIGURATION ////////
+
+const REGISTRY_PDA_SEED = 'vrf-oracle-registry';
+const PROGRAM_ID = '6Usfwwkhv8VDJXWgtpueU39w7J8zUvPUSgLvgYxoX9gM';
+
+// Update manually base on config.json
+export const CONFIG = {
+	URL: 'https://api.devnet.solana.com',
+	ADMIN: Keypair.fromSecretKey(
+		new Uint8Array([
+			111, 118, 107, 173, 240, 168, 69, 73, 10, 9, 142, 105, 124, 62, 45, 115, 251, 251, 178, 118, 181, 234, 217,
+			39, 216, 132, 91, 232, 83, 32, 181, 192, 99, 160, 13, 45, 231, 79, 179, 214, 183, 114, 85, 42, 30, 241, 135,
+			24, 20, 224, 106, 75, 227, 156, 241, 10, 60, 211, 131, 200, 123, 9, 190, 37,
+		])
+	),
+	// Oracle, the `owner` key of the vrf server
+	ORACLE_AUTHORITY: '',
+	// Base64, the `public_key` of the requests returned by the vrf server API
+	ORACLE_VRF_PUBLIC_KEY: '',
+};
+
+//////////////////////////////
+
+const opts = AnchorProvider.defaultOptions();
+export const provider = new AnchorProvider(
+	new Connection(CONFIG.URL, opts.preflightCommitment),
+	new NodeWallet(CONFIG.ADMIN),
+	opts
+);
+
+setProvider(provider);
+
+export const program = new Program(idl, PROGRAM_ID) as Program<VrfOracle>;
+
+export const getRegistry = () =>
+	PublicKey.findProgramAddress([Buffer.from(REGISTRY_PDA_SEED, 'utf-8')], program.programId).then((r) => r[0]);
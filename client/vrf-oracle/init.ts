@@ -0,0 +1,23 @@
+import { SystemProgram } from '@solana/web3.js';
+import { CONFIG, getRegistry, program, provider } from './config';
+
+(async () => {
+	const registry = await getRegistry();
+
+	console.log('Executing...');
+	const tx = await program.methods
+		.init()
+		.accounts({
+			admin: CONFIG.ADMIN.publicKey,
+			registry,
+			systemProgram: SystemProgram.programId,
+		})
+		.signers([CONFIG.ADMIN])
+		.rpc({ commitment: 'confirmed' });
+
+	console.log('Fetching transaction logs...');
+	const trans = await provider.connection.getTransaction(tx, {
+		commitment: 'confirmed',
+	});
+	console.log(trans?.meta?.logMessages);
+})();
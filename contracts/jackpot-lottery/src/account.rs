@@ -4,6 +4,7 @@ use crate::{
 };
 use anchor_lang::prelude::*;
 use anchor_spl::token::{Mint, Token, TokenAccount};
+use vrf_lib::registry::{OracleRegistry, REGISTRY_PDA_SEED};
 
 #[derive(Accounts)]
 pub struct Init<'info> {
@@ -60,7 +61,7 @@ pub struct RollLottery<'info> {
     pub round_result: Account<'info, LotteryRoundResult>,
 
     /// CHECK
-    #[account(init_if_needed, payer = owner, space = 8, seeds = [b"vrf-lock", &round_result.key().to_bytes()[..]], bump)]
+    #[account(init_if_needed, payer = owner, space = 8 + VrfLock::SPACE, seeds = [b"vrf-lock", &round_result.key().to_bytes()[..]], bump)]
     pub vrf_lock: Account<'info, VrfLock>,
 
     pub system_program: Program<'info, System>,
@@ -68,8 +69,7 @@ pub struct RollLottery<'info> {
 
 #[derive(Accounts)]
 pub struct VrfFullfilled<'info> {
-    #[account(mut, address = state.owner @ GameError::InvalidOwner)]
-    pub owner: Signer<'info>,
+    pub oracle: Signer<'info>,
     #[account(mut)]
     pub state: Account<'info, LotteryState>,
     #[account(mut, address = state.round_result @ GameError::ViolatedRoundResultConstraint)]
@@ -78,6 +78,12 @@ pub struct VrfFullfilled<'info> {
     /// CHECK
     #[account(mut, close = owner, seeds = [b"vrf-lock", &round_result.key().to_bytes()[..]], bump)]
     pub vrf_lock: Account<'info, VrfLock>,
+    /// CHECK: receives the rent of vrf_lock
+    #[account(mut, address = state.owner @ GameError::InvalidOwner)]
+    pub owner: AccountInfo<'info>,
+
+    #[account(seeds = [REGISTRY_PDA_SEED], bump, seeds::program = vrf_lib::ID)]
+    pub oracle_registry: Box<Account<'info, OracleRegistry>>,
 }
 
 #[derive(Accounts)]
@@ -28,4 +28,6 @@ pub enum GameError {
     ViolatedTreasuryConstraint,
     #[msg("Violated lottery ticket constraint")]
     ViolatedLotteryTicketConstraint,
+    #[msg("Lottery length must be between 1 and 6 and not exceed the max number")]
+    InvalidLotteryConfig,
 }
@@ -1,19 +1,18 @@
 use crate::{
     account::*,
     error::GameError,
-    random::random,
     state::{GameStage, LotteryRoundResult, LotteryState, LotteryTicket},
 };
 use anchor_lang::prelude::*;
 use anchor_spl::token::{
     self, spl_token::instruction::AuthorityType, Burn, CloseAccount, SetAuthority, Transfer,
 };
+use vrf_lib::registry::OracleRegistry;
 
 declare_id!("Bw2HwtYcTro3nzYt2XFqKA3iu4worp5qkgmrNXVZYEAE");
 
 mod account;
 mod error;
-mod random;
 mod state;
 
 type ProgramResult = Result<()>;
@@ -67,6 +66,10 @@ pub mod jackpot_lottery {
             state.stage == GameStage::WaitNextRound,
             GameError::InvalidStage
         );
+        require!(
+            (1..=6).contains(&lottery_len) && lottery_len <= lottery_max_num,
+            GameError::InvalidLotteryConfig
+        );
 
         *ctx.accounts.next_round_result = LotteryRoundResult {
             pool: ctx.accounts.next_round_pool.key(),
@@ -174,7 +177,7 @@ pub mod jackpot_lottery {
             GameError::InvalidStage
         );
 
-        vrf_lib::request_random(
+        ctx.accounts.vrf_lock.request = vrf_lib::request_random_stored(
             instruction::OnVrfFulfilled {
                 result: vrf_lib::VrfResult::default(),
             },
@@ -182,6 +185,8 @@ pub mod jackpot_lottery {
                 vrf_lib::account_meta(&ctx.accounts.state).mutable(),
                 vrf_lib::account_meta(&ctx.accounts.round_result).mutable(),
                 vrf_lib::account_meta(&ctx.accounts.vrf_lock).mutable(),
+                vrf_lib::account_meta(&ctx.accounts.owner).mutable(),
+                vrf_lib::account_meta(&OracleRegistry::address()),
             ],
         );
 
@@ -192,16 +197,28 @@ pub mod jackpot_lottery {
         ctx: Context<VrfFullfilled>,
         result: vrf_lib::VrfResult,
     ) -> ProgramResult {
+        ctx.accounts.vrf_lock.request.verify(
+            instruction::OnVrfFulfilled {
+                result: vrf_lib::VrfResult::default(),
+            },
+            &ctx.accounts.to_account_metas(None)[1..],
+        )?;
+
         let state = &mut ctx.accounts.state;
         let round = &mut ctx.accounts.round_result;
 
         require!(GameStage::BuyTicket == state.stage, GameError::InvalidStage);
+        ctx.accounts.oracle_registry.verify(
+            &ctx.accounts.oracle.key(),
+            &result,
+            &ctx.accounts.vrf_lock.request.alpha(ctx.program_id),
+        )?;
+
+        let numbers = result
+            .stream(b"jackpot-lottery")
+            .sample_distinct(1..=round.lottery_max_num, round.lottery_len as usize);
         let mut lottery_result = [0; 6];
-        random(
-            1..=round.lottery_max_num,
-            &mut lottery_result[0..round.lottery_len as usize],
-            &result.random,
-        );
+        lottery_result[0..numbers.len()].copy_from_slice(&numbers);
 
         msg!("Lottery result: {:?}", lottery_result);
         round.lottery_result = lottery_result;
@@ -35,8 +35,16 @@ pub struct LotteryTicket {
     pub unix_time_buy: u64,
 }
 
+/// Pending VRF request of a round, closed on fulfillment
 #[account]
-pub struct VrfLock {}
+pub struct VrfLock {
+    pub request: vrf_lib::VrfRequest,
+}
+
+impl VrfLock {
+    /// `on_vrf_fulfilled` only takes the `VrfResult` and 5 accounts
+    pub const SPACE: usize = vrf_lib::VrfRequest::space(vrf_lib::VrfResult::PLACEHOLDER_LEN, 5);
+}
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
 pub enum GameStage {
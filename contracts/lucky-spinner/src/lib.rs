@@ -1,8 +1,9 @@
-use anchor_lang::prelude::*;
+use anchor_lang::{prelude::*, Discriminator};
 use anchor_spl::token::{
     self, spl_token::instruction::AuthorityType, Burn, Mint, SetAuthority, Token, TokenAccount,
     Transfer,
 };
+use vrf_lib::registry::{OracleRegistry, REGISTRY_PDA_SEED};
 
 declare_id!("DEoxdV1CCWvbeGp8PpwkUifmm3pV5AgtFwFaS4P7qZeZ");
 
@@ -12,6 +13,10 @@ const DECIMAL: u64 = 1000000;
 const POOL_OWNER_PDA_SEED: &[u8] = b"lamas_finance";
 const PROGRAM_STATE_PDA_SEED: &[u8] = b"lamas_program_state";
 
+/// Shortest spin timeout in seconds, about the 150 slots a fulfillment transaction stays valid, so a refund does not
+/// race a fulfillment in flight
+const MIN_SPIN_TIMEOUT: i64 = 60;
+
 type ProgramResult = Result<()>;
 
 #[program]
@@ -23,8 +28,14 @@ pub mod lucky_spinner {
         profit_tax_percentage: u64,
         tax_burn_percentage: u64,
         min_bet_amount: u64,
+        spin_timeout: i64,
         rates: Vec<[u64; 2]>,
     ) -> ProgramResult {
+        require!(
+            spin_timeout >= MIN_SPIN_TIMEOUT,
+            GameError::InvalidSpinTimeout
+        );
+
         // Init program state
         **ctx.accounts.program_state = ProgramState {
             owner: ctx.accounts.owner.key(),
@@ -35,6 +46,7 @@ pub mod lucky_spinner {
             tax_burn_percentage,
             min_bet_amount,
             rates,
+            spin_timeout,
         };
 
         // Update pools owner
@@ -61,11 +73,18 @@ pub mod lucky_spinner {
         profit_tax_percentage: u64,
         tax_burn_percentage: u64,
         min_bet_amount: u64,
+        spin_timeout: i64,
         rates: Vec<[u64; 2]>,
     ) -> ProgramResult {
+        require!(
+            spin_timeout >= MIN_SPIN_TIMEOUT,
+            GameError::InvalidSpinTimeout
+        );
+
         ctx.accounts.program_state.profit_tax_percentage = profit_tax_percentage;
         ctx.accounts.program_state.tax_burn_percentage = tax_burn_percentage;
         ctx.accounts.program_state.min_bet_amount = min_bet_amount;
+        ctx.accounts.program_state.spin_timeout = spin_timeout;
         ctx.accounts.program_state.rates = rates;
         Ok(())
     }
@@ -75,6 +94,11 @@ pub mod lucky_spinner {
             amount >= ctx.accounts.program_state.min_bet_amount,
             GameError::BetTooSmall
         );
+        // Usually zero on a state created before the timeout existed, until `update` sets it
+        require!(
+            ctx.accounts.program_state.spin_timeout >= MIN_SPIN_TIMEOUT,
+            GameError::InvalidSpinTimeout
+        );
 
         msg!("Transfering stake to pool");
         token::transfer(
@@ -89,10 +113,19 @@ pub mod lucky_spinner {
             amount,
         )?;
 
-        vrf_lib::request_random(
+        let clock = Clock::get()?;
+        *ctx.accounts.vrf_lock = VrfLock {
+            amount,
+            slot: clock.slot,
+            unix_timestamp: clock.unix_timestamp,
+        };
+
+        // As an event instruction, the logs of a spin can be truncated by those of the token program
+        vrf_lib::request_random_cpi(
             instruction::OnVrfFulfilled {
                 result: vrf_lib::VrfResult::default(),
                 amount,
+                request_slot: clock.slot,
             },
             vec![
                 vrf_lib::account_meta(&ctx.accounts.user).mutable(),
@@ -104,8 +137,11 @@ pub mod lucky_spinner {
                 vrf_lib::account_meta(&ctx.accounts.token_program.key()),
                 vrf_lib::account_meta(&ctx.accounts.pda_authority),
                 vrf_lib::account_meta(&ctx.accounts.vrf_lock).mutable(),
+                vrf_lib::account_meta(&OracleRegistry::address()),
             ],
-        );
+            &ctx.accounts.event_authority,
+            &ctx.accounts.program,
+        )?;
 
         Ok(())
     }
@@ -114,9 +150,31 @@ pub mod lucky_spinner {
         ctx: Context<VrfFulfilled>,
         result: vrf_lib::VrfResult,
         amount: u64,
+        request_slot: u64,
     ) -> Result<()> {
+        let request = vrf_lib::VrfRequest::from_callback(
+            instruction::OnVrfFulfilled {
+                result: vrf_lib::VrfResult::default(),
+                amount,
+                request_slot,
+            },
+            &ctx.accounts.to_account_metas(None)[1..],
+        );
+        ctx.accounts.oracle_registry.verify(
+            &ctx.accounts.oracle.key(),
+            &result,
+            &request.alpha(ctx.program_id),
+        )?;
+
+        // The lock may belong to a newer spin if this request was refunded
+        let vrf_lock = &ctx.accounts.vrf_lock;
+        require!(
+            vrf_lock.amount == amount && vrf_lock.slot == request_slot,
+            GameError::StaleRequest
+        );
+
         let request_trans = result.request_transaction;
-        let multiplier = random_rates(&ctx.accounts.program_state.rates, result);
+        let multiplier = random_rates(&ctx.accounts.program_state.rates, &result);
 
         emit!(SpinResult {
             request_trans,
@@ -193,6 +251,95 @@ pub mod lucky_spinner {
 
         Ok(())
     }
+
+    /// Give the stake back to the user when the spin is still not fulfilled after `spin_timeout` seconds
+    pub fn refund_expired_spin(ctx: Context<RefundExpiredSpin>) -> ProgramResult {
+        let vrf_lock = &ctx.accounts.vrf_lock;
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            now >= vrf_lock
+                .unix_timestamp
+                .saturating_add(ctx.accounts.program_state.spin_timeout),
+            GameError::SpinNotExpired
+        );
+
+        let (_, pda_bump) = Pubkey::find_program_address(&[POOL_OWNER_PDA_SEED], ctx.program_id);
+
+        msg!("Refunding stake to user");
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.pool.to_account_info(),
+                    to: ctx.accounts.user_token.to_account_info(),
+                    authority: ctx.accounts.pda_authority.to_account_info(),
+                },
+            )
+            .with_signer(&[&[&POOL_OWNER_PDA_SEED[..], &[pda_bump]]]),
+            vrf_lock.amount,
+        )?;
+
+        emit!(SpinRefunded {
+            user: ctx.accounts.user.key(),
+            bet_amount: vrf_lock.amount,
+            request_slot: vrf_lock.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Close a lock left by a spin made before `VrfLock` recorded the stake, which can be neither fulfilled nor
+    /// refunded. The stake is not on-chain, the owner refunds the amount of the spin's `RequestVrf` event
+    pub fn close_legacy_lock(ctx: Context<CloseLegacyLock>, refund: u64) -> ProgramResult {
+        let vrf_lock = &ctx.accounts.vrf_lock;
+        require!(
+            vrf_lock.owner == ctx.program_id
+                && vrf_lock.data_len() == 8
+                && vrf_lock.try_borrow_data()?[..] == VrfLock::discriminator(),
+            GameError::NotLegacyLock
+        );
+
+        if refund > 0 {
+            let (_, pda_bump) =
+                Pubkey::find_program_address(&[POOL_OWNER_PDA_SEED], ctx.program_id);
+
+            msg!("Refunding stake to user");
+            token::transfer(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.pool.to_account_info(),
+                        to: ctx.accounts.user_token.to_account_info(),
+                        authority: ctx.accounts.pda_authority.to_account_info(),
+                    },
+                )
+                .with_signer(&[&[&POOL_OWNER_PDA_SEED[..], &[pda_bump]]]),
+                refund,
+            )?;
+        }
+
+        // Same as `close = user`, which needs the lock to deserialize
+        let user = &ctx.accounts.user;
+        **user.lamports.borrow_mut() = user
+            .lamports()
+            .checked_add(vrf_lock.lamports())
+            .ok_or(GameError::IntegerOverflow)?;
+        **vrf_lock.lamports.borrow_mut() = 0;
+        vrf_lock.try_borrow_mut_data()?.fill(0);
+
+        emit!(SpinRefunded {
+            user: user.key(),
+            bet_amount: refund,
+            request_slot: 0,
+        });
+
+        Ok(())
+    }
+
+    /// Event instructions of `spin`, see `vrf_lib::event`
+    pub fn fallback(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> Result<()> {
+        vrf_lib::event::handle_event_instruction(program_id, accounts, data)
+    }
 }
 
 #[event]
@@ -204,6 +351,13 @@ pub struct SpinResult {
     pub decimal: u64,
 }
 
+#[event]
+pub struct SpinRefunded {
+    pub user: Pubkey,
+    pub bet_amount: u64,
+    pub request_slot: u64,
+}
+
 #[error_code]
 pub enum GameError {
     #[msg("Program is not in an expected stage")]
@@ -222,6 +376,14 @@ pub enum GameError {
     ViolatedPoolConstraint,
     #[msg("Violated treasury constraint")]
     ViolatedTreasuryConstraint,
+    #[msg("Spin can not be refunded before the timeout")]
+    SpinNotExpired,
+    #[msg("Fulfillment does not match the pending spin")]
+    StaleRequest,
+    #[msg("Spin timeout is shorter than allowed")]
+    InvalidSpinTimeout,
+    #[msg("Lock is not of the previous empty layout")]
+    NotLegacyLock,
 }
 
 #[account]
@@ -236,18 +398,35 @@ pub struct ProgramState {
     pub min_bet_amount: u64,
 
     pub rates: Vec<[u64; 2]>,
+
+    /// Seconds after which an unfulfilled spin can be refunded.
+    ///
+    /// Appended so deployed states keep their layout: it takes the 8 bytes the first `SPACE` reserved for a fourth
+    /// u64, and reads whatever follows the rates until `update` sets it.
+    pub spin_timeout: i64,
 }
 
 impl ProgramState {
     const SPACE: usize =
         32 * 4 // Pubkey
-        + 8 * 4 // u64
+        + 8 * 3 // u64
         + 4 + (8 + 8) * 16 // Vec - max 16 element
+        + 8 // i64
         ;
 }
 
+/// Pending spin of a user, closed on fulfillment or refund. Locks of the spins made before it had fields are empty,
+/// see `close_legacy_lock`
 #[account]
-pub struct VrfLock {}
+pub struct VrfLock {
+    pub amount: u64,
+    pub slot: u64,
+    pub unix_timestamp: i64,
+}
+
+impl VrfLock {
+    const SPACE: usize = 8 * 3;
+}
 
 #[derive(Accounts)]
 pub struct Initialize<'info> {
@@ -289,17 +468,22 @@ pub struct Spin<'info> {
     #[account(seeds = [POOL_OWNER_PDA_SEED], bump)]
     pub pda_authority: AccountInfo<'info>,
 
-    /// CHECK
-    #[account(init_if_needed, payer = user, space = 8, seeds = [b"vrf-lock", &user.key().to_bytes()[..]], bump)]
+    /// Only one pending spin per user
+    #[account(init, payer = user, space = 8 + VrfLock::SPACE, seeds = [b"vrf-lock", &user.key().to_bytes()[..]], bump)]
     pub vrf_lock: Account<'info, VrfLock>,
 
     pub system_program: Program<'info, System>,
+
+    /// CHECK: signs the `RequestVrf` event instruction, checked by `vrf_lib::event::emit_cpi`
+    pub event_authority: AccountInfo<'info>,
+    /// CHECK: this program, invoked with the event instruction
+    #[account(address = crate::ID)]
+    pub program: AccountInfo<'info>,
 }
 
 #[derive(Accounts)]
 pub struct VrfFulfilled<'info> {
-    #[account(mut, constraint = owner.key() == program_state.owner @ GameError::InvalidOwner)]
-    pub owner: Signer<'info>,
+    pub oracle: Signer<'info>,
     /// CHECK
     #[account(mut)]
     pub user: AccountInfo<'info>,
@@ -324,6 +508,61 @@ pub struct VrfFulfilled<'info> {
     /// CHECK
     #[account(mut, close = user, seeds = [b"vrf-lock", &user.key().to_bytes()[..]], bump)]
     pub vrf_lock: Account<'info, VrfLock>,
+
+    #[account(seeds = [REGISTRY_PDA_SEED], bump, seeds::program = vrf_lib::ID)]
+    pub oracle_registry: Box<Account<'info, OracleRegistry>>,
+}
+
+#[derive(Accounts)]
+pub struct RefundExpiredSpin<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(mut, constraint = user_token.mint == program_state.mint @ GameError::InvalidUserToken)]
+    pub user_token: Account<'info, TokenAccount>,
+
+    #[account(seeds = [PROGRAM_STATE_PDA_SEED], bump)]
+    pub program_state: Box<Account<'info, ProgramState>>,
+
+    #[account(mut, constraint = pool.key() == program_state.pool @ GameError::ViolatedPoolConstraint)]
+    pub pool: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+
+    /// CHECK: checked using PDA
+    #[account(seeds = [POOL_OWNER_PDA_SEED], bump)]
+    pub pda_authority: AccountInfo<'info>,
+
+    #[account(mut, close = user, seeds = [b"vrf-lock", &user.key().to_bytes()[..]], bump)]
+    pub vrf_lock: Account<'info, VrfLock>,
+}
+
+#[derive(Accounts)]
+pub struct CloseLegacyLock<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(seeds = [PROGRAM_STATE_PDA_SEED], bump, has_one = owner @ GameError::InvalidOwner)]
+    pub program_state: Box<Account<'info, ProgramState>>,
+
+    /// CHECK: receives the rent of the lock, which is derived from it
+    #[account(mut)]
+    pub user: AccountInfo<'info>,
+    #[account(
+        mut,
+        constraint = user_token.mint == program_state.mint @ GameError::InvalidUserToken,
+        constraint = user_token.owner == user.key() @ GameError::InvalidUserToken
+    )]
+    pub user_token: Account<'info, TokenAccount>,
+
+    #[account(mut, constraint = pool.key() == program_state.pool @ GameError::ViolatedPoolConstraint)]
+    pub pool: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+
+    /// CHECK: checked using PDA
+    #[account(seeds = [POOL_OWNER_PDA_SEED], bump)]
+    pub pda_authority: AccountInfo<'info>,
+
+    /// CHECK: a lock of the previous layout, checked in the instruction
+    #[account(mut, seeds = [b"vrf-lock", &user.key().to_bytes()[..]], bump)]
+    pub vrf_lock: AccountInfo<'info>,
 }
 
 #[derive(Accounts)]
@@ -331,13 +570,18 @@ pub struct Update<'info> {
     #[account(mut)]
     pub owner: Signer<'info>,
 
-    #[account(mut, seeds = [PROGRAM_STATE_PDA_SEED], bump)]
+    #[account(mut, seeds = [PROGRAM_STATE_PDA_SEED], bump, has_one = owner @ GameError::InvalidOwner)]
     pub program_state: Box<Account<'info, ProgramState>>,
 }
 
-fn random_rates(rates: &[[u64; 2]], random: vrf_lib::VrfResult) -> u64 {
+fn random_rates(rates: &[[u64; 2]], random: &vrf_lib::VrfResult) -> u64 {
     let sum: u64 = rates.iter().map(|v| v[0]).sum();
-    let mut sum = random.bound(0..=sum);
+    if sum == 0 {
+        return 0;
+    }
+
+    // Each rate is picked with probability weight / sum
+    let mut sum = random.bound(0..=sum - 1);
 
     for [weight, multiplier] in rates.iter().copied() {
         if sum < weight {
@@ -0,0 +1,95 @@
+use anchor_lang::prelude::*;
+use vrf_lib::registry::{Oracle, OracleRegistry, REGISTRY_PDA_SEED};
+
+// The registry account type lives in vrf-lib so the games can read it,
+// the program id is declared there too to keep a single source of truth
+pub use vrf_lib::{id, ID};
+
+type ProgramResult = Result<()>;
+
+#[program]
+pub mod vrf_oracle {
+    use super::*;
+
+    pub fn init(ctx: Context<Initialize>) -> ProgramResult {
+        **ctx.accounts.registry = OracleRegistry {
+            admin: ctx.accounts.admin.key(),
+            oracles: Vec::new(),
+        };
+
+        Ok(())
+    }
+
+    pub fn add_oracle(
+        ctx: Context<UpdateRegistry>,
+        authority: Pubkey,
+        vrf_public_key: vrf_lib::VrfPublicKey,
+    ) -> ProgramResult {
+        let registry = &mut ctx.accounts.registry;
+        require!(
+            registry.oracle(&authority).is_none(),
+            RegistryError::OracleAlreadyRegistered
+        );
+        require!(
+            registry.oracles.len() < OracleRegistry::MAX_ORACLES,
+            RegistryError::RegistryFull
+        );
+
+        msg!("Adding oracle {}", authority);
+        registry.oracles.push(Oracle {
+            authority,
+            vrf_public_key,
+        });
+
+        Ok(())
+    }
+
+    pub fn remove_oracle(ctx: Context<UpdateRegistry>, authority: Pubkey) -> ProgramResult {
+        let registry = &mut ctx.accounts.registry;
+        let len = registry.oracles.len();
+        registry
+            .oracles
+            .retain(|oracle| oracle.authority != authority);
+        require!(registry.oracles.len() < len, RegistryError::OracleNotFound);
+
+        msg!("Removed oracle {}", authority);
+        Ok(())
+    }
+
+    pub fn set_admin(ctx: Context<UpdateRegistry>, admin: Pubkey) -> ProgramResult {
+        ctx.accounts.registry.admin = admin;
+        Ok(())
+    }
+}
+
+#[error_code]
+pub enum RegistryError {
+    #[msg("Invalid Admin")]
+    InvalidAdmin,
+    #[msg("Oracle registry is full")]
+    RegistryFull,
+    #[msg("Oracle is already registered")]
+    OracleAlreadyRegistered,
+    #[msg("Oracle not found")]
+    OracleNotFound,
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(init, payer = admin, space = 8 + OracleRegistry::SPACE, seeds = [REGISTRY_PDA_SEED], bump)]
+    pub registry: Box<Account<'info, OracleRegistry>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateRegistry<'info> {
+    #[account(constraint = admin.key() == registry.admin @ RegistryError::InvalidAdmin)]
+    pub admin: Signer<'info>,
+
+    #[account(mut, seeds = [REGISTRY_PDA_SEED], bump)]
+    pub registry: Box<Account<'info, OracleRegistry>>,
+}
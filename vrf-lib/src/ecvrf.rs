@@ -0,0 +1,322 @@
+//! ECVRF-SECP256K1-SHA256-TAI verification, compatible with the proofs produced by the
+//! `vrf` crate used in `vrf-server`.
+//!
+//! Scalar multiplication is far too expensive to do in BPF, so it is delegated to the
+//! `secp256k1_recover` syscall: recovering the "signature" `(r, s)` over the hash `z`
+//! where `r` is the x coordinate of a point `R` returns `r⁻¹·(s·R - z·G)`, which lets us
+//! compute any `a·R + b·G` by picking `s = a·r` and `z = -b·r`.
+
+use anchor_lang::{
+    prelude::borsh,
+    solana_program::{hash::hashv, secp256k1_recover::secp256k1_recover},
+    AnchorDeserialize, AnchorSerialize,
+};
+use libsecp256k1_core::curve::{Affine, Field, Jacobian, Scalar};
+
+use crate::error::VrfError;
+
+const SUITE_STRING: u8 = 0xFE;
+
+/// SEC1 compressed secp256k1 point, borsh encoded as its 33 raw bytes
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CompressedPoint {
+    pub tag: u8,
+    pub x: [u8; 32],
+}
+
+pub type VrfPublicKey = CompressedPoint;
+
+impl CompressedPoint {
+    pub const BYTE_LEN: usize = 33;
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != Self::BYTE_LEN {
+            return None;
+        }
+
+        Some(Self {
+            tag: bytes[0],
+            x: bytes[1..].try_into().unwrap(),
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::BYTE_LEN] {
+        let mut bytes = [0u8; Self::BYTE_LEN];
+        bytes[0] = self.tag;
+        bytes[1..].copy_from_slice(&self.x);
+        bytes
+    }
+
+    fn from_affine(point: &Affine) -> Self {
+        let mut x = point.x;
+        let mut y = point.y;
+        x.normalize();
+        y.normalize();
+
+        Self {
+            tag: if y.is_odd() { 0x03 } else { 0x02 },
+            x: x.b32(),
+        }
+    }
+
+    fn decompress(&self) -> Option<Affine> {
+        let odd = match self.tag {
+            0x02 => false,
+            0x03 => true,
+            _ => return None,
+        };
+
+        let mut x = Field::default();
+        let mut point = Affine::default();
+        if x.set_b32(&self.x) && point.set_xo_var(&x, odd) {
+            Some(point)
+        } else {
+            None
+        }
+    }
+}
+
+/// `Gamma || c || s`, borsh encoded as the 81 byte proof string
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct VrfProof {
+    pub gamma: CompressedPoint,
+    pub c: [u8; 16],
+    pub s: [u8; 32],
+}
+
+impl VrfProof {
+    pub const BYTE_LEN: usize = CompressedPoint::BYTE_LEN + 16 + 32;
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != Self::BYTE_LEN {
+            return None;
+        }
+
+        Some(Self {
+            gamma: CompressedPoint::from_bytes(&bytes[..33])?,
+            c: bytes[33..49].try_into().unwrap(),
+            s: bytes[49..].try_into().unwrap(),
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::BYTE_LEN] {
+        let mut bytes = [0u8; Self::BYTE_LEN];
+        bytes[..33].copy_from_slice(&self.gamma.to_bytes());
+        bytes[33..49].copy_from_slice(&self.c);
+        bytes[49..].copy_from_slice(&self.s);
+        bytes
+    }
+
+    /// VRF output (beta) of this proof, does not check that the proof is valid
+    pub fn to_hash(&self) -> [u8; 32] {
+        hashv(&[&[SUITE_STRING, 0x03], &self.gamma.to_bytes()]).to_bytes()
+    }
+}
+
+/// Verify `proof` of `alpha` under `public_key`, returning the VRF output on success
+pub fn verify(public_key: &VrfPublicKey, proof: &VrfProof, alpha: &[u8]) -> Result<[u8; 32], VrfError> {
+    let y = public_key.decompress().ok_or(VrfError::InvalidPublicKey)?;
+    let gamma = proof.gamma.decompress().ok_or(VrfError::MalformedProof)?;
+    let c = {
+        let mut bytes = [0u8; 32];
+        bytes[16..].copy_from_slice(&proof.c);
+        scalar_from_bytes(&bytes)?
+    };
+    let s = scalar_from_bytes(&proof.s)?;
+
+    let h = hash_to_curve(public_key, alpha)?;
+
+    // U = s*B - c*Y
+    let u = linear_combination(&y, &-c, &s)?;
+    // V = s*H - c*Gamma
+    let v = {
+        let s_h = linear_combination(&h, &s, &Scalar::from_int(0))?;
+        let c_gamma = linear_combination(&gamma, &c, &Scalar::from_int(0))?;
+        let v = Jacobian::from_ge(&s_h).add_ge_var(&c_gamma.neg(), None);
+        if v.is_infinity() {
+            return Err(VrfError::InvalidProof);
+        }
+
+        Affine::from_gej(&v)
+    };
+
+    let derived_c = hash_points(&[
+        CompressedPoint::from_affine(&h),
+        proof.gamma,
+        CompressedPoint::from_affine(&u),
+        CompressedPoint::from_affine(&v),
+    ]);
+    if derived_c != proof.c {
+        return Err(VrfError::InvalidProof);
+    }
+
+    Ok(proof.to_hash())
+}
+
+fn scalar_from_bytes(bytes: &[u8; 32]) -> Result<Scalar, VrfError> {
+    let mut scalar = Scalar::default();
+    let overflow: bool = scalar.set_b32(bytes).into();
+    if overflow {
+        return Err(VrfError::MalformedProof);
+    }
+
+    Ok(scalar)
+}
+
+/// `a*point + b*G` via `secp256k1_recover`
+fn linear_combination(point: &Affine, a: &Scalar, b: &Scalar) -> Result<Affine, VrfError> {
+    let mut x = point.x;
+    let mut y = point.y;
+    x.normalize();
+    y.normalize();
+
+    // The recovery id encodes the parity of y and whether r overflowed the curve order
+    let mut r = Scalar::default();
+    let overflow: bool = r.set_b32(&x.b32()).into();
+    let recovery_id = y.is_odd() as u8 | (overflow as u8) << 1;
+
+    let s = a * &r;
+    let z = -(b * &r);
+
+    let mut signature = [0u8; 64];
+    signature[..32].copy_from_slice(&r.b32());
+    signature[32..].copy_from_slice(&s.b32());
+
+    let recovered = secp256k1_recover(&z.b32(), recovery_id, &signature).map_err(|_| VrfError::InvalidProof)?;
+    let recovered = recovered.to_bytes();
+
+    let mut x = Field::default();
+    let mut y = Field::default();
+    if !x.set_b32(recovered[..32].try_into().unwrap()) || !y.set_b32(recovered[32..].try_into().unwrap()) {
+        return Err(VrfError::InvalidProof);
+    }
+
+    let mut result = Affine::default();
+    result.set_xy(&x, &y);
+    Ok(result)
+}
+
+/// ECVRF_hash_to_curve_try_and_increment
+fn hash_to_curve(public_key: &VrfPublicKey, alpha: &[u8]) -> Result<Affine, VrfError> {
+    let public_key = public_key.to_bytes();
+
+    for ctr in 0..=u8::MAX {
+        let hash = hashv(&[&[SUITE_STRING, 0x01], &public_key, alpha, &[ctr]]).to_bytes();
+
+        let mut x = Field::default();
+        let mut point = Affine::default();
+        if x.set_b32(&hash) && point.set_xo_var(&x, false) {
+            return Ok(point);
+        }
+    }
+
+    Err(VrfError::HashToCurveFailed)
+}
+
+/// ECVRF_hash_points, truncated to the 16 byte challenge length of the suite
+fn hash_points(points: &[CompressedPoint]) -> [u8; 16] {
+    let points = points.iter().map(CompressedPoint::to_bytes).collect::<Vec<_>>();
+    let mut data: Vec<&[u8]> = vec![&[SUITE_STRING, 0x02]];
+    data.extend(points.iter().map(|point| &point[..]));
+
+    let hash = hashv(&data).to_bytes();
+    hash[..16].try_into().unwrap()
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use libsecp256k1_core::curve::{ECMultContext, ECMultGenContext};
+
+    use super::*;
+
+    /// Reference prover, only used to produce proofs for the tests
+    pub fn prove(secret: &[u8; 32], alpha: &[u8]) -> (VrfPublicKey, VrfProof) {
+        let gen_ctx = ECMultGenContext::new_boxed();
+        let ctx = ECMultContext::new_boxed();
+        let zero = Scalar::from_int(0);
+
+        let mut x = Scalar::default();
+        let _ = x.set_b32(secret);
+
+        let mul = |point: &Affine, scalar: &Scalar| {
+            let mut r = Jacobian::default();
+            ctx.ecmult(&mut r, &Jacobian::from_ge(point), scalar, &zero);
+            Affine::from_gej(&r)
+        };
+        let mul_gen = |scalar: &Scalar| {
+            let mut r = Jacobian::default();
+            gen_ctx.ecmult_gen(&mut r, scalar);
+            Affine::from_gej(&r)
+        };
+
+        let public_key = CompressedPoint::from_affine(&mul_gen(&x));
+        let h = hash_to_curve(&public_key, alpha).unwrap();
+        let gamma = mul(&h, &x);
+
+        let mut k = Scalar::default();
+        let _ = k.set_b32(&hashv(&[secret, alpha]).to_bytes());
+
+        let c = hash_points(&[
+            CompressedPoint::from_affine(&h),
+            CompressedPoint::from_affine(&gamma),
+            CompressedPoint::from_affine(&mul_gen(&k)),
+            CompressedPoint::from_affine(&mul(&h, &k)),
+        ]);
+
+        let mut c_scalar = Scalar::default();
+        let mut c_bytes = [0u8; 32];
+        c_bytes[16..].copy_from_slice(&c);
+        let _ = c_scalar.set_b32(&c_bytes);
+        let s = k + c_scalar * x;
+
+        (
+            public_key,
+            VrfProof {
+                gamma: CompressedPoint::from_affine(&gamma),
+                c,
+                s: s.b32(),
+            },
+        )
+    }
+
+    #[test]
+    fn test_verify() {
+        let secret = hashv(&[b"secret"]).to_bytes();
+        for alpha in [&b""[..], b"sample", &[7; 96]] {
+            let (public_key, proof) = prove(&secret, alpha);
+            assert_eq!(verify(&public_key, &proof, alpha).unwrap(), proof.to_hash());
+            assert!(verify(&public_key, &proof, b"other alpha").is_err());
+        }
+    }
+
+    #[test]
+    fn test_verify_tampered_proof() {
+        let secret = hashv(&[b"secret"]).to_bytes();
+        let (public_key, proof) = prove(&secret, b"sample");
+
+        let mut tampered = proof;
+        tampered.s[31] ^= 1;
+        assert!(verify(&public_key, &tampered, b"sample").is_err());
+
+        let mut tampered = proof;
+        tampered.c[0] ^= 1;
+        assert!(verify(&public_key, &tampered, b"sample").is_err());
+
+        let mut tampered = proof;
+        tampered.gamma.tag ^= 1;
+        assert!(verify(&public_key, &tampered, b"sample").is_err());
+
+        let (other_key, _) = prove(&hashv(&[b"other"]).to_bytes(), b"sample");
+        assert!(verify(&other_key, &proof, b"sample").is_err());
+    }
+
+    #[test]
+    fn test_proof_bytes() {
+        let (public_key, proof) = prove(&hashv(&[b"secret"]).to_bytes(), b"sample");
+
+        assert_eq!(VrfProof::from_bytes(&proof.to_bytes()), Some(proof));
+        assert_eq!(proof.try_to_vec().unwrap(), proof.to_bytes());
+        assert_eq!(public_key.try_to_vec().unwrap(), public_key.to_bytes());
+        assert_eq!(VrfProof::from_bytes(&[0; 80]), None);
+    }
+}
@@ -0,0 +1,22 @@
+use anchor_lang::prelude::*;
+
+// Offset past the 6000 range used by the game programs so the codes never collide
+#[error_code(offset = 7000)]
+pub enum VrfError {
+    #[msg("Invalid VRF public key")]
+    InvalidPublicKey,
+    #[msg("Malformed VRF proof")]
+    MalformedProof,
+    #[msg("Can not hash VRF input to a curve point")]
+    HashToCurveFailed,
+    #[msg("VRF proof verification failed")]
+    InvalidProof,
+    #[msg("Random value does not match the VRF proof output")]
+    RandomMismatch,
+    #[msg("Fulfillment signer is not a registered oracle")]
+    UnknownOracle,
+    #[msg("Fulfillment does not match the stored VRF request")]
+    RequestMismatch,
+    #[msg("Event instruction not signed by the event authority of the program")]
+    InvalidEventAuthority,
+}
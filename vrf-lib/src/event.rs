@@ -0,0 +1,125 @@
+//! `RequestVrf` emitted as the data of an instruction the program invokes on itself. The server reads it from the inner
+//! instructions of the transaction, where it can neither be truncated like the logs of `emit!` nor be mistaken for other
+//! log lines. The layout is the one of `emit_cpi!` in later Anchor versions.
+//!
+//! The requesting instruction takes the event authority (`event_authority(program_id)`) and the program itself as
+//! accounts, and the program routes the event instruction to `handle_event_instruction` from its fallback:
+//!
+//! ```ignore
+//! pub fn fallback(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> Result<()> {
+//!     vrf_lib::event::handle_event_instruction(program_id, accounts, data)
+//! }
+//! ```
+
+use anchor_lang::{
+    prelude::*,
+    solana_program::{instruction::Instruction, program::invoke_signed},
+    Event,
+};
+
+use crate::VrfError;
+
+/// `sha256("anchor:event")[..8]` read as big endian, serialized little endian as the first bytes of an event instruction,
+/// like Anchor does
+pub const EVENT_IX_TAG: u64 = 0x1d9a_cb51_2ea5_45e4;
+pub const EVENT_AUTHORITY_SEED: &[u8] = b"__event_authority";
+
+/// PDA signing the event instructions of `program_id`, so that no other program can forge them
+pub fn event_authority(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[EVENT_AUTHORITY_SEED], program_id)
+}
+
+/// `EVENT_IX_TAG` followed by the event discriminator and data
+pub fn instruction_data(event: &impl Event) -> Vec<u8> {
+    [&EVENT_IX_TAG.to_le_bytes()[..], &event.data()].concat()
+}
+
+/// Emit `event` by invoking `program` with it, signed by `event_authority`
+// The anchor `Result` of the calling instruction, boxing its error would only move the allocation there
+#[allow(clippy::result_large_err)]
+pub fn emit_cpi<'info>(event: &impl Event, event_authority_info: &AccountInfo<'info>, program: &AccountInfo<'info>) -> Result<()> {
+    let (address, bump) = event_authority(program.key);
+    if *event_authority_info.key != address {
+        return err!(VrfError::InvalidEventAuthority);
+    }
+
+    let instruction = Instruction {
+        program_id: *program.key,
+        accounts: vec![AccountMeta::new_readonly(address, true)],
+        data: instruction_data(event),
+    };
+    invoke_signed(
+        &instruction,
+        &[event_authority_info.clone(), program.clone()],
+        &[&[EVENT_AUTHORITY_SEED, &[bump]]],
+    )?;
+
+    Ok(())
+}
+
+/// Accept the event instructions of the program, only the program can sign them
+// Returned as is from the program's `#[fallback]`, which anchor expects to return its `Result`
+#[allow(clippy::result_large_err)]
+pub fn handle_event_instruction(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> Result<()> {
+    if !data.starts_with(&EVENT_IX_TAG.to_le_bytes()) {
+        return Err(ErrorCode::InstructionFallbackNotFound.into());
+    }
+
+    let authority = accounts.first().ok_or(ErrorCode::AccountNotEnoughKeys)?;
+    if !authority.is_signer || *authority.key != event_authority(program_id).0 {
+        return err!(VrfError::InvalidEventAuthority);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use anchor_lang::{solana_program::hash::hash, Discriminator};
+
+    use super::*;
+    use crate::RequestVrf;
+
+    #[test]
+    fn test_event_instruction() {
+        assert_eq!(EVENT_IX_TAG.to_be_bytes(), hash(b"anchor:event").to_bytes()[..8]);
+
+        let event = RequestVrf {
+            ix_sighash: [1; 8],
+            ix_data: vec![2, 3],
+            accounts: vec![],
+        };
+        let data = instruction_data(&event);
+        assert_eq!(data[8..16], RequestVrf::discriminator());
+        assert_eq!(RequestVrf::try_from_slice(&data[16..]).unwrap().ix_data, vec![2, 3]);
+
+        let program_id = Pubkey::new_unique();
+        let (address, _) = event_authority(&program_id);
+        let owner = Pubkey::default();
+        let (mut lamports, mut account_data) = (0, vec![]);
+        let signer = AccountInfo::new(&address, true, false, &mut lamports, &mut account_data, &owner, false, 0);
+        assert!(handle_event_instruction(&program_id, std::slice::from_ref(&signer), &data).is_ok());
+        assert!(handle_event_instruction(&program_id, &[], &data).is_err());
+        assert!(handle_event_instruction(&Pubkey::new_unique(), std::slice::from_ref(&signer), &data).is_err());
+        assert_eq!(
+            error_code(handle_event_instruction(&program_id, std::slice::from_ref(&signer), &data[8..])),
+            u32::from(ErrorCode::InstructionFallbackNotFound)
+        );
+
+        let not_signer = AccountInfo {
+            is_signer: false,
+            ..signer
+        };
+        assert_eq!(
+            error_code(handle_event_instruction(&program_id, &[not_signer], &data)),
+            u32::from(VrfError::InvalidEventAuthority)
+        );
+    }
+
+    fn error_code(result: Result<()>) -> u32 {
+        match result {
+            Err(Error::AnchorError(error)) => error.error_code_number,
+            result => panic!("{result:?}"),
+        }
+    }
+}
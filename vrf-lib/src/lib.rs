@@ -3,6 +3,22 @@ use std::ops::RangeInclusive;
 use anchor_lang::{prelude::*, InstructionData};
 use num_traits::{AsPrimitive, PrimInt};
 
+pub mod ecvrf;
+mod error;
+pub mod event;
+pub mod registry;
+mod request;
+mod sample;
+mod stream;
+
+pub use ecvrf::{VrfProof, VrfPublicKey};
+pub use error::VrfError;
+pub use request::VrfRequest;
+pub use stream::RandomStream;
+
+// Program id of `vrf-oracle`, owner of the `OracleRegistry` account
+declare_id!("6Usfwwkhv8VDJXWgtpueU39w7J8zUvPUSgLvgYxoX9gM");
+
 #[event]
 pub struct RequestVrf {
     pub ix_sighash: [u8; 8],
@@ -10,6 +26,13 @@ pub struct RequestVrf {
     pub accounts: Vec<AccountMetaRef>,
 }
 
+impl RequestVrf {
+    /// VRF input of this request made by `program_id`, see `VrfRequest::alpha`
+    pub fn alpha(&self, program_id: &Pubkey) -> [u8; 32] {
+        request::alpha(program_id, &self.ix_sighash, &self.ix_data, &self.accounts)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, AnchorSerialize, AnchorDeserialize)]
 pub struct AccountMetaRef {
     pub pubkey: Pubkey,
@@ -31,19 +54,33 @@ pub fn account_meta(pubkey: &impl anchor_lang::Key) -> AccountMetaRef {
 }
 
 pub fn request_random<T: InstructionData>(ix: T, accounts: Vec<AccountMetaRef>) {
-    let data = ix.data();
+    VrfRequest::new(ix, accounts).emit();
+}
+
+/// Like `request_random`, emitting the request with an event instruction, see the `event` module
+// Anchor's `Result`, propagated with `?` by the requesting instruction
+#[allow(clippy::result_large_err)]
+pub fn request_random_cpi<'info, T: InstructionData>(
+    ix: T,
+    accounts: Vec<AccountMetaRef>,
+    event_authority: &AccountInfo<'info>,
+    program: &AccountInfo<'info>,
+) -> Result<()> {
+    VrfRequest::new(ix, accounts).emit_cpi(event_authority, program)
+}
 
-    emit!(RequestVrf {
-        ix_sighash: data[0..8].try_into().unwrap(),
-        ix_data: data[8..].to_vec(),
-        accounts
-    });
+/// Like `request_random`, also returns the request so the program can store it and check the callback against it
+pub fn request_random_stored<T: InstructionData>(ix: T, accounts: Vec<AccountMetaRef>) -> VrfRequest {
+    let request = VrfRequest::new(ix, accounts);
+    request.emit();
+    request
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize)]
 pub struct VrfResult {
     pub random: [u8; VrfResult::RANDOM_BYTE_LEN],
     pub request_transaction: [u8; VrfResult::SIGNATURE_BYTE_LEN],
+    pub proof: VrfProof,
 }
 
 impl Default for VrfResult {
@@ -51,6 +88,7 @@ impl Default for VrfResult {
         Self {
             random: [0u8; VrfResult::RANDOM_BYTE_LEN],
             request_transaction: [0u8; VrfResult::SIGNATURE_BYTE_LEN],
+            proof: VrfProof::default(),
         }
     }
 }
@@ -58,23 +96,41 @@ impl Default for VrfResult {
 impl VrfResult {
     pub const RANDOM_BYTE_LEN: usize = 16;
     pub const SIGNATURE_BYTE_LEN: usize = 64;
+    /// Serialized size of `VrfResult::default()`, the placeholder in the requested callback data
+    pub const PLACEHOLDER_LEN: usize = Self::RANDOM_BYTE_LEN + Self::SIGNATURE_BYTE_LEN + VrfProof::BYTE_LEN;
 
     #[allow(unused)]
     fn new(random: [u8; VrfResult::RANDOM_BYTE_LEN]) -> Self {
         Self {
             random,
-            request_transaction: [0u8; VrfResult::SIGNATURE_BYTE_LEN],
+            ..Default::default()
+        }
+    }
+
+    /// Check that `random` is the output of a valid proof by `public_key` of `alpha`, the VRF input of the request
+    /// rebuilt by the program (see `VrfRequest::alpha`)
+    pub fn verify(&self, public_key: &VrfPublicKey, alpha: &[u8]) -> std::result::Result<(), VrfError> {
+        let hash = ecvrf::verify(public_key, &self.proof, alpha)?;
+
+        if hash[..VrfResult::RANDOM_BYTE_LEN] != self.random {
+            return Err(VrfError::RandomMismatch);
         }
+
+        Ok(())
     }
 
-    pub fn bound<T>(self, range: RangeInclusive<T>) -> T
+    /// Uniformly distributed value in `range`, both ends included. See the `sample` module for the exact construction
+    pub fn bound<T>(&self, range: RangeInclusive<T>) -> T
     where
-        T: PrimInt + AsPrimitive<i128>,
-        i128: AsPrimitive<T>,
+        T: PrimInt + AsPrimitive<u128>,
+        u128: AsPrimitive<T>,
     {
-        let v = i128::from_be_bytes(self.random);
-        let bound: i128 = (*range.end() - *range.start()).as_();
-        ((v % bound) + range.start().as_()).as_()
+        sample::uniform_in(&self.random, range)
+    }
+
+    /// Stream of random values for games that need more than one, `domain` separates the streams of different uses
+    pub fn stream(&self, domain: &[u8]) -> RandomStream {
+        RandomStream::new(&self.random, domain)
     }
 }
 
@@ -100,9 +156,37 @@ mod test {
         println!("{:?}\n{:?}", &v[0..32], &v[32..]);
     }
 
+    #[test]
+    fn test_placeholder_len() {
+        assert_eq!(VrfResult::default().try_to_vec().unwrap().len(), VrfResult::PLACEHOLDER_LEN);
+    }
+
     #[test]
     fn r1() {
         let r = VrfResult::new([111, 118, 107, 173, 240, 168, 69, 73, 10, 9, 142, 105, 124, 62, 45, 22]);
         println!("{}", r.bound(0u64..=u32::MAX as u64));
     }
+
+    #[test]
+    fn test_verify() {
+        let alpha = [5; 32];
+        let (public_key, proof) = ecvrf::test::prove(&[9; 32], &alpha);
+
+        let mut result = VrfResult {
+            random: proof.to_hash()[..VrfResult::RANDOM_BYTE_LEN].try_into().unwrap(),
+            request_transaction: [3; VrfResult::SIGNATURE_BYTE_LEN],
+            proof,
+        };
+        assert!(result.verify(&public_key, &alpha).is_ok());
+
+        // Not part of the VRF input
+        result.request_transaction[0] ^= 1;
+        assert!(result.verify(&public_key, &alpha).is_ok());
+
+        result.random[0] ^= 1;
+        assert!(result.verify(&public_key, &alpha).is_err());
+        result.random[0] ^= 1;
+
+        assert!(result.verify(&public_key, &[6; 32]).is_err());
+    }
 }
@@ -0,0 +1,86 @@
+use anchor_lang::prelude::*;
+
+use crate::{VrfError, VrfPublicKey, VrfResult};
+
+pub const REGISTRY_PDA_SEED: &[u8] = b"vrf-oracle-registry";
+
+/// Oracles allowed to fulfill VRF requests, owned by the `vrf-oracle` program
+#[account]
+pub struct OracleRegistry {
+    pub admin: Pubkey,
+    pub oracles: Vec<Oracle>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Oracle {
+    /// Key signing the fulfillment transactions
+    pub authority: Pubkey,
+    pub vrf_public_key: VrfPublicKey,
+}
+
+impl OracleRegistry {
+    pub const MAX_ORACLES: usize = 16;
+    pub const SPACE: usize =
+        32 // Pubkey
+        + 4 + (32 + 33) * Self::MAX_ORACLES // Vec - max 16 element
+        ;
+
+    pub fn address() -> Pubkey {
+        Pubkey::find_program_address(&[REGISTRY_PDA_SEED], &crate::ID).0
+    }
+
+    pub fn oracle(&self, authority: &Pubkey) -> Option<&Oracle> {
+        self.oracles.iter().find(|oracle| oracle.authority == *authority)
+    }
+
+    /// Check that `authority` is a registered oracle and `result` was proven for `alpha` with its VRF key
+    pub fn verify(&self, authority: &Pubkey, result: &VrfResult, alpha: &[u8]) -> std::result::Result<(), VrfError> {
+        let oracle = self.oracle(authority).ok_or(VrfError::UnknownOracle)?;
+        result.verify(&oracle.vrf_public_key, alpha)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ecvrf;
+
+    #[test]
+    fn test_verify() {
+        let alpha = [5; 32];
+        let (public_key, proof) = ecvrf::test::prove(&[1; 32], &alpha);
+        let (other_public_key, _) = ecvrf::test::prove(&[2; 32], &alpha);
+
+        let result = VrfResult {
+            random: proof.to_hash()[..VrfResult::RANDOM_BYTE_LEN].try_into().unwrap(),
+            request_transaction: [3; VrfResult::SIGNATURE_BYTE_LEN],
+            proof,
+        };
+
+        let authority = Pubkey::new_unique();
+        let other_authority = Pubkey::new_unique();
+        let registry = OracleRegistry {
+            admin: Pubkey::new_unique(),
+            oracles: vec![
+                Oracle {
+                    authority: other_authority,
+                    vrf_public_key: other_public_key,
+                },
+                Oracle {
+                    authority,
+                    vrf_public_key: public_key,
+                },
+            ],
+        };
+
+        assert!(registry.verify(&authority, &result, &alpha).is_ok());
+        assert!(matches!(
+            registry.verify(&other_authority, &result, &alpha),
+            Err(VrfError::InvalidProof)
+        ));
+        assert!(matches!(
+            registry.verify(&Pubkey::new_unique(), &result, &alpha),
+            Err(VrfError::UnknownOracle)
+        ));
+    }
+}
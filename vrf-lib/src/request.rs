@@ -0,0 +1,168 @@
+use anchor_lang::{prelude::*, solana_program::hash::hashv, InstructionData};
+
+use crate::{AccountMetaRef, RequestVrf, VrfError};
+
+/// Callback of a pending VRF request, for programs that keep it on-chain (usually in their vrf lock PDA) instead of
+/// trusting the server to replay the `RequestVrf` event faithfully
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct VrfRequest {
+    pub ix_sighash: [u8; 8],
+    /// Callback arguments with a default `VrfResult` placeholder
+    pub ix_data: Vec<u8>,
+    /// Callback accounts, without the oracle signer
+    pub accounts: Vec<AccountMetaRef>,
+}
+
+impl VrfRequest {
+    pub fn new<T: InstructionData>(ix: T, accounts: Vec<AccountMetaRef>) -> Self {
+        let data = ix.data();
+
+        Self {
+            ix_sighash: data[0..8].try_into().unwrap(),
+            ix_data: data[8..].to_vec(),
+            accounts,
+        }
+    }
+
+    /// The request answered by the callback being executed, for programs that rebuild it from the handler arguments
+    /// instead of storing it. `ix` and `accounts` are as in `verify`
+    pub fn from_callback<T: InstructionData>(ix: T, accounts: &[AccountMeta]) -> Self {
+        let accounts = accounts
+            .iter()
+            .map(|meta| AccountMetaRef {
+                pubkey: meta.pubkey,
+                is_writable: meta.is_writable,
+            })
+            .collect();
+
+        Self::new(ix, accounts)
+    }
+
+    /// Serialized size of a request with `ix_data_len` bytes of arguments and `num_accounts` accounts
+    pub const fn space(ix_data_len: usize, num_accounts: usize) -> usize {
+        8 // [u8; 8]
+        + 4 + ix_data_len // Vec<u8>
+        + 4 + (32 + 1) * num_accounts // Vec<AccountMetaRef>
+    }
+
+    pub fn emit(&self) {
+        emit!(self.event());
+    }
+
+    /// Emit with an event instruction, see the `event` module
+    // Anchor's `Result`, as returned by the instructions calling it
+    #[allow(clippy::result_large_err)]
+    pub fn emit_cpi<'info>(&self, event_authority: &AccountInfo<'info>, program: &AccountInfo<'info>) -> Result<()> {
+        crate::event::emit_cpi(&self.event(), event_authority, program)
+    }
+
+    /// VRF input of this request made by `program_id`, to check the `VrfResult` of the callback against
+    pub fn alpha(&self, program_id: &Pubkey) -> [u8; 32] {
+        alpha(program_id, &self.ix_sighash, &self.ix_data, &self.accounts)
+    }
+
+    fn event(&self) -> RequestVrf {
+        RequestVrf {
+            ix_sighash: self.ix_sighash,
+            ix_data: self.ix_data.clone(),
+            accounts: self.accounts.clone(),
+        }
+    }
+
+    /// Check that the callback being executed answers this request.
+    ///
+    /// `ix` is the callback instruction rebuilt from the handler arguments with the `VrfResult` replaced by the
+    /// default placeholder, and `accounts` the callback accounts without the oracle signer. Only the keys are compared,
+    /// writability is enforced by the callback's own account constraints.
+    pub fn verify<T: InstructionData>(&self, ix: T, accounts: &[AccountMeta]) -> std::result::Result<(), VrfError> {
+        let data = ix.data();
+        if data[0..8] != self.ix_sighash || data[8..] != self.ix_data[..] {
+            return Err(VrfError::RequestMismatch);
+        }
+
+        if accounts.len() != self.accounts.len() || accounts.iter().zip(&self.accounts).any(|(a, b)| a.pubkey != b.pubkey) {
+            return Err(VrfError::RequestMismatch);
+        }
+
+        Ok(())
+    }
+}
+
+/// Hash of everything the program fixes when requesting: itself, the callback with its placeholder and the callback
+/// account keys (writability is left out, as in `VrfRequest::verify`).
+///
+/// The oracle chooses no part of the input, so each request has a single valid output and a win can not be ground
+/// out by retrying. Programs make their requests unique, e.g. with the request slot in the callback arguments.
+pub(crate) fn alpha(program_id: &Pubkey, ix_sighash: &[u8; 8], ix_data: &[u8], accounts: &[AccountMetaRef]) -> [u8; 32] {
+    let ix_data_len = (ix_data.len() as u32).to_le_bytes();
+    let mut parts: Vec<&[u8]> = vec![program_id.as_ref(), ix_sighash, &ix_data_len, ix_data];
+    parts.extend(accounts.iter().map(|account| account.pubkey.as_ref()));
+
+    hashv(&parts).to_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{account_meta, VrfResult};
+
+    #[derive(AnchorSerialize)]
+    struct Callback {
+        result: VrfResult,
+        amount: u64,
+    }
+
+    impl InstructionData for Callback {
+        fn data(&self) -> Vec<u8> {
+            let mut data = vec![1, 2, 3, 4, 5, 6, 7, 8];
+            data.extend(self.result.try_to_vec().unwrap());
+            data.extend(self.amount.to_le_bytes());
+            data
+        }
+    }
+
+    fn callback(amount: u64) -> Callback {
+        Callback {
+            result: VrfResult::default(),
+            amount,
+        }
+    }
+
+    #[test]
+    fn test_verify() {
+        let keys = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let request = VrfRequest::new(callback(10), vec![account_meta(&keys[0]).mutable(), account_meta(&keys[1])]);
+        assert_eq!(
+            request.try_to_vec().unwrap().len(),
+            VrfRequest::space(VrfResult::PLACEHOLDER_LEN + 8, 2)
+        );
+
+        let metas = [AccountMeta::new(keys[0], false), AccountMeta::new_readonly(keys[1], false)];
+        assert!(request.verify(callback(10), &metas).is_ok());
+
+        assert!(matches!(request.verify(callback(11), &metas), Err(VrfError::RequestMismatch)));
+        assert!(matches!(request.verify(callback(10), &metas[..1]), Err(VrfError::RequestMismatch)));
+
+        let swapped = [metas[1].clone(), metas[0].clone()];
+        assert!(matches!(request.verify(callback(10), &swapped), Err(VrfError::RequestMismatch)));
+
+        let other = [metas[0].clone(), AccountMeta::new_readonly(Pubkey::new_unique(), false)];
+        assert!(matches!(request.verify(callback(10), &other), Err(VrfError::RequestMismatch)));
+    }
+
+    #[test]
+    fn test_alpha() {
+        let program_id = Pubkey::new_unique();
+        let keys = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let request = VrfRequest::new(callback(10), vec![account_meta(&keys[0]).mutable(), account_meta(&keys[1])]);
+        let alpha = request.alpha(&program_id);
+
+        assert_eq!(request.event().alpha(&program_id), alpha);
+        let metas = [AccountMeta::new_readonly(keys[0], false), AccountMeta::new_readonly(keys[1], false)];
+        assert_eq!(VrfRequest::from_callback(callback(10), &metas).alpha(&program_id), alpha);
+
+        assert_ne!(request.alpha(&Pubkey::new_unique()), alpha);
+        assert_ne!(VrfRequest::from_callback(callback(11), &metas).alpha(&program_id), alpha);
+        assert_ne!(VrfRequest::from_callback(callback(10), &metas[..1]).alpha(&program_id), alpha);
+    }
+}
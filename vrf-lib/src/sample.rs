@@ -0,0 +1,165 @@
+//! Uniform sampling of integer ranges from VRF output.
+//!
+//! `uniform(seed, span)` returns a value in `0..=span` where every value has exactly the same
+//! probability. The 16 seed bytes are read as a big endian `u128`; if it falls in the incomplete
+//! block at the top of the `u128` range (the last `2^128 mod (span + 1)` values) it is rejected and
+//! a new candidate is derived as `sha256(DOMAIN || seed || counter)[..16]`, otherwise the result is
+//! `candidate % (span + 1)`. A candidate is rejected with probability below `(span + 1) / 2^128`, so
+//! for any range a game can use the first candidate is almost always accepted.
+//!
+//! `RandomStream` uses the same reduction with its own output as the candidate source.
+
+use std::ops::RangeInclusive;
+
+use anchor_lang::solana_program::hash::hashv;
+use num_traits::{AsPrimitive, PrimInt};
+
+const DOMAIN: &[u8] = b"vrf-lib:uniform";
+
+pub(crate) fn uniform(seed: &[u8; 16], span: u128) -> u128 {
+    let mut counter: Option<u32> = None;
+    uniform_with(span, || match counter.as_mut() {
+        None => {
+            counter = Some(0);
+            u128::from_be_bytes(*seed)
+        }
+        Some(counter) => {
+            let hash = hashv(&[DOMAIN, seed, &counter.to_le_bytes()]).to_bytes();
+            *counter += 1;
+            u128::from_be_bytes(hash[..16].try_into().unwrap())
+        }
+    })
+}
+
+/// Value in `0..=span`, drawing candidates from `next` until one falls outside the rejected block
+pub(crate) fn uniform_with(span: u128, mut next: impl FnMut() -> u128) -> u128 {
+    let mut candidate = next();
+    if span == u128::MAX {
+        return candidate;
+    }
+
+    let len = span + 1;
+    let max_accepted = u128::MAX - rejected_len(len);
+    while candidate > max_accepted {
+        candidate = next();
+    }
+
+    candidate % len
+}
+
+/// `2^128 mod len`, the size of the incomplete block at the top of the `u128` range
+fn rejected_len(len: u128) -> u128 {
+    (u128::MAX % len + 1) % len
+}
+
+/// Uniform value in `range`, works for every primitive integer including signed and 128 bit types
+pub(crate) fn uniform_in<T>(seed: &[u8; 16], range: RangeInclusive<T>) -> T
+where
+    T: PrimInt + AsPrimitive<u128>,
+    u128: AsPrimitive<T>,
+{
+    let (start, span) = span_of(&range);
+    start.wrapping_add(uniform(seed, span)).as_()
+}
+
+/// `range` as a `u128` start and `end - start`, adding an offset in `0..=span` to `start` with wrapping
+/// arithmetic then converting back gives a value of `range`
+pub(crate) fn span_of<T>(range: &RangeInclusive<T>) -> (u128, u128)
+where
+    T: PrimInt + AsPrimitive<u128>,
+{
+    assert!(range.start() <= range.end(), "empty range");
+
+    // Signed values are sign extended, wrapping arithmetic keeps the difference exact
+    let start: u128 = range.start().as_();
+    (start, range.end().as_().wrapping_sub(start))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn seeds(count: usize) -> impl Iterator<Item = [u8; 16]> {
+        (0..count as u64).map(|i| hashv(&[b"seed", &i.to_le_bytes()]).to_bytes()[..16].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_accepted_block_is_whole() {
+        // Accepted candidates are 0..=max_accepted, that count must be a multiple of len for every residue to be equally likely
+        let lens = (1..=4096u128)
+            .chain((1..128).flat_map(|bit| [(1u128 << bit) - 1, 1 << bit, (1 << bit) + 1]))
+            .chain([u128::MAX - 1, u128::MAX, u128::MAX / 3, u64::MAX as u128 * 7]);
+
+        for len in lens {
+            let max_accepted = u128::MAX - rejected_len(len);
+            assert_eq!(max_accepted % len, len - 1, "len={len}");
+            assert!(rejected_len(len) < len, "len={len}");
+        }
+    }
+
+    #[test]
+    fn test_rejection() {
+        // 3 does not divide 2^128, the top value is in the rejected block and must be re-derived
+        let seed = [0xff; 16];
+        assert_eq!(rejected_len(3), 1);
+        let hash = hashv(&[DOMAIN, &seed, &0u32.to_le_bytes()]).to_bytes();
+        let expected = u128::from_be_bytes(hash[..16].try_into().unwrap()) % 3;
+        assert_eq!(uniform(&seed, 2), expected);
+
+        // Powers of two never reject
+        assert_eq!(uniform(&seed, 255), 255);
+        assert_eq!(uniform(&seed, u128::MAX), u128::MAX);
+    }
+
+    #[test]
+    fn test_range_bounds() {
+        for seed in seeds(2000) {
+            assert!((0..=9).contains(&uniform_in(&seed, 0u8..=9)));
+            assert!((-5..=5).contains(&uniform_in(&seed, -5i64..=5)));
+            assert!((i64::MIN..=i64::MIN + 2).contains(&uniform_in(&seed, i64::MIN..=i64::MIN + 2)));
+            assert!((u64::MAX - 2..=u64::MAX).contains(&uniform_in(&seed, u64::MAX - 2..=u64::MAX)));
+            assert_eq!(uniform_in(&seed, 7u32..=7), 7);
+            uniform_in(&seed, i128::MIN..=i128::MAX);
+            uniform_in(&seed, u128::MIN..=u128::MAX);
+        }
+    }
+
+    #[test]
+    fn test_full_range_identity() {
+        for seed in seeds(100) {
+            assert_eq!(uniform_in(&seed, u128::MIN..=u128::MAX), u128::from_be_bytes(seed));
+            assert_eq!(uniform_in(&seed, u8::MIN..=u8::MAX), seed[15]);
+            assert_eq!(uniform_in(&seed, i8::MIN..=i8::MAX), (seed[15] as i8).wrapping_add(i8::MIN));
+        }
+    }
+
+    #[test]
+    fn test_distribution() {
+        // Every value of small ranges is hit, and counts stay within a loose bound around the mean
+        const SAMPLES: usize = 60_000;
+        for end in [1u64, 2, 5, 6, 36] {
+            let mut counts = vec![0usize; end as usize + 1];
+            for seed in seeds(SAMPLES) {
+                counts[uniform_in(&seed, 0..=end) as usize] += 1;
+            }
+
+            let mean = SAMPLES / counts.len();
+            for (value, count) in counts.iter().enumerate() {
+                assert!(
+                    count.abs_diff(mean) < mean / 10,
+                    "end={end} value={value} count={count} mean={mean}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_signed_distribution() {
+        let mut counts = [0usize; 7];
+        for seed in seeds(7000) {
+            counts[(uniform_in(&seed, -3i16..=3) + 3) as usize] += 1;
+        }
+
+        assert!(counts.iter().all(|count| count.abs_diff(1000) < 150), "{counts:?}");
+    }
+}
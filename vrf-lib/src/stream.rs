@@ -0,0 +1,230 @@
+//! Expandable random stream derived from a `VrfResult`.
+//!
+//! The stream seed is `sha256(DOMAIN || domain || random)` and block `i` of the output is
+//! `sha256(seed || i)` with `i` as a little endian `u64`. Blocks are consumed byte by byte, so the
+//! same result and domain always produce the same sequence however the values are read, and
+//! different domains produce independent sequences.
+
+use std::ops::RangeInclusive;
+
+use anchor_lang::solana_program::hash::{hashv, HASH_BYTES};
+use num_traits::{AsPrimitive, PrimInt};
+
+use crate::sample;
+
+const DOMAIN: &[u8] = b"vrf-lib:stream";
+
+pub struct RandomStream {
+    seed: [u8; HASH_BYTES],
+    counter: u64,
+    block: [u8; HASH_BYTES],
+    cursor: usize,
+}
+
+impl RandomStream {
+    pub(crate) fn new(random: &[u8], domain: &[u8]) -> Self {
+        Self {
+            seed: hashv(&[DOMAIN, domain, random]).to_bytes(),
+            counter: 0,
+            block: [0; HASH_BYTES],
+            cursor: HASH_BYTES,
+        }
+    }
+
+    pub fn fill_bytes(&mut self, output: &mut [u8]) {
+        let mut written = 0;
+        while written < output.len() {
+            if self.cursor == HASH_BYTES {
+                self.block = hashv(&[&self.seed, &self.counter.to_le_bytes()]).to_bytes();
+                self.counter += 1;
+                self.cursor = 0;
+            }
+
+            let len = (output.len() - written).min(HASH_BYTES - self.cursor);
+            output[written..written + len].copy_from_slice(&self.block[self.cursor..self.cursor + len]);
+            written += len;
+            self.cursor += len;
+        }
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_be_bytes(bytes)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_be_bytes(bytes)
+    }
+
+    pub fn next_u128(&mut self) -> u128 {
+        let mut bytes = [0; 16];
+        self.fill_bytes(&mut bytes);
+        u128::from_be_bytes(bytes)
+    }
+
+    /// Uniformly distributed value in `range`, both ends included
+    pub fn bound<T>(&mut self, range: RangeInclusive<T>) -> T
+    where
+        T: PrimInt + AsPrimitive<u128>,
+        u128: AsPrimitive<T>,
+    {
+        let (start, span) = sample::span_of(&range);
+        start.wrapping_add(self.offset(span)).as_()
+    }
+
+    /// `count` distinct values of `range` in ascending order, every subset of that size is equally likely.
+    /// Panics if `range` has less than `count` values
+    pub fn sample_distinct<T>(&mut self, range: RangeInclusive<T>, count: usize) -> Vec<T>
+    where
+        T: PrimInt + AsPrimitive<u128>,
+        u128: AsPrimitive<T>,
+    {
+        let (start, span) = sample::span_of(&range);
+        if count == 0 {
+            return Vec::new();
+        }
+        assert!(count as u128 - 1 <= span, "range has less than {count} values");
+
+        // Floyd's algorithm, exactly `count` draws whatever the size of the range
+        let mut offsets: Vec<u128> = Vec::with_capacity(count);
+        for max in span - (count as u128 - 1)..=span {
+            let offset = self.offset(max);
+            offsets.push(if offsets.contains(&offset) { max } else { offset });
+        }
+
+        offsets.sort_unstable();
+        offsets.into_iter().map(|offset| start.wrapping_add(offset).as_()).collect()
+    }
+
+    /// Fisher-Yates shuffle, every permutation is equally likely
+    pub fn shuffle<T>(&mut self, values: &mut [T]) {
+        for i in (1..values.len()).rev() {
+            let j = self.bound(0..=i);
+            values.swap(i, j);
+        }
+    }
+
+    fn offset(&mut self, span: u128) -> u128 {
+        sample::uniform_with(span, || self.next_u128())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn stream(seed: u64) -> RandomStream {
+        RandomStream::new(&seed.to_le_bytes(), b"test")
+    }
+
+    #[test]
+    fn test_deterministic() {
+        let mut a = RandomStream::new(&[1; 16], b"lottery");
+        let mut b = RandomStream::new(&[1; 16], b"lottery");
+        let mut other_domain = RandomStream::new(&[1; 16], b"spinner");
+        let mut other_random = RandomStream::new(&[2; 16], b"lottery");
+
+        let values: Vec<_> = (0..10).map(|_| a.next_u64()).collect();
+        assert_eq!(values, (0..10).map(|_| b.next_u64()).collect::<Vec<_>>());
+        assert_ne!(values, (0..10).map(|_| other_domain.next_u64()).collect::<Vec<_>>());
+        assert_ne!(values, (0..10).map(|_| other_random.next_u64()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_fill_bytes_across_blocks() {
+        let mut bytes = [0u8; 100];
+        stream(0).fill_bytes(&mut bytes);
+
+        // Reading the same stream in uneven chunks gives the same bytes
+        let mut chunked = stream(0);
+        let mut collected = Vec::new();
+        for len in [3, 29, 1, 31, 36] {
+            let mut chunk = vec![0; len];
+            chunked.fill_bytes(&mut chunk);
+            collected.extend(chunk);
+        }
+        assert_eq!(&bytes[..], &collected[..]);
+
+        let mut numbers = stream(0);
+        assert_eq!(numbers.next_u32().to_be_bytes(), bytes[..4]);
+        assert_eq!(numbers.next_u64().to_be_bytes(), bytes[4..12]);
+        assert_eq!(numbers.next_u128().to_be_bytes(), bytes[12..28]);
+        assert_eq!(numbers.next_u64().to_be_bytes(), bytes[28..36]);
+    }
+
+    #[test]
+    fn test_bound() {
+        let mut stream = stream(1);
+        let mut counts = [0usize; 6];
+        for _ in 0..60_000 {
+            let value = stream.bound(1u8..=6);
+            counts[value as usize - 1] += 1;
+            assert!((-3..=-1).contains(&stream.bound(-3i32..=-1)));
+        }
+
+        assert!(counts.iter().all(|count| count.abs_diff(10_000) < 1_000), "{counts:?}");
+    }
+
+    #[test]
+    fn test_sample_distinct() {
+        for seed in 0..500 {
+            let mut stream = stream(seed);
+            let values = stream.sample_distinct(1u8..=45, 6);
+            assert_eq!(values.len(), 6);
+            assert!(values.windows(2).all(|pair| pair[0] < pair[1]), "{values:?}");
+            assert!(values.iter().all(|value| (1..=45).contains(value)));
+
+            assert_eq!(stream.sample_distinct(3u8..=7, 5), vec![3, 4, 5, 6, 7]);
+            assert_eq!(stream.sample_distinct(i64::MIN..=i64::MIN + 1, 2), vec![i64::MIN, i64::MIN + 1]);
+            assert_eq!(stream.sample_distinct(u128::MIN..=u128::MAX, 16).len(), 16);
+            assert!(stream.sample_distinct(0u8..=0, 0).is_empty());
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_sample_distinct_too_many() {
+        stream(0).sample_distinct(1u8..=5, 6);
+    }
+
+    #[test]
+    fn test_sample_distinct_distribution() {
+        // Each of the 10 values is in a 3 value sample with probability 3/10, each of the 120 subsets with probability 1/120
+        let mut values = [0usize; 10];
+        let mut subsets = HashMap::new();
+        let mut stream = stream(2);
+        for _ in 0..60_000 {
+            let sample = stream.sample_distinct(0usize..=9, 3);
+            for value in &sample {
+                values[*value] += 1;
+            }
+            *subsets.entry(sample).or_insert(0usize) += 1;
+        }
+
+        assert!(values.iter().all(|count| count.abs_diff(18_000) < 900), "{values:?}");
+        assert_eq!(subsets.len(), 120);
+        assert!(subsets.values().all(|count| count.abs_diff(500) < 150), "{subsets:?}");
+    }
+
+    #[test]
+    fn test_shuffle() {
+        let mut permutations = HashMap::new();
+        let mut stream = stream(3);
+        for _ in 0..24_000 {
+            let mut values = [1, 2, 3, 4];
+            stream.shuffle(&mut values);
+            *permutations.entry(values).or_insert(0usize) += 1;
+        }
+
+        assert_eq!(permutations.len(), 24);
+        assert!(permutations.values().all(|count| count.abs_diff(1_000) < 200), "{permutations:?}");
+
+        let mut empty: [u8; 0] = [];
+        stream.shuffle(&mut empty);
+    }
+}
@@ -0,0 +1,2 @@
+ALTER TABLE vrf DROP COLUMN program_error_code;
+ALTER TABLE vrf DROP COLUMN error_code;
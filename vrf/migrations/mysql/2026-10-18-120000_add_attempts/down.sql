@@ -0,0 +1,5 @@
+UPDATE vrf SET `status` = 'fatal_error' WHERE `status` = 'dead_letter';
+ALTER TABLE vrf
+    MODIFY `status` ENUM('new', 'processing', 'processed', 'fatal_error', 'retryable_error') NOT NULL,
+    DROP COLUMN next_attempt_at,
+    DROP COLUMN attempts;
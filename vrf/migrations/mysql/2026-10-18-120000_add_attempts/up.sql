@@ -0,0 +1,4 @@
+ALTER TABLE vrf
+    MODIFY `status` ENUM('new', 'processing', 'processed', 'fatal_error', 'retryable_error', 'dead_letter') NOT NULL,
+    ADD COLUMN attempts INT NOT NULL DEFAULT 0,
+    ADD COLUMN next_attempt_at TIMESTAMP NULL;
@@ -0,0 +1,3 @@
+UPDATE vrf SET `status` = 'fatal_error' WHERE `status` = 'ignored';
+ALTER TABLE vrf
+    MODIFY `status` ENUM('new', 'processing', 'processed', 'fatal_error', 'retryable_error', 'dead_letter') NOT NULL;
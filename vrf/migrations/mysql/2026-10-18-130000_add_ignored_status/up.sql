@@ -0,0 +1,2 @@
+ALTER TABLE vrf
+    MODIFY `status` ENUM('new', 'processing', 'processed', 'fatal_error', 'retryable_error', 'dead_letter', 'ignored') NOT NULL;
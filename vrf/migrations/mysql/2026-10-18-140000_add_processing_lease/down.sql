@@ -0,0 +1,3 @@
+ALTER TABLE vrf
+    DROP COLUMN lease_expires_at,
+    DROP COLUMN owner_instance;
@@ -0,0 +1,3 @@
+ALTER TABLE vrf
+    ADD COLUMN owner_instance VARCHAR(64) NULL,
+    ADD COLUMN lease_expires_at TIMESTAMP NULL;
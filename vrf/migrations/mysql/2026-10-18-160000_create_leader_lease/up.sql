@@ -0,0 +1,5 @@
+CREATE TABLE leader_lease (
+    name VARCHAR(64) CHARACTER SET UTF8MB4 NOT NULL PRIMARY KEY,
+    owner VARCHAR(64) CHARACTER SET UTF8MB4 NOT NULL,
+    expires_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP()
+);
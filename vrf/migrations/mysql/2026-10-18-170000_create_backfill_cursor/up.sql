@@ -0,0 +1,6 @@
+CREATE TABLE backfill_cursor (
+    program_id VARCHAR(128) CHARACTER SET UTF8MB4 NOT NULL PRIMARY KEY,
+    slot BIGINT NOT NULL,
+    signature VARCHAR(128) CHARACTER SET UTF8MB4 NOT NULL,
+    time_update TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP() ON UPDATE CURRENT_TIMESTAMP()
+);
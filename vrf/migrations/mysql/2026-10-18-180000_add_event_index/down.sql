@@ -0,0 +1,5 @@
+DELETE FROM vrf WHERE event_index > 0;
+ALTER TABLE vrf
+    DROP INDEX index_program_tx_event,
+    ADD UNIQUE KEY index_program_tx(program_id, `transaction`),
+    DROP COLUMN event_index;
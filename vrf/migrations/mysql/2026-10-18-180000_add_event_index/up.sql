@@ -0,0 +1,5 @@
+-- One row per `RequestVrf` event of a transaction
+ALTER TABLE vrf
+    ADD COLUMN event_index INT NOT NULL DEFAULT 0,
+    DROP INDEX index_program_tx,
+    ADD UNIQUE KEY index_program_tx_event(program_id, `transaction`, event_index);
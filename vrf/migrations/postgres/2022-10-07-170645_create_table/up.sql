@@ -0,0 +1,18 @@
+
+CREATE TABLE vrf (
+    id SERIAL PRIMARY KEY,
+    program_id VARCHAR(128) NOT NULL,
+    "transaction" VARCHAR(128) NOT NULL,
+    -- `db::Status`
+    "status" INTEGER NOT NULL,
+    vrf_seeds BYTEA,
+    vrf_proof BYTEA,
+    response_transaction VARCHAR(128),
+    log_messages TEXT,
+    errors TEXT,
+    time_create TIMESTAMP NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc'),
+    time_update TIMESTAMP NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc'),
+    CONSTRAINT index_program_tx UNIQUE (program_id, "transaction")
+);
+
+CREATE INDEX index_status ON vrf ("status");
@@ -0,0 +1,2 @@
+ALTER TABLE vrf DROP COLUMN units_consumed;
+ALTER TABLE vrf DROP COLUMN simulation_logs;
@@ -0,0 +1,2 @@
+ALTER TABLE vrf ADD COLUMN error_code VARCHAR(64);
+ALTER TABLE vrf ADD COLUMN program_error_code BIGINT;
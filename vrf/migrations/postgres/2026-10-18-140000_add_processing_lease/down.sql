@@ -0,0 +1,2 @@
+ALTER TABLE vrf DROP COLUMN lease_expires_at;
+ALTER TABLE vrf DROP COLUMN owner_instance;
@@ -0,0 +1,2 @@
+ALTER TABLE vrf ADD COLUMN owner_instance VARCHAR(64);
+ALTER TABLE vrf ADD COLUMN lease_expires_at TIMESTAMP;
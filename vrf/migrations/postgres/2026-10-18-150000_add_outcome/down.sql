@@ -0,0 +1 @@
+ALTER TABLE vrf DROP COLUMN outcome;
@@ -0,0 +1,5 @@
+CREATE TABLE leader_lease (
+    name VARCHAR(64) PRIMARY KEY,
+    owner VARCHAR(64) NOT NULL,
+    expires_at TIMESTAMP NOT NULL
+);
@@ -0,0 +1,6 @@
+CREATE TABLE backfill_cursor (
+    program_id VARCHAR(128) PRIMARY KEY,
+    slot BIGINT NOT NULL,
+    signature VARCHAR(128) NOT NULL,
+    time_update TIMESTAMP NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc')
+);
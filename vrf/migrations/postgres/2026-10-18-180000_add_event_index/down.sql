@@ -0,0 +1,4 @@
+DELETE FROM vrf WHERE event_index > 0;
+ALTER TABLE vrf DROP CONSTRAINT index_program_tx_event;
+ALTER TABLE vrf ADD CONSTRAINT index_program_tx UNIQUE (program_id, "transaction");
+ALTER TABLE vrf DROP COLUMN event_index;
@@ -0,0 +1,4 @@
+-- One row per `RequestVrf` event of a transaction
+ALTER TABLE vrf ADD COLUMN event_index INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE vrf DROP CONSTRAINT index_program_tx;
+ALTER TABLE vrf ADD CONSTRAINT index_program_tx_event UNIQUE (program_id, "transaction", event_index);
@@ -0,0 +1,18 @@
+
+CREATE TABLE vrf (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    program_id VARCHAR(128) NOT NULL,
+    `transaction` VARCHAR(128) NOT NULL,
+    -- `db::Status`
+    `status` INTEGER NOT NULL,
+    vrf_seeds BLOB,
+    vrf_proof BLOB,
+    response_transaction VARCHAR(128),
+    log_messages TEXT,
+    errors TEXT,
+    time_create TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    time_update TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (program_id, `transaction`)
+);
+
+CREATE INDEX index_status ON vrf (`status`);
@@ -0,0 +1 @@
+ALTER TABLE vrf ADD COLUMN compute_unit_price BIGINT;
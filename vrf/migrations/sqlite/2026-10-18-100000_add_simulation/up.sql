@@ -0,0 +1,2 @@
+ALTER TABLE vrf ADD COLUMN simulation_logs TEXT;
+ALTER TABLE vrf ADD COLUMN units_consumed BIGINT;
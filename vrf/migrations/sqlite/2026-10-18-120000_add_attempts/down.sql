@@ -0,0 +1,3 @@
+UPDATE vrf SET `status` = 4 WHERE `status` = 6;
+ALTER TABLE vrf DROP COLUMN next_attempt_at;
+ALTER TABLE vrf DROP COLUMN attempts;
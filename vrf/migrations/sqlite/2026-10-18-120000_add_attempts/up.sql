@@ -0,0 +1,2 @@
+ALTER TABLE vrf ADD COLUMN attempts INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE vrf ADD COLUMN next_attempt_at TIMESTAMP;
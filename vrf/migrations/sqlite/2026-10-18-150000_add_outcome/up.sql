@@ -0,0 +1 @@
+ALTER TABLE vrf ADD COLUMN outcome VARCHAR(32);
@@ -0,0 +1,42 @@
+-- One row per `RequestVrf` event of a transaction. SQLite can not drop the unique constraint, the table is rebuilt
+CREATE TABLE vrf_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    program_id VARCHAR(128) NOT NULL,
+    `transaction` VARCHAR(128) NOT NULL,
+    event_index INTEGER NOT NULL DEFAULT 0,
+    -- `db::Status`
+    `status` INTEGER NOT NULL,
+    vrf_seeds BLOB,
+    vrf_proof BLOB,
+    response_transaction VARCHAR(128),
+    log_messages TEXT,
+    errors TEXT,
+    time_create TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    time_update TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    compute_unit_price BIGINT,
+    simulation_logs TEXT,
+    units_consumed BIGINT,
+    error_code VARCHAR(64),
+    program_error_code BIGINT,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMP,
+    owner_instance VARCHAR(64),
+    lease_expires_at TIMESTAMP,
+    outcome VARCHAR(32),
+    UNIQUE (program_id, `transaction`, event_index)
+);
+
+INSERT INTO vrf_new (
+    id, program_id, `transaction`, `status`, vrf_seeds, vrf_proof, response_transaction, log_messages, errors,
+    time_create, time_update, compute_unit_price, simulation_logs, units_consumed, error_code, program_error_code,
+    attempts, next_attempt_at, owner_instance, lease_expires_at, outcome
+)
+SELECT
+    id, program_id, `transaction`, `status`, vrf_seeds, vrf_proof, response_transaction, log_messages, errors,
+    time_create, time_update, compute_unit_price, simulation_logs, units_consumed, error_code, program_error_code,
+    attempts, next_attempt_at, owner_instance, lease_expires_at, outcome
+FROM vrf;
+
+DROP TABLE vrf;
+ALTER TABLE vrf_new RENAME TO vrf;
+CREATE INDEX index_status ON vrf (`status`);
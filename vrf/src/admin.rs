@@ -0,0 +1,126 @@
+//! `vrf-server admin`: inspect failed requests and drive them again once their cause is fixed.
+//!
+//! Requeued requests get the retryable status with a fresh attempt count, the running server picks them up on its
+//! next retry pass.
+
+use anyhow::Context;
+
+use crate::db::{Request, Status, Storage};
+
+#[derive(Debug, clap::Subcommand)]
+pub enum AdminCommand {
+    /// Latest updated requests
+    List {
+        #[arg(long)]
+        status: Option<Status>,
+        #[arg(long)]
+        program_id: Option<String>,
+        #[arg(long, default_value_t = 50)]
+        limit: usize,
+    },
+    /// Everything stored about the requests of a transaction
+    Show { signature: String },
+    /// Retry the failed or ignored requests of a transaction
+    Requeue {
+        signature: String,
+        /// Only the request of this program, when the transaction made several
+        #[arg(long)]
+        program_id: Option<String>,
+    },
+    /// Stop retrying the failed requests of a transaction
+    MarkIgnored {
+        signature: String,
+        #[arg(long)]
+        program_id: Option<String>,
+    },
+    /// Retry the fatal and dead-letter requests updated since a time
+    Replay {
+        /// RFC 3339, e.g. 2022-10-07T17:00:00Z
+        #[arg(long)]
+        since: chrono::DateTime<chrono::Utc>,
+        #[arg(long)]
+        program_id: Option<String>,
+    },
+}
+
+pub fn run(command: AdminCommand, storage: &dyn Storage) -> anyhow::Result<()> {
+    match command {
+        AdminCommand::List { status, program_id, limit } => {
+            for request in storage.list_requests(status, program_id.as_deref(), limit)? {
+                println!(
+                    "{}  {:<15}  {:>3}  {}  {}#{}  {}",
+                    request.time_update,
+                    request.status.name(),
+                    request.attempts,
+                    request.program_id,
+                    request.transaction,
+                    request.event_index,
+                    request.error_code.as_deref().unwrap_or("-"),
+                );
+            }
+        }
+        AdminCommand::Show { signature } => {
+            let requests = storage.get_requests(&signature)?;
+            anyhow::ensure!(!requests.is_empty(), "No request {signature}");
+            for request in &requests {
+                print_request(request);
+            }
+        }
+        AdminCommand::Requeue { signature, program_id } => {
+            let count = storage.requeue(&signature, program_id.as_deref())?;
+            anyhow::ensure!(count > 0, "No failed or ignored request {signature}");
+            println!("Requeued {count} request(s)");
+        }
+        AdminCommand::MarkIgnored { signature, program_id } => {
+            let count = storage.mark_ignored(&signature, program_id.as_deref())?;
+            anyhow::ensure!(count > 0, "No failed request {signature}");
+            println!("Ignored {count} request(s)");
+        }
+        AdminCommand::Replay { since, program_id } => {
+            let count = storage
+                .requeue_failed_since(since.naive_utc(), program_id.as_deref())
+                .context("requeue failed requests")?;
+            println!("Requeued {count} request(s) failed since {since}");
+        }
+    }
+
+    Ok(())
+}
+
+fn print_request(request: &Request) {
+    println!("Request {}#{} ({})", request.transaction, request.event_index, request.program_id);
+    println!("  status:               {}", request.status.name());
+    if let Some(outcome) = &request.outcome {
+        println!("  outcome:              {outcome}");
+    }
+    println!("  attempts:             {}", request.attempts);
+    if let Some(next_attempt_at) = request.next_attempt_at {
+        println!("  next attempt:         {next_attempt_at}");
+    }
+    if let Some(response_transaction) = &request.response_transaction {
+        println!("  response transaction: {response_transaction}");
+    }
+    if let Some(units_consumed) = request.units_consumed {
+        println!("  units consumed:       {units_consumed}");
+    }
+    if let Some(error_code) = &request.error_code {
+        match request.program_error_code {
+            Some(program_error_code) => println!("  error code:           {error_code} ({program_error_code})"),
+            None => println!("  error code:           {error_code}"),
+        }
+    }
+    println!("  created:              {}", request.time_create);
+    println!("  updated:              {}", request.time_update);
+    if let Some(errors) = &request.errors {
+        println!("  errors:");
+        for line in errors.lines() {
+            println!("    {line}");
+        }
+    }
+    if let Some(simulation_logs) = &request.simulation_logs {
+        println!("  simulation logs:");
+        for line in simulation_logs.lines() {
+            println!("    {line}");
+        }
+    }
+}
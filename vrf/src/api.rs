@@ -0,0 +1,314 @@
+//! Read-only HTTP API so players can check how their result was generated.
+//!
+//! `GET /requests/{signature}` returns the stored state of the VRF request made by transaction `signature`, with the
+//! randomness backend and public key needed to verify the proof. A transaction making several requests has one per
+//! `RequestVrf` event, `GET /requests/{signature}/{event_index}` returns the one at `event_index`. Both take a
+//! `program_id` query parameter, required when the transaction made requests to several programs. The seeds, proof
+//! and simulation logs are only returned once the request is fulfilled.
+
+use std::{collections::HashMap, net::SocketAddr, str::FromStr, sync::Arc};
+
+use anchor_client::solana_sdk::signature::Signature;
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    routing::get,
+    Extension, Json, Router,
+};
+
+use crate::{
+    db::{Status, Storage},
+    randomness::{ProviderKind, RandomnessProvider},
+    VrfConfig,
+};
+
+struct ProgramInfo {
+    provider: ProviderKind,
+    public_key: Option<String>,
+}
+
+impl ProgramInfo {
+    fn new(provider: &dyn RandomnessProvider) -> anyhow::Result<Self> {
+        // The "public key" of the deterministic backend is its seed
+        let public_key = match provider.kind() {
+            ProviderKind::Deterministic => None,
+            _ => Some(base64::encode(provider.public_key()?)),
+        };
+
+        Ok(Self {
+            provider: provider.kind(),
+            public_key,
+        })
+    }
+}
+
+struct ApiState {
+    storage: Arc<dyn Storage>,
+    programs: HashMap<String, ProgramInfo>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct RequestQuery {
+    program_id: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct RequestResponse {
+    program_id: String,
+    transaction: String,
+    event_index: i32,
+    status: Status,
+    outcome: Option<String>,
+    provider: Option<ProviderKind>,
+    /// Base64, as are the seeds and proof
+    public_key: Option<String>,
+    vrf_seeds: Option<String>,
+    vrf_proof: Option<String>,
+    response_transaction: Option<String>,
+    errors: Option<String>,
+    error_code: Option<String>,
+    program_error_code: Option<i64>,
+    /// Of the last fulfillment simulation
+    simulation_logs: Option<String>,
+    units_consumed: Option<i64>,
+    attempts: i32,
+    next_attempt_at: Option<chrono::NaiveDateTime>,
+    time_create: chrono::NaiveDateTime,
+    time_update: chrono::NaiveDateTime,
+}
+
+fn router(state: ApiState) -> Router {
+    Router::new()
+        .route("/requests/:signature", get(get_request))
+        .route("/requests/:signature/:event_index", get(get_event_request))
+        .layer(Extension(Arc::new(state)))
+}
+
+pub async fn serve(config: Arc<VrfConfig>, storage: Arc<dyn Storage>, address: SocketAddr) -> anyhow::Result<()> {
+    let mut programs = HashMap::new();
+    for (program_id, provider) in &config.randomness_providers {
+        programs.insert(program_id.to_string(), ProgramInfo::new(provider.as_ref())?);
+    }
+
+    let app = router(ApiState { storage, programs });
+
+    tracing::info!("HTTP API listening on {address}");
+    axum::Server::bind(&address).serve(app.into_make_service()).await?;
+    Ok(())
+}
+
+async fn get_request(
+    state: Extension<Arc<ApiState>>,
+    Path(signature): Path<String>,
+    query: Query<RequestQuery>,
+) -> Result<Json<RequestResponse>, (StatusCode, String)> {
+    get_event_request(state, Path((signature, 0)), query).await
+}
+
+async fn get_event_request(
+    Extension(state): Extension<Arc<ApiState>>,
+    Path((signature, event_index)): Path<(String, i32)>,
+    Query(query): Query<RequestQuery>,
+) -> Result<Json<RequestResponse>, (StatusCode, String)> {
+    if Signature::from_str(&signature).is_err() {
+        return Err((StatusCode::BAD_REQUEST, "Invalid transaction signature".to_string()));
+    }
+
+    let storage = state.storage.clone();
+    let requests = match tokio::task::spawn_blocking(move || storage.get_requests(&signature)).await {
+        Ok(requests) => requests,
+        Err(err) => Err(err.into()),
+    };
+    let requests = requests.map_err(|err| {
+        tracing::error!("[DB] Get request error: {err:#}");
+        (StatusCode::INTERNAL_SERVER_ERROR, "Internal error".to_string())
+    })?;
+
+    let mut requests = requests.into_iter().filter(|request| {
+        request.event_index == event_index && (query.program_id.is_none() || query.program_id.as_ref() == Some(&request.program_id))
+    });
+    let request = requests
+        .next()
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Request not found".to_string()))?;
+    if requests.next().is_some() {
+        return Err((StatusCode::BAD_REQUEST, "Requests of several programs, set program_id".to_string()));
+    }
+    let program = state.programs.get(&request.program_id);
+    // The proof is only final once its fulfillment landed, a request still being retried could be re-proven
+    let fulfilled = request.status == Status::Processed && request.outcome.as_deref() == Some("fulfilled");
+
+    Ok(Json(RequestResponse {
+        provider: program.map(|program| program.provider),
+        public_key: program.and_then(|program| program.public_key.clone()),
+        program_id: request.program_id,
+        transaction: request.transaction,
+        event_index: request.event_index,
+        status: request.status,
+        outcome: request.outcome,
+        vrf_seeds: request.vrf_seeds.filter(|_| fulfilled).map(base64::encode),
+        vrf_proof: request.vrf_proof.filter(|_| fulfilled).map(base64::encode),
+        response_transaction: request.response_transaction,
+        errors: request.errors,
+        error_code: request.error_code,
+        program_error_code: request.program_error_code,
+        simulation_logs: request.simulation_logs.filter(|_| fulfilled),
+        units_consumed: request.units_consumed.filter(|_| fulfilled),
+        attempts: request.attempts,
+        next_attempt_at: request.next_attempt_at,
+        time_create: request.time_create,
+        time_update: request.time_update,
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::{
+        db,
+        error::ProcessError,
+        program_error::Classification,
+        randomness::{DeterministicProvider, Secp256k1Provider},
+    };
+
+    fn app(storage: Arc<dyn Storage>) -> Router {
+        router(ApiState {
+            storage,
+            programs: HashMap::from([(
+                "program".to_string(),
+                ProgramInfo::new(&Secp256k1Provider::new(vec![5; 32])).unwrap(),
+            )]),
+        })
+    }
+
+    async fn get(app: Router, uri: &str) -> (StatusCode, Vec<u8>) {
+        let response = app.oneshot(Request::get(uri).body(Body::empty()).unwrap()).await.unwrap();
+        let status = response.status();
+        (status, hyper::body::to_bytes(response.into_body()).await.unwrap().to_vec())
+    }
+
+    #[tokio::test]
+    async fn test_get_request() {
+        let signature = Signature::new(&[7; 64]).to_string();
+        let storage = db::connect("sqlite://:memory:").unwrap();
+        storage.run_migration().unwrap();
+        storage.new_transaction("program", &signature, "").unwrap();
+        storage
+            .process_transaction("program", &signature, 0, "instance", std::time::Duration::from_secs(60))
+            .unwrap();
+        storage
+            .set_simulation("program", &signature, 0, "Program log: ok".to_string(), Some(1000))
+            .unwrap();
+        storage
+            .complete_processing("program", &signature, 0, "response".to_string(), vec![1, 2, 3], vec![4, 5, 6])
+            .unwrap();
+        storage.new_events("program", &signature, "", 4).unwrap();
+        for event_index in [2, 3] {
+            storage
+                .process_transaction("program", &signature, event_index, "instance", std::time::Duration::from_secs(60))
+                .unwrap();
+            storage
+                .set_simulation("program", &signature, event_index, "Program log: ok".to_string(), Some(1000))
+                .unwrap();
+            storage
+                .set_response_transaction(
+                    "program",
+                    &signature,
+                    event_index,
+                    "response".to_string(),
+                    vec![1, 2, 3],
+                    vec![4, 5, 6],
+                )
+                .unwrap();
+        }
+        let error = ProcessError::Simulation {
+            classification: Classification {
+                is_fatal: false,
+                program_error_code: Some(6000),
+                description: "program error 6000".to_string(),
+            },
+            logs: Some(vec!["Program log: won".to_string()]),
+        };
+        storage
+            .error_processing(
+                "program",
+                &signature,
+                3,
+                error.to_request_error(),
+                Some(std::time::Duration::from_secs(60)),
+            )
+            .unwrap();
+        storage.new_transaction("other", &signature, "").unwrap();
+        let app = app(storage);
+
+        let (status, body) = get(app.clone(), &format!("/requests/{signature}?program_id=program")).await;
+        assert_eq!(status, StatusCode::OK);
+        let response: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response["event_index"], 0);
+        assert_eq!(response["status"], "processed");
+        assert_eq!(response["outcome"], "fulfilled");
+        assert_eq!(response["provider"], "ecvrf-secp256k1");
+        assert_eq!(
+            response["public_key"],
+            base64::encode(Secp256k1Provider::new(vec![5; 32]).public_key().unwrap())
+        );
+        assert_eq!(response["vrf_seeds"], base64::encode([1, 2, 3]));
+        assert_eq!(response["vrf_proof"], base64::encode([4, 5, 6]));
+        assert_eq!(response["response_transaction"], "response");
+        assert!(response["errors"].is_null());
+        assert!(response["error_code"].is_null());
+        assert_eq!(response["simulation_logs"], "Program log: ok");
+        assert_eq!(response["units_consumed"], 1000);
+
+        let (status, body) = get(app.clone(), &format!("/requests/{signature}/1")).await;
+        assert_eq!(status, StatusCode::OK);
+        let response: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response["event_index"], 1);
+        assert_eq!(response["status"], "new");
+
+        for (event_index, status) in [(2, "processing"), (3, "retryable_error")] {
+            let (_, body) = get(app.clone(), &format!("/requests/{signature}/{event_index}")).await;
+            let response: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(response["status"], status);
+            assert_eq!(response["response_transaction"], "response");
+            assert!(response["vrf_seeds"].is_null());
+            assert!(response["vrf_proof"].is_null());
+            assert!(response["simulation_logs"].is_null());
+            assert!(response["units_consumed"].is_null());
+        }
+        let (_, body) = get(app.clone(), &format!("/requests/{signature}/3")).await;
+        let response: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response["error_code"], "simulation");
+        assert_eq!(response["errors"], "Simulation failed: retryable, program error 6000");
+
+        // Both programs have a request at index 0 of the transaction
+        let (status, _) = get(app.clone(), &format!("/requests/{signature}")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, body) = get(app.clone(), &format!("/requests/{signature}/0?program_id=other")).await;
+        assert_eq!(status, StatusCode::OK);
+        let response: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response["program_id"], "other");
+        assert_eq!(response["status"], "new");
+        assert!(response["provider"].is_null());
+        let (status, _) = get(app.clone(), &format!("/requests/{signature}/1?program_id=other")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, _) = get(app.clone(), &format!("/requests/{signature}/4")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, _) = get(app.clone(), &format!("/requests/{}", Signature::new(&[8; 64]))).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, _) = get(app, "/requests/not-a-signature").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_program_info() {
+        let info = ProgramInfo::new(&DeterministicProvider::new(b"seed".to_vec())).unwrap();
+        assert_eq!(info.provider, ProviderKind::Deterministic);
+        assert!(info.public_key.is_none());
+    }
+}
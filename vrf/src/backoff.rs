@@ -0,0 +1,99 @@
+//! When a request failing with a retryable error is processed again, set with `[retry]` in `vrf-config.toml`.
+//!
+//! The delay doubles with each failed attempt up to `backoff-max-seconds`, and is jittered so the requests failing
+//! together, e.g. during an RPC outage, are not retried together. A request failing `max-attempts` times is moved to
+//! the dead-letter status and left for an operator.
+
+use std::time::Duration;
+
+use rand::Rng;
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Backoff {
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    #[serde(default = "default_backoff_base_seconds")]
+    pub backoff_base_seconds: u64,
+    #[serde(default = "default_backoff_max_seconds")]
+    pub backoff_max_seconds: u64,
+}
+
+fn default_max_attempts() -> u32 {
+    10
+}
+
+fn default_backoff_base_seconds() -> u64 {
+    10
+}
+
+fn default_backoff_max_seconds() -> u64 {
+    3600
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_max_attempts(),
+            backoff_base_seconds: default_backoff_base_seconds(),
+            backoff_max_seconds: default_backoff_max_seconds(),
+        }
+    }
+}
+
+impl Backoff {
+    /// Delay before the next attempt of a request which failed `attempts` times, `None` when it should not be retried
+    pub fn next_attempt_delay(&self, attempts: u32) -> Option<Duration> {
+        if attempts >= self.max_attempts {
+            return None;
+        }
+
+        let delay = self.max_delay(attempts);
+        // Half fixed so a retry never comes right away, half random
+        Some(Duration::from_secs(delay / 2 + rand::thread_rng().gen_range(0, delay / 2 + 1)))
+    }
+
+    fn max_delay(&self, attempts: u32) -> u64 {
+        let exponent = attempts.saturating_sub(1).min(63);
+        self.backoff_base_seconds
+            .saturating_mul(1 << exponent)
+            .min(self.backoff_max_seconds)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_max_delay() {
+        let backoff = Backoff {
+            max_attempts: 100,
+            backoff_base_seconds: 10,
+            backoff_max_seconds: 3600,
+        };
+        assert_eq!(backoff.max_delay(1), 10);
+        assert_eq!(backoff.max_delay(2), 20);
+        assert_eq!(backoff.max_delay(3), 40);
+        assert_eq!(backoff.max_delay(9), 2560);
+        assert_eq!(backoff.max_delay(10), 3600);
+        assert_eq!(backoff.max_delay(99), 3600);
+    }
+
+    #[test]
+    fn test_next_attempt_delay() {
+        let backoff = Backoff {
+            max_attempts: 5,
+            ..Default::default()
+        };
+        for attempts in 1..5 {
+            let max_delay = backoff.max_delay(attempts);
+            for _ in 0..100 {
+                let delay = backoff.next_attempt_delay(attempts).unwrap().as_secs();
+                assert!(max_delay / 2 <= delay && delay <= max_delay, "{attempts}: {delay}");
+            }
+        }
+        assert_eq!(backoff.next_attempt_delay(5), None);
+        assert_eq!(backoff.next_attempt_delay(6), None);
+    }
+}
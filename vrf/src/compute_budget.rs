@@ -0,0 +1,196 @@
+//! Compute budget of the fulfillment transactions, set per program with `compute-budget` in `vrf-config.toml`.
+
+use anchor_client::{
+    solana_client::{client_error::Result as ClientResult, nonblocking::rpc_client::RpcClient, rpc_request::RpcRequest},
+    solana_sdk::{compute_budget::ComputeBudgetInstruction, instruction::Instruction, pubkey::Pubkey},
+};
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ComputeBudgetPolicy {
+    /// Limit of the simulation, the fulfillment is sent with the units it consumed plus `unit-margin`
+    #[serde(default = "default_unit_limit")]
+    pub unit_limit: u32,
+    /// Fraction of the simulated units added to the limit, the state may change between simulating and sending
+    #[serde(default = "default_unit_margin")]
+    pub unit_margin: f64,
+    #[serde(default)]
+    pub price: PriceSource,
+    /// Price multiplier of a retried request over its previous attempt
+    #[serde(default = "default_escalation")]
+    pub escalation: f64,
+    /// Micro-lamports per compute unit
+    pub max_price: Option<u64>,
+}
+
+/// On-chain proof verification alone takes most of the default 200k units
+fn default_unit_limit() -> u32 {
+    1_400_000
+}
+
+fn default_unit_margin() -> f64 {
+    0.2
+}
+
+fn default_escalation() -> f64 {
+    1.0
+}
+
+impl Default for ComputeBudgetPolicy {
+    fn default() -> Self {
+        Self {
+            unit_limit: default_unit_limit(),
+            unit_margin: default_unit_margin(),
+            price: PriceSource::default(),
+            escalation: default_escalation(),
+            max_price: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PriceSource {
+    /// Micro-lamports per compute unit
+    Static(u64),
+    /// Percentile of the prioritization fees recently paid to write the accounts of the request
+    Percentile(u8),
+}
+
+impl Default for PriceSource {
+    fn default() -> Self {
+        PriceSource::Static(0)
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcPrioritizationFee {
+    prioritization_fee: u64,
+}
+
+impl ComputeBudgetPolicy {
+    /// Compute unit price for a request writing `accounts`, `previous_price` being the price of its last attempt
+    pub async fn price(&self, rpc_client: &RpcClient, accounts: &[Pubkey], previous_price: Option<u64>) -> ClientResult<u64> {
+        let base = match self.price {
+            PriceSource::Static(price) => price,
+            PriceSource::Percentile(percentile) => {
+                // `getRecentPrioritizationFees` is newer than this client, the cluster takes at most 128 accounts
+                let accounts = accounts.iter().take(128).map(|account| account.to_string()).collect::<Vec<_>>();
+                let fees: Vec<RpcPrioritizationFee> = rpc_client
+                    .send(
+                        RpcRequest::Custom {
+                            method: "getRecentPrioritizationFees",
+                        },
+                        serde_json::json!([accounts]),
+                    )
+                    .await?;
+                nearest_rank(fees.into_iter().map(|fee| fee.prioritization_fee).collect(), percentile)
+            }
+        };
+
+        Ok(self.choose(base, previous_price))
+    }
+
+    fn choose(&self, base: u64, previous_price: Option<u64>) -> u64 {
+        let escalated = previous_price.map_or(0, |price| (price as f64 * self.escalation).ceil() as u64);
+        let price = base.max(escalated);
+        self.max_price.map_or(price, |max_price| price.min(max_price))
+    }
+
+    /// Limit for a fulfillment which consumed `units_consumed` in simulation, `unit_limit` when unknown
+    pub fn estimated_unit_limit(&self, units_consumed: Option<u64>) -> u32 {
+        units_consumed.map_or(self.unit_limit, |units| {
+            let limit = (units as f64 * (1.0 + self.unit_margin)).ceil() as u64;
+            limit.min(u64::from(self.unit_limit)) as u32
+        })
+    }
+
+    pub fn instructions(&self, unit_limit: u32, price: u64) -> Vec<Instruction> {
+        let mut instructions = vec![ComputeBudgetInstruction::set_compute_unit_limit(unit_limit)];
+        if price > 0 {
+            instructions.push(ComputeBudgetInstruction::set_compute_unit_price(price));
+        }
+        instructions
+    }
+}
+
+/// Nearest-rank `percentile` of `values`, 0 when empty
+fn nearest_rank(mut values: Vec<u64>, percentile: u8) -> u64 {
+    if values.is_empty() {
+        return 0;
+    }
+
+    values.sort_unstable();
+    let rank = (usize::from(percentile.min(100)) * values.len()).div_ceil(100);
+    values[rank.saturating_sub(1)]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_nearest_rank() {
+        assert_eq!(nearest_rank(vec![], 50), 0);
+        assert_eq!(nearest_rank(vec![7], 0), 7);
+        assert_eq!(nearest_rank(vec![7], 100), 7);
+
+        let values = vec![50, 10, 40, 20, 30];
+        assert_eq!(nearest_rank(values.clone(), 0), 10);
+        assert_eq!(nearest_rank(values.clone(), 20), 10);
+        assert_eq!(nearest_rank(values.clone(), 21), 20);
+        assert_eq!(nearest_rank(values.clone(), 50), 30);
+        assert_eq!(nearest_rank(values.clone(), 100), 50);
+        assert_eq!(nearest_rank(values, 255), 50);
+    }
+
+    #[test]
+    fn test_choose() {
+        let policy = ComputeBudgetPolicy {
+            escalation: 1.5,
+            max_price: Some(1000),
+            ..Default::default()
+        };
+        assert_eq!(policy.choose(100, None), 100);
+        assert_eq!(policy.choose(100, Some(50)), 100);
+        assert_eq!(policy.choose(100, Some(100)), 150);
+        assert_eq!(policy.choose(100, Some(101)), 152);
+        assert_eq!(policy.choose(100, Some(900)), 1000);
+        assert_eq!(policy.choose(2000, None), 1000);
+
+        assert_eq!(ComputeBudgetPolicy::default().choose(0, Some(100)), 100);
+    }
+
+    #[test]
+    fn test_estimated_unit_limit() {
+        let policy = ComputeBudgetPolicy {
+            unit_limit: 300_000,
+            ..Default::default()
+        };
+        assert_eq!(policy.estimated_unit_limit(None), 300_000);
+        assert_eq!(policy.estimated_unit_limit(Some(100_000)), 120_000);
+        assert_eq!(policy.estimated_unit_limit(Some(100_001)), 120_002);
+        assert_eq!(policy.estimated_unit_limit(Some(290_000)), 300_000);
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let policies: Vec<ComputeBudgetPolicy> = serde_json::from_value(serde_json::json!([
+            {},
+            { "unit-limit": 300000, "unit-margin": 0.5, "price": { "static": 5000 } },
+            { "price": { "percentile": 75 }, "escalation": 2.0, "max-price": 100000 },
+        ]))
+        .unwrap();
+
+        assert_eq!(policies[0].unit_limit, 1_400_000);
+        assert!(matches!(policies[0].price, PriceSource::Static(0)));
+        assert_eq!(policies[0].unit_margin, 0.2);
+        assert_eq!(policies[1].unit_limit, 300_000);
+        assert_eq!(policies[1].unit_margin, 0.5);
+        assert!(matches!(policies[1].price, PriceSource::Static(5000)));
+        assert!(matches!(policies[2].price, PriceSource::Percentile(75)));
+        assert_eq!(policies[2].escalation, 2.0);
+        assert_eq!(policies[2].max_price, Some(100_000));
+    }
+}
@@ -0,0 +1,167 @@
+//! Events emitted with an event instruction (`vrf_lib::event`), read from the inner instructions of the transaction.
+//!
+//! Fetching the transaction costs a `getTransaction` call, so it is only done when the logs show a configured program
+//! invoked by a program, or when they were truncated. The events in the logs are the fallback, e.g. for programs
+//! emitting with `emit!`.
+
+use std::str::FromStr;
+
+use anchor_client::{
+    solana_client::{nonblocking::rpc_client::RpcClient, rpc_config::RpcTransactionConfig},
+    solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature},
+};
+use solana_transaction_status::{
+    EncodedTransaction, UiInnerInstructions, UiInstruction, UiMessage, UiTransactionEncoding, UiTransactionStatusMeta,
+};
+
+use crate::parse_log::{self, Event};
+
+/// Whether the transaction may hold event instructions of `program_ids`
+pub fn may_be_emitted<S: AsRef<str>>(logs: &[S], program_ids: &[Pubkey]) -> bool {
+    let (parsed, _) = parse_log::parse(logs);
+    let program_ids = program_ids.iter().map(|program_id| program_id.to_string()).collect::<Vec<_>>();
+
+    parsed.truncated
+        || parsed
+            .iter()
+            .any(|invocation| invocation.depth > 1 && program_ids.contains(&invocation.program_id))
+}
+
+/// Events of `program_ids` in the inner instructions of `transaction`
+pub async fn fetch(
+    rpc_client: &RpcClient,
+    transaction: &str,
+    program_ids: &[Pubkey],
+    commitment: CommitmentConfig,
+) -> anyhow::Result<Vec<Event>> {
+    // `getTransaction` does not support the processed commitment
+    let commitment = if commitment.is_at_least_confirmed() {
+        commitment
+    } else {
+        CommitmentConfig::confirmed()
+    };
+
+    let encoded_transaction = rpc_client
+        .get_transaction_with_config(
+            &Signature::from_str(transaction)?,
+            RpcTransactionConfig {
+                encoding: Some(UiTransactionEncoding::Json),
+                commitment: Some(commitment),
+                ..Default::default()
+            },
+        )
+        .await?
+        .transaction;
+
+    let account_keys = match encoded_transaction.transaction {
+        EncodedTransaction::Json(transaction) => match transaction.message {
+            UiMessage::Raw(message) => message.account_keys,
+            UiMessage::Parsed(_) => anyhow::bail!("Parsed message"),
+        },
+        _ => anyhow::bail!("Transaction not encoded as JSON"),
+    };
+    let inner_instructions = match encoded_transaction.meta {
+        Some(UiTransactionStatusMeta {
+            inner_instructions: Some(inner_instructions),
+            ..
+        }) => inner_instructions,
+        _ => return Ok(Vec::new()),
+    };
+
+    Ok(decode(&account_keys, &inner_instructions, program_ids))
+}
+
+/// Only the static account keys are resolved, the event instructions of a program loaded from an address lookup table
+/// are left to the logs
+fn decode(account_keys: &[String], inner_instructions: &[UiInnerInstructions], program_ids: &[Pubkey]) -> Vec<Event> {
+    let key = |index: u8| account_keys.get(usize::from(index)).and_then(|key| Pubkey::from_str(key).ok());
+
+    let mut events = Vec::new();
+    for instruction in inner_instructions.iter().flat_map(|inner| &inner.instructions) {
+        let instruction = match instruction {
+            UiInstruction::Compiled(instruction) => instruction,
+            UiInstruction::Parsed(_) => continue,
+        };
+
+        let program_id = match key(instruction.program_id_index) {
+            Some(program_id) if program_ids.contains(&program_id) => program_id,
+            _ => continue,
+        };
+
+        // Only the program can sign with its event authority
+        let authority = instruction.accounts.first().and_then(|index| key(*index));
+        if authority != Some(vrf_lib::event::event_authority(&program_id).0) {
+            continue;
+        }
+
+        let data = match bs58::decode(&instruction.data).into_vec() {
+            Ok(data) => data,
+            Err(_) => continue,
+        };
+        if let Some(data) = data.strip_prefix(&vrf_lib::event::EVENT_IX_TAG.to_le_bytes()[..]) {
+            events.push(Event {
+                program_id,
+                data: data.to_vec(),
+            });
+        }
+    }
+
+    events
+}
+
+#[cfg(test)]
+mod test {
+    use anchor_client::anchor_lang::Discriminator;
+    use solana_transaction_status::UiCompiledInstruction;
+
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        let program_id = Pubkey::new_unique();
+        let (event_authority, _) = vrf_lib::event::event_authority(&program_id);
+        let other_program_id = Pubkey::new_unique();
+        let account_keys = [Pubkey::new_unique(), program_id, event_authority, other_program_id]
+            .iter()
+            .map(Pubkey::to_string)
+            .collect::<Vec<_>>();
+
+        let event = vrf_lib::RequestVrf {
+            ix_sighash: [1; 8],
+            ix_data: vec![2, 3],
+            accounts: vec![],
+        };
+        let data = bs58::encode(vrf_lib::event::instruction_data(&event)).into_string();
+        let instruction = |program_id_index: u8, accounts: Vec<u8>, data: &str| {
+            UiInstruction::Compiled(UiCompiledInstruction {
+                program_id_index,
+                accounts,
+                data: data.to_string(),
+            })
+        };
+        let inner_instructions = [UiInnerInstructions {
+            index: 0,
+            instructions: vec![
+                instruction(3, vec![0], "3Bxs4h24hBtQy9rw"),
+                // Not signed by the event authority
+                instruction(1, vec![0], &data),
+                instruction(1, vec![2], &data),
+                instruction(3, vec![2], &data),
+            ],
+        }];
+
+        let events = decode(&account_keys, &inner_instructions, &[program_id]);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].program_id, program_id);
+        assert_eq!(events[0].data[..8], vrf_lib::RequestVrf::discriminator());
+
+        let invoke = |program_id: &Pubkey, depth: usize| format!("Program {program_id} invoke [{depth}]");
+        let logs = [invoke(&program_id, 1), invoke(&other_program_id, 2)];
+        assert!(!may_be_emitted(&logs, &[program_id]));
+        assert!(may_be_emitted(&[invoke(&program_id, 1), invoke(&program_id, 2)], &[program_id]));
+        assert!(may_be_emitted(
+            &[invoke(&program_id, 1), "Log truncated".to_string()],
+            &[program_id]
+        ));
+    }
+}
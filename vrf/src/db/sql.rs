@@ -0,0 +1,882 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use diesel::{
+    prelude::*,
+    r2d2::{self, ConnectionManager, R2D2Connection},
+    result::DatabaseErrorKind,
+    serialize::ToSql,
+    sql_types::Integer,
+    MysqlConnection, PgConnection, SqliteConnection,
+};
+
+use super::{BackfillCursor, ProcessedTransaction, Request, RequestError, RetryableTransaction, Status, Storage, StuckTransaction};
+
+impl ToSql<Integer, diesel::mysql::Mysql> for Status {
+    fn to_sql<'b>(&'b self, out: &mut diesel::serialize::Output<'b, '_, diesel::mysql::Mysql>) -> diesel::serialize::Result {
+        let v = *self as i32;
+        <i32 as ToSql<Integer, diesel::mysql::Mysql>>::to_sql(&v, &mut out.reborrow())
+    }
+}
+
+impl ToSql<Integer, diesel::pg::Pg> for Status {
+    fn to_sql<'b>(&'b self, out: &mut diesel::serialize::Output<'b, '_, diesel::pg::Pg>) -> diesel::serialize::Result {
+        let v = *self as i32;
+        <i32 as ToSql<Integer, diesel::pg::Pg>>::to_sql(&v, &mut out.reborrow())
+    }
+}
+
+impl ToSql<Integer, diesel::sqlite::Sqlite> for Status {
+    fn to_sql<'b>(&'b self, out: &mut diesel::serialize::Output<'b, '_, diesel::sqlite::Sqlite>) -> diesel::serialize::Result {
+        out.set_value(*self as i32);
+        Ok(diesel::serialize::IsNull::No)
+    }
+}
+
+mod schema {
+    diesel::table! {
+        vrf (id) {
+            id -> Integer,
+            program_id -> Varchar,
+            transaction -> Varchar,
+            event_index -> Integer,
+            status -> Integer,
+            outcome -> Nullable<Varchar>,
+            vrf_seeds -> Nullable<Blob>,
+            vrf_proof -> Nullable<Blob>,
+            response_transaction -> Nullable<Varchar>,
+            log_messages -> Text,
+            errors -> Nullable<Text>,
+            error_code -> Nullable<Varchar>,
+            program_error_code -> Nullable<BigInt>,
+            compute_unit_price -> Nullable<BigInt>,
+            simulation_logs -> Nullable<Text>,
+            units_consumed -> Nullable<BigInt>,
+            attempts -> Integer,
+            next_attempt_at -> Nullable<Timestamp>,
+            owner_instance -> Nullable<Varchar>,
+            lease_expires_at -> Nullable<Timestamp>,
+            time_create -> Timestamp,
+            time_update -> Timestamp,
+        }
+    }
+
+    diesel::table! {
+        leader_lease (name) {
+            name -> Varchar,
+            owner -> Varchar,
+            expires_at -> Timestamp,
+        }
+    }
+
+    diesel::table! {
+        backfill_cursor (program_id) {
+            program_id -> Varchar,
+            slot -> BigInt,
+            signature -> Varchar,
+            time_update -> Timestamp,
+        }
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = schema::vrf)]
+struct NewTrans<'a> {
+    program_id: &'a str,
+    transaction: &'a str,
+    event_index: i32,
+    status: Status,
+    log_messages: &'a str,
+}
+
+type RequestRow = (
+    String,
+    String,
+    i32,
+    i32,
+    Option<String>,
+    Option<Vec<u8>>,
+    Option<Vec<u8>>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<i64>,
+    Option<String>,
+    Option<i64>,
+    i32,
+    Option<chrono::NaiveDateTime>,
+    chrono::NaiveDateTime,
+    chrono::NaiveDateTime,
+);
+
+/// Diesel storage over a connection pool of `C`
+pub struct SqlStorage<C: R2D2Connection + 'static> {
+    pool: r2d2::Pool<ConnectionManager<C>>,
+    /// Migrations directory of the backend, relative to the working directory
+    migrations: &'static str,
+}
+
+impl SqlStorage<MysqlConnection> {
+    pub fn new(database_url: &str) -> Result<Self> {
+        Self::with_pool(database_url, "migrations/mysql", r2d2::Pool::builder())
+    }
+}
+
+impl SqlStorage<PgConnection> {
+    pub fn new(database_url: &str) -> Result<Self> {
+        Self::with_pool(database_url, "migrations/postgres", r2d2::Pool::builder())
+    }
+}
+
+impl SqlStorage<SqliteConnection> {
+    /// `path` of the database file, or `:memory:`
+    pub fn new(path: &str) -> Result<Self> {
+        // A single connection kept forever: SQLite allows one writer, and every `:memory:` connection is another database
+        let builder = r2d2::Pool::builder().max_size(1).idle_timeout(None).max_lifetime(None);
+        Self::with_pool(path, "migrations/sqlite", builder)
+    }
+}
+
+impl<C: R2D2Connection + 'static> SqlStorage<C> {
+    fn with_pool(database_url: &str, migrations: &'static str, builder: r2d2::Builder<ConnectionManager<C>>) -> Result<Self> {
+        let pool = builder
+            .connection_timeout(Duration::from_secs(5))
+            .test_on_check_out(true)
+            .build(ConnectionManager::<C>::new(database_url))?;
+
+        Ok(Self { pool, migrations })
+    }
+
+    fn connection(&self) -> Result<r2d2::PooledConnection<ConnectionManager<C>>> {
+        Ok(self.pool.get()?)
+    }
+}
+
+fn expect_one_row(row_affected: usize) -> Result<()> {
+    if row_affected == 1 {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("No row affected"))
+    }
+}
+
+/// Columns of `RequestRow`
+macro_rules! request_columns {
+    () => {{
+        use schema::vrf::dsl;
+        (
+            dsl::program_id,
+            dsl::transaction,
+            dsl::event_index,
+            // `status` is an ENUM on MySQL, adding 0 gives its index which is the `Status` value
+            diesel::dsl::sql::<Integer>("status + 0"),
+            dsl::outcome,
+            dsl::vrf_seeds,
+            dsl::vrf_proof,
+            dsl::response_transaction,
+            dsl::errors,
+            dsl::error_code,
+            dsl::program_error_code,
+            dsl::simulation_logs,
+            dsl::units_consumed,
+            dsl::attempts,
+            dsl::next_attempt_at,
+            dsl::time_create,
+            dsl::time_update,
+        )
+    }};
+}
+
+/// Statuses an operator can requeue
+const REQUEUEABLE: [Status; 4] = [Status::FatalError, Status::RetryableError, Status::DeadLetter, Status::Ignored];
+
+// The queries are the same for every backend, but diesel needs a concrete connection type to build them
+macro_rules! impl_storage {
+    ($connection:ty) => {
+        impl Storage for SqlStorage<$connection> {
+            fn run_migration(&self) -> Result<()> {
+                use diesel_migrations::{FileBasedMigrations, MigrationHarness};
+
+                let mut conn = self.connection()?;
+                let migration_source = FileBasedMigrations::from_path(self.migrations)?;
+                conn.run_pending_migrations(migration_source)
+                    .map_err(|err| anyhow::anyhow!("Run pending migrations: {err}"))?;
+                Ok(())
+            }
+
+            fn new_transaction(&self, program_id: &str, transaction: &str, log_messages: &str) -> Result<bool> {
+                let mut conn = self.connection()?;
+                let result = diesel::insert_into(schema::vrf::table)
+                    .values(&NewTrans {
+                        program_id,
+                        transaction,
+                        event_index: 0,
+                        status: Status::New,
+                        log_messages,
+                    })
+                    .execute(&mut conn);
+
+                match result {
+                    Ok(1) => Ok(true),
+                    Ok(_) => Err(anyhow::anyhow!("No row affected")),
+                    Err(diesel::result::Error::DatabaseError(
+                        DatabaseErrorKind::CheckViolation | DatabaseErrorKind::UniqueViolation,
+                        _,
+                    )) => Ok(false),
+                    Err(err) => Err(err.into()),
+                }
+            }
+
+            fn new_events(&self, program_id: &str, transaction: &str, log_messages: &str, count: usize) -> Result<Vec<i32>> {
+                let mut conn = self.connection()?;
+                let mut inserted = Vec::new();
+                for event_index in 1..i32::try_from(count)? {
+                    let result = diesel::insert_into(schema::vrf::table)
+                        .values(&NewTrans {
+                            program_id,
+                            transaction,
+                            event_index,
+                            status: Status::New,
+                            log_messages,
+                        })
+                        .execute(&mut conn);
+
+                    match result {
+                        Ok(_) => inserted.push(event_index),
+                        // Stored by a previous attempt
+                        Err(diesel::result::Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => {}
+                        Err(err) => return Err(err.into()),
+                    }
+                }
+
+                Ok(inserted)
+            }
+
+            fn process_transaction(
+                &self,
+                program_id: &str,
+                transaction: &str,
+                event_index: i32,
+                owner: &str,
+                lease: Duration,
+            ) -> Result<bool> {
+                use schema::vrf::dsl;
+
+                let now = now();
+                let lease_expires_at = now + chrono::Duration::from_std(lease)?;
+                let mut conn = self.connection()?;
+                // A single conditional update, so of instances claiming the same transaction only one gets it
+                let result = diesel::update(
+                    dsl::vrf.filter(
+                        dsl::program_id
+                            .eq(program_id)
+                            .and(dsl::transaction.eq(transaction))
+                            .and(dsl::event_index.eq(event_index))
+                            // Failed requests come back through a requeue, retries once they are due and abandoned
+                            // attempts once their lease expired
+                            .and(
+                                dsl::status
+                                    .eq(Status::New)
+                                    .or(dsl::status
+                                        .eq(Status::RetryableError)
+                                        .and(dsl::next_attempt_at.is_null().or(dsl::next_attempt_at.le(now))))
+                                    .or(dsl::status
+                                        .eq(Status::Processing)
+                                        .and(dsl::lease_expires_at.is_null().or(dsl::lease_expires_at.le(now)))),
+                            ),
+                    ),
+                )
+                .set((
+                    dsl::status.eq(Status::Processing),
+                    dsl::owner_instance.eq(owner),
+                    dsl::lease_expires_at.eq(lease_expires_at),
+                    // Only a fulfillment of this attempt may be found on-chain by the recovery
+                    dsl::response_transaction.eq(None::<String>),
+                    dsl::time_update.eq(now),
+                ))
+                .execute(&mut conn);
+
+                match result {
+                    Ok(1) => Ok(true),
+                    Ok(_) => Ok(false),
+                    Err(diesel::result::Error::DatabaseError(
+                        DatabaseErrorKind::CheckViolation | DatabaseErrorKind::UniqueViolation,
+                        _,
+                    )) => Ok(false),
+                    Err(err) => Err(err.into()),
+                }
+            }
+
+            fn renew_lease(&self, program_id: &str, transaction: &str, event_index: i32, owner: &str, lease: Duration) -> Result<()> {
+                use schema::vrf::dsl;
+
+                let lease_expires_at = now() + chrono::Duration::from_std(lease)?;
+                let mut conn = self.connection()?;
+                let row_affected = diesel::update(
+                    dsl::vrf.filter(
+                        dsl::program_id
+                            .eq(program_id)
+                            .and(dsl::transaction.eq(transaction))
+                            .and(dsl::event_index.eq(event_index))
+                            .and(dsl::status.eq(Status::Processing))
+                            .and(dsl::owner_instance.eq(owner)),
+                    ),
+                )
+                .set(dsl::lease_expires_at.eq(lease_expires_at))
+                .execute(&mut conn)?;
+
+                expect_one_row(row_affected)
+            }
+
+            fn get_stuck_transaction(&self) -> Result<Vec<StuckTransaction>> {
+                use schema::vrf::dsl;
+
+                let mut conn = self.connection()?;
+                Ok(schema::vrf::table
+                    .select((
+                        dsl::program_id,
+                        dsl::transaction,
+                        dsl::event_index,
+                        dsl::response_transaction,
+                        dsl::vrf_seeds,
+                        dsl::vrf_proof,
+                        dsl::attempts,
+                    ))
+                    .filter(
+                        dsl::status
+                            .eq(Status::Processing)
+                            // Rows claimed before leases were recorded have no lease
+                            .and(dsl::lease_expires_at.is_null().or(dsl::lease_expires_at.le(now()))),
+                    )
+                    .limit(20)
+                    .load::<StuckTransaction>(&mut conn)?)
+            }
+
+            fn take_over_processing(
+                &self,
+                program_id: &str,
+                transaction: &str,
+                event_index: i32,
+                owner: &str,
+                lease: Duration,
+            ) -> Result<bool> {
+                use schema::vrf::dsl;
+
+                let now = now();
+                let mut conn = self.connection()?;
+                let row_affected = diesel::update(
+                    dsl::vrf.filter(
+                        dsl::program_id
+                            .eq(program_id)
+                            .and(dsl::transaction.eq(transaction))
+                            .and(dsl::event_index.eq(event_index))
+                            .and(dsl::status.eq(Status::Processing))
+                            .and(dsl::lease_expires_at.is_null().or(dsl::lease_expires_at.le(now))),
+                    ),
+                )
+                .set((
+                    dsl::owner_instance.eq(owner),
+                    dsl::lease_expires_at.eq(now + chrono::Duration::from_std(lease)?),
+                ))
+                .execute(&mut conn)?;
+
+                Ok(row_affected == 1)
+            }
+
+            fn complete_none_vrf_processing(&self, program_id: &str, transaction: &str, event_index: i32) -> Result<()> {
+                use schema::vrf::dsl;
+
+                let mut conn = self.connection()?;
+                let row_affected = diesel::update(
+                    dsl::vrf.filter(
+                        dsl::program_id
+                            .eq(program_id)
+                            .and(dsl::transaction.eq(transaction))
+                            .and(dsl::event_index.eq(event_index))
+                            .and(dsl::status.eq(Status::Processing)),
+                    ),
+                )
+                .set((
+                    dsl::status.eq(Status::Processed),
+                    dsl::outcome.eq("no_request"),
+                    dsl::time_update.eq(now()),
+                ))
+                .execute(&mut conn)?;
+
+                expect_one_row(row_affected)
+            }
+
+            fn complete_already_fulfilled(&self, program_id: &str, transaction: &str, event_index: i32) -> Result<()> {
+                use schema::vrf::dsl;
+
+                let mut conn = self.connection()?;
+                let row_affected = diesel::update(
+                    dsl::vrf.filter(
+                        dsl::program_id
+                            .eq(program_id)
+                            .and(dsl::transaction.eq(transaction))
+                            .and(dsl::event_index.eq(event_index))
+                            .and(dsl::status.eq(Status::Processing)),
+                    ),
+                )
+                .set((
+                    dsl::status.eq(Status::Processed),
+                    dsl::outcome.eq("already_fulfilled"),
+                    dsl::time_update.eq(now()),
+                ))
+                .execute(&mut conn)?;
+
+                expect_one_row(row_affected)
+            }
+
+            fn set_proof(
+                &self,
+                program_id: &str,
+                transaction: &str,
+                event_index: i32,
+                vrf_seeds: Vec<u8>,
+                vrf_proof: Vec<u8>,
+            ) -> Result<()> {
+                use schema::vrf::dsl;
+
+                let mut conn = self.connection()?;
+                let row_affected = diesel::update(
+                    dsl::vrf.filter(
+                        dsl::program_id
+                            .eq(program_id)
+                            .and(dsl::transaction.eq(transaction))
+                            .and(dsl::event_index.eq(event_index))
+                            .and(dsl::status.eq(Status::Processing)),
+                    ),
+                )
+                .set((dsl::vrf_seeds.eq(vrf_seeds), dsl::vrf_proof.eq(vrf_proof)))
+                .execute(&mut conn)?;
+
+                expect_one_row(row_affected)
+            }
+
+            fn set_response_transaction(
+                &self,
+                program_id: &str,
+                transaction: &str,
+                event_index: i32,
+                response_transaction: String,
+                vrf_seeds: Vec<u8>,
+                vrf_proof: Vec<u8>,
+            ) -> Result<()> {
+                use schema::vrf::dsl;
+
+                let mut conn = self.connection()?;
+                let row_affected = diesel::update(
+                    dsl::vrf.filter(
+                        dsl::program_id
+                            .eq(program_id)
+                            .and(dsl::transaction.eq(transaction))
+                            .and(dsl::event_index.eq(event_index))
+                            .and(dsl::status.eq(Status::Processing)),
+                    ),
+                )
+                .set((
+                    dsl::vrf_seeds.eq(vrf_seeds),
+                    dsl::vrf_proof.eq(vrf_proof),
+                    dsl::response_transaction.eq(response_transaction),
+                ))
+                .execute(&mut conn)?;
+
+                expect_one_row(row_affected)
+            }
+
+            fn complete_processing(
+                &self,
+                program_id: &str,
+                transaction: &str,
+                event_index: i32,
+                response_transaction: String,
+                vrf_seeds: Vec<u8>,
+                vrf_proof: Vec<u8>,
+            ) -> Result<()> {
+                use schema::vrf::dsl;
+
+                let mut conn = self.connection()?;
+                let row_affected = diesel::update(
+                    dsl::vrf.filter(
+                        dsl::program_id
+                            .eq(program_id)
+                            .and(dsl::transaction.eq(transaction))
+                            .and(dsl::event_index.eq(event_index))
+                            .and(dsl::status.eq(Status::Processing)),
+                    ),
+                )
+                .set((
+                    dsl::status.eq(Status::Processed),
+                    dsl::outcome.eq("fulfilled"),
+                    dsl::vrf_seeds.eq(vrf_seeds),
+                    dsl::vrf_proof.eq(vrf_proof),
+                    dsl::response_transaction.eq(response_transaction),
+                    dsl::time_update.eq(now()),
+                ))
+                .execute(&mut conn)?;
+
+                expect_one_row(row_affected)
+            }
+
+            fn error_processing(
+                &self,
+                program_id: &str,
+                transaction: &str,
+                event_index: i32,
+                error: RequestError,
+                retry_after: Option<Duration>,
+            ) -> Result<()> {
+                use schema::vrf::dsl;
+
+                let (status, next_attempt_at) = match retry_after {
+                    _ if error.is_fatal => (Status::FatalError, None),
+                    Some(retry_after) => (Status::RetryableError, Some(now() + chrono::Duration::from_std(retry_after)?)),
+                    None => (Status::DeadLetter, None),
+                };
+
+                let mut conn = self.connection()?;
+                let row_affected = diesel::update(
+                    dsl::vrf.filter(
+                        dsl::program_id
+                            .eq(program_id)
+                            .and(dsl::transaction.eq(transaction))
+                            .and(dsl::event_index.eq(event_index))
+                            .and(dsl::status.eq(Status::Processing)),
+                    ),
+                )
+                .set((
+                    dsl::status.eq(status),
+                    dsl::attempts.eq(dsl::attempts + 1),
+                    dsl::next_attempt_at.eq(next_attempt_at),
+                    dsl::errors.eq(error.message),
+                    dsl::error_code.eq(error.code),
+                    dsl::program_error_code.eq(error.program_error_code.map(i64::from)),
+                    dsl::time_update.eq(now()),
+                ))
+                .execute(&mut conn)?;
+
+                expect_one_row(row_affected)
+            }
+
+            fn set_compute_unit_price(&self, program_id: &str, transaction: &str, event_index: i32, compute_unit_price: u64) -> Result<()> {
+                use schema::vrf::dsl;
+
+                let mut conn = self.connection()?;
+                let row_affected = diesel::update(
+                    dsl::vrf.filter(
+                        dsl::program_id
+                            .eq(program_id)
+                            .and(dsl::transaction.eq(transaction))
+                            .and(dsl::event_index.eq(event_index))
+                            .and(dsl::status.eq(Status::Processing)),
+                    ),
+                )
+                .set(dsl::compute_unit_price.eq(i64::try_from(compute_unit_price)?))
+                .execute(&mut conn)?;
+
+                expect_one_row(row_affected)
+            }
+
+            fn set_simulation(
+                &self,
+                program_id: &str,
+                transaction: &str,
+                event_index: i32,
+                logs: String,
+                units_consumed: Option<u64>,
+            ) -> Result<()> {
+                use schema::vrf::dsl;
+
+                let mut conn = self.connection()?;
+                let row_affected = diesel::update(
+                    dsl::vrf.filter(
+                        dsl::program_id
+                            .eq(program_id)
+                            .and(dsl::transaction.eq(transaction))
+                            .and(dsl::event_index.eq(event_index))
+                            .and(dsl::status.eq(Status::Processing)),
+                    ),
+                )
+                .set((
+                    dsl::simulation_logs.eq(logs),
+                    dsl::units_consumed.eq(units_consumed.map(i64::try_from).transpose()?),
+                ))
+                .execute(&mut conn)?;
+
+                expect_one_row(row_affected)
+            }
+
+            fn get_retryable_transaction(&self) -> Result<Vec<RetryableTransaction>> {
+                use schema::vrf::dsl;
+
+                let mut conn = self.connection()?;
+                Ok(schema::vrf::table
+                    .select((
+                        dsl::program_id,
+                        dsl::transaction,
+                        dsl::event_index,
+                        dsl::log_messages,
+                        dsl::compute_unit_price,
+                        dsl::vrf_seeds,
+                        dsl::vrf_proof,
+                        dsl::attempts,
+                    ))
+                    .filter(
+                        dsl::status
+                            .eq(Status::RetryableError)
+                            // Rows failed before attempts were counted have no next attempt time
+                            .and(dsl::next_attempt_at.is_null().or(dsl::next_attempt_at.le(now()))),
+                    )
+                    .limit(20)
+                    .load::<RetryableTransaction>(&mut conn)?)
+            }
+
+            fn get_processed_transaction(
+                &self,
+                program_id: &str,
+                transaction: &str,
+                event_index: i32,
+            ) -> Result<Option<ProcessedTransaction>> {
+                use schema::vrf::dsl;
+
+                let mut conn = self.connection()?;
+                Ok(schema::vrf::table
+                    .select((
+                        dsl::program_id,
+                        dsl::transaction,
+                        dsl::event_index,
+                        dsl::vrf_seeds,
+                        dsl::vrf_proof,
+                        dsl::response_transaction,
+                    ))
+                    .filter(
+                        dsl::program_id
+                            .eq(program_id)
+                            .and(dsl::transaction.eq(transaction))
+                            .and(dsl::event_index.eq(event_index))
+                            .and(dsl::status.eq(Status::Processed)),
+                    )
+                    .first::<ProcessedTransaction>(&mut conn)
+                    .optional()?)
+            }
+
+            fn get_requests(&self, transaction: &str) -> Result<Vec<Request>> {
+                use schema::vrf::dsl;
+
+                let mut conn = self.connection()?;
+                let rows = schema::vrf::table
+                    .select(request_columns!())
+                    .filter(dsl::transaction.eq(transaction))
+                    .order(dsl::id.asc())
+                    .load::<RequestRow>(&mut conn)?;
+
+                rows.into_iter().map(request_from_row).collect()
+            }
+
+            fn list_requests(&self, status: Option<Status>, program_id: Option<&str>, limit: usize) -> Result<Vec<Request>> {
+                use schema::vrf::dsl;
+
+                let mut query = schema::vrf::table.select(request_columns!()).into_boxed();
+                if let Some(status) = status {
+                    query = query.filter(dsl::status.eq(status));
+                }
+                if let Some(program_id) = program_id {
+                    query = query.filter(dsl::program_id.eq(program_id));
+                }
+
+                let mut conn = self.connection()?;
+                let rows = query
+                    .order(dsl::time_update.desc())
+                    .limit(limit as i64)
+                    .load::<RequestRow>(&mut conn)?;
+
+                rows.into_iter().map(request_from_row).collect()
+            }
+
+            fn requeue(&self, transaction: &str, program_id: Option<&str>) -> Result<usize> {
+                use schema::vrf::dsl;
+
+                let mut query = diesel::update(schema::vrf::table).into_boxed();
+                if let Some(program_id) = program_id {
+                    query = query.filter(dsl::program_id.eq(program_id));
+                }
+
+                let mut conn = self.connection()?;
+                Ok(query
+                    .filter(dsl::transaction.eq(transaction).and(dsl::status.eq_any(REQUEUEABLE)))
+                    .set((
+                        dsl::status.eq(Status::RetryableError),
+                        dsl::attempts.eq(0),
+                        dsl::next_attempt_at.eq(None::<chrono::NaiveDateTime>),
+                        dsl::time_update.eq(now()),
+                    ))
+                    .execute(&mut conn)?)
+            }
+
+            fn requeue_failed_since(&self, since: chrono::NaiveDateTime, program_id: Option<&str>) -> Result<usize> {
+                use schema::vrf::dsl;
+
+                let mut query = diesel::update(schema::vrf::table).into_boxed();
+                if let Some(program_id) = program_id {
+                    query = query.filter(dsl::program_id.eq(program_id));
+                }
+
+                let mut conn = self.connection()?;
+                Ok(query
+                    .filter(
+                        dsl::status
+                            .eq_any([Status::FatalError, Status::DeadLetter])
+                            .and(dsl::time_update.ge(since)),
+                    )
+                    .set((
+                        dsl::status.eq(Status::RetryableError),
+                        dsl::attempts.eq(0),
+                        dsl::next_attempt_at.eq(None::<chrono::NaiveDateTime>),
+                        dsl::time_update.eq(now()),
+                    ))
+                    .execute(&mut conn)?)
+            }
+
+            fn mark_ignored(&self, transaction: &str, program_id: Option<&str>) -> Result<usize> {
+                use schema::vrf::dsl;
+
+                let mut query = diesel::update(schema::vrf::table).into_boxed();
+                if let Some(program_id) = program_id {
+                    query = query.filter(dsl::program_id.eq(program_id));
+                }
+
+                let mut conn = self.connection()?;
+                Ok(query
+                    .filter(dsl::transaction.eq(transaction).and(dsl::status.eq_any([
+                        Status::FatalError,
+                        Status::RetryableError,
+                        Status::DeadLetter,
+                    ])))
+                    .set((
+                        dsl::status.eq(Status::Ignored),
+                        dsl::next_attempt_at.eq(None::<chrono::NaiveDateTime>),
+                        dsl::time_update.eq(now()),
+                    ))
+                    .execute(&mut conn)?)
+            }
+
+            fn acquire_leadership(&self, name: &str, owner: &str, lease: Duration) -> Result<bool> {
+                use schema::leader_lease::dsl;
+
+                let now = now();
+                let expires_at = now + chrono::Duration::from_std(lease)?;
+                let mut conn = self.connection()?;
+                let row_affected =
+                    diesel::update(dsl::leader_lease.filter(dsl::name.eq(name).and(dsl::owner.eq(owner).or(dsl::expires_at.le(now)))))
+                        .set((dsl::owner.eq(owner), dsl::expires_at.eq(expires_at)))
+                        .execute(&mut conn)?;
+                if row_affected == 1 {
+                    return Ok(true);
+                }
+
+                // Nobody was ever leader, or another instance is
+                let result = diesel::insert_into(schema::leader_lease::table)
+                    .values((dsl::name.eq(name), dsl::owner.eq(owner), dsl::expires_at.eq(expires_at)))
+                    .execute(&mut conn);
+
+                match result {
+                    Ok(_) => Ok(true),
+                    Err(diesel::result::Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => Ok(false),
+                    Err(err) => Err(err.into()),
+                }
+            }
+
+            fn get_backfill_cursor(&self, program_id: &str) -> Result<Option<BackfillCursor>> {
+                use schema::backfill_cursor::dsl;
+
+                let mut conn = self.connection()?;
+                Ok(schema::backfill_cursor::table
+                    .select((dsl::slot, dsl::signature))
+                    .filter(dsl::program_id.eq(program_id))
+                    .first::<BackfillCursor>(&mut conn)
+                    .optional()?)
+            }
+
+            fn set_backfill_cursor(&self, program_id: &str, cursor: &BackfillCursor) -> Result<()> {
+                use schema::backfill_cursor::dsl;
+
+                let mut conn = self.connection()?;
+                let values = (
+                    dsl::slot.eq(cursor.slot),
+                    dsl::signature.eq(&cursor.signature),
+                    dsl::time_update.eq(now()),
+                );
+                let row_affected = diesel::update(dsl::backfill_cursor.filter(dsl::program_id.eq(program_id)))
+                    .set(values)
+                    .execute(&mut conn)?;
+                if row_affected > 0 {
+                    return Ok(());
+                }
+
+                let result = diesel::insert_into(schema::backfill_cursor::table)
+                    .values((dsl::program_id.eq(program_id), values))
+                    .execute(&mut conn);
+
+                match result {
+                    // MySQL counts the rows changed, not matched, the cursor was already there
+                    Ok(_) | Err(diesel::result::Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => Ok(()),
+                    Err(err) => Err(err.into()),
+                }
+            }
+        }
+    };
+}
+
+impl_storage!(MysqlConnection);
+impl_storage!(PgConnection);
+impl_storage!(SqliteConnection);
+
+/// Times are written by the server in UTC, as `get_retryable_transaction` and `get_stuck_transaction` compare
+/// `next_attempt_at` and `lease_expires_at` with the server clock
+fn now() -> chrono::NaiveDateTime {
+    chrono::Utc::now().naive_utc()
+}
+
+fn request_from_row(
+    (
+        program_id,
+        transaction,
+        event_index,
+        status,
+        outcome,
+        vrf_seeds,
+        vrf_proof,
+        response_transaction,
+        errors,
+        error_code,
+        program_error_code,
+        simulation_logs,
+        units_consumed,
+        attempts,
+        next_attempt_at,
+        time_create,
+        time_update,
+    ): RequestRow,
+) -> Result<Request> {
+    Ok(Request {
+        program_id,
+        transaction,
+        event_index,
+        status: Status::try_from(status)?,
+        outcome,
+        vrf_seeds,
+        vrf_proof,
+        response_transaction,
+        errors,
+        error_code,
+        program_error_code,
+        simulation_logs,
+        units_consumed,
+        attempts,
+        next_attempt_at,
+        time_create,
+        time_update,
+    })
+}
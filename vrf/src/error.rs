@@ -1,46 +1,233 @@
-pub struct ProcessError {
-    pub is_fatal: bool,
-    pub error: anyhow::Error,
+use anchor_client::{
+    solana_client::client_error::ClientError,
+    solana_sdk::{pubkey::Pubkey, signer::SignerError, transaction::TransactionError},
+};
+
+use crate::{db::RequestError, parse_log::ParseLogError, program_error::Classification};
+
+/// Whether a failed request is processed again
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryPolicy {
+    Never,
+    Retry,
+}
+
+#[derive(Debug)]
+pub enum ProcessError {
+    /// RPC request failing or timing out, not an answer about the request
+    Rpc(ClientError),
+    ParseLog(Vec<ParseLogError>),
+    DecodeEvent(std::io::Error),
+    NoRandomnessProvider(Pubkey),
+    Randomness(anyhow::Error),
+    /// The program's placeholder `VrfResult` is shorter than the one of this server
+    IncompatibleLayout {
+        ix_data_len: usize,
+        placeholder_len: usize,
+    },
+    /// The pending check of the program could not tell whether the request was fulfilled
+    PendingCheck(anyhow::Error),
+    FeePayersDrained,
+    FeePayerCanNotPay {
+        fee_payer: Pubkey,
+        error: ClientError,
+    },
+    Sign(SignerError),
+    /// Simulation or preflight of the fulfillment failed, with the preflight logs. Only logged, as they can tell the
+    /// outcome of a request which is not fulfilled yet
+    Simulation {
+        classification: Classification,
+        logs: Option<Vec<String>>,
+    },
+    /// The RPC node refused the fulfillment
+    SendTransaction(ClientError),
+    BlockhashExpired,
+    Db(anyhow::Error),
+    /// The instance processing the request stopped, and its fulfillment, if any was sent, did not land
+    Interrupted {
+        response_transaction: Option<String>,
+    },
+    /// The fulfillment sent by a stopped instance landed but failed
+    ResponseFailed {
+        response_transaction: String,
+        error: TransactionError,
+    },
 }
 
-impl<T> From<T> for ProcessError
-where
-    T: std::error::Error + Send + Sync + 'static,
-{
-    fn from(err: T) -> Self {
-        Self {
-            is_fatal: false,
-            error: anyhow::Error::from(err),
+impl ProcessError {
+    pub fn retry_policy(&self) -> RetryPolicy {
+        match self {
+            ProcessError::Rpc(_)
+            | ProcessError::PendingCheck(_)
+            | ProcessError::FeePayersDrained
+            | ProcessError::FeePayerCanNotPay { .. }
+            | ProcessError::Sign(_)
+            | ProcessError::BlockhashExpired
+            | ProcessError::Db(_)
+            | ProcessError::Interrupted { .. }
+            // Without logs the error can not be classified, the simulation of the next attempt does it
+            | ProcessError::ResponseFailed { .. } => RetryPolicy::Retry,
+            ProcessError::ParseLog(_)
+            | ProcessError::DecodeEvent(_)
+            | ProcessError::NoRandomnessProvider(_)
+            | ProcessError::Randomness(_)
+            | ProcessError::IncompatibleLayout { .. }
+            | ProcessError::SendTransaction(_) => RetryPolicy::Never,
+            ProcessError::Simulation { classification, .. } => {
+                if classification.is_fatal {
+                    RetryPolicy::Never
+                } else {
+                    RetryPolicy::Retry
+                }
+            }
+        }
+    }
+
+    pub fn is_fatal(&self) -> bool {
+        self.retry_policy() == RetryPolicy::Never
+    }
+
+    /// Stored in the `error_code` column
+    pub fn code(&self) -> &'static str {
+        match self {
+            ProcessError::Rpc(_) => "rpc",
+            ProcessError::ParseLog(_) => "parse_log",
+            ProcessError::DecodeEvent(_) => "decode_event",
+            ProcessError::NoRandomnessProvider(_) => "no_randomness_provider",
+            ProcessError::Randomness(_) => "randomness",
+            ProcessError::IncompatibleLayout { .. } => "incompatible_layout",
+            ProcessError::PendingCheck(_) => "pending_check",
+            ProcessError::FeePayersDrained => "fee_payers_drained",
+            ProcessError::FeePayerCanNotPay { .. } => "fee_payer_can_not_pay",
+            ProcessError::Sign(_) => "sign",
+            ProcessError::Simulation { .. } => "simulation",
+            ProcessError::SendTransaction(_) => "send_transaction",
+            ProcessError::BlockhashExpired => "blockhash_expired",
+            ProcessError::Db(_) => "db",
+            ProcessError::Interrupted { .. } => "interrupted",
+            ProcessError::ResponseFailed { .. } => "response_failed",
+        }
+    }
+
+    pub fn to_request_error(&self) -> RequestError {
+        RequestError {
+            is_fatal: self.is_fatal(),
+            code: self.code().to_string(),
+            program_error_code: match self {
+                ProcessError::Simulation { classification, .. } => classification.program_error_code,
+                _ => None,
+            },
+            message: match self {
+                ProcessError::Simulation { classification, .. } => format!("Simulation failed: {classification}"),
+                _ => self.to_string(),
+            },
         }
     }
 }
 
-pub trait FatalProcessError<T> {
-    fn fatal_error(self) -> Result<T, ProcessError>;
+impl std::fmt::Display for ProcessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProcessError::Rpc(err) => write!(f, "RPC error: {err}"),
+            ProcessError::ParseLog(errors) => {
+                for err in errors {
+                    writeln!(f, "{err}")?;
+                }
+                Ok(())
+            }
+            ProcessError::DecodeEvent(err) => write!(f, "Deserialize RequestVrf Event: {err}"),
+            ProcessError::NoRandomnessProvider(program_id) => write!(f, "No randomness provider for program {program_id}"),
+            ProcessError::Randomness(err) => write!(f, "Randomness provider error: {err:#}"),
+            ProcessError::IncompatibleLayout {
+                ix_data_len,
+                placeholder_len,
+            } => write!(
+                f,
+                "VrfResult incompatible layout: ix_data.len()={ix_data_len}, vrf_result.len()={placeholder_len}"
+            ),
+            ProcessError::PendingCheck(err) => write!(f, "Pending check error: {err:#}"),
+            ProcessError::FeePayersDrained => write!(f, "All fee payers are below the minimum balance"),
+            ProcessError::FeePayerCanNotPay { fee_payer, error } => write!(f, "Fee payer {fee_payer} can not pay: {error}"),
+            ProcessError::Sign(err) => write!(f, "Sign error: {err}"),
+            ProcessError::Simulation { classification, logs } => {
+                write!(f, "Simulation failed: {classification}")?;
+                if let Some(logs) = logs {
+                    writeln!(f, "\nSimulation error logs:")?;
+                    for log in logs {
+                        writeln!(f, "\t{log}")?;
+                    }
+                }
+                Ok(())
+            }
+            ProcessError::SendTransaction(err) => write!(f, "Send transaction error: {err}"),
+            ProcessError::BlockhashExpired => write!(f, "Failed to send transaction, blockhash expired"),
+            ProcessError::Db(err) => write!(f, "[DB] {err:#}"),
+            ProcessError::Interrupted {
+                response_transaction: None,
+            } => write!(f, "Processing interrupted before sending the fulfillment"),
+            ProcessError::Interrupted {
+                response_transaction: Some(response_transaction),
+            } => write!(f, "Processing interrupted, fulfillment {response_transaction} did not land"),
+            ProcessError::ResponseFailed {
+                response_transaction,
+                error,
+            } => write!(f, "Fulfillment {response_transaction} failed: {error}"),
+        }
+    }
+}
+
+impl From<ClientError> for ProcessError {
+    fn from(err: ClientError) -> Self {
+        ProcessError::Rpc(err)
+    }
+}
 
-    fn fatal_error_with_context<S>(self, context: S) -> Result<T, ProcessError>
-    where
-        S: std::fmt::Display + Send + Sync + 'static;
+impl From<SignerError> for ProcessError {
+    fn from(err: SignerError) -> Self {
+        ProcessError::Sign(err)
+    }
 }
 
-impl<T, E> FatalProcessError<T> for Result<T, E>
-where
-    E: std::error::Error + Send + Sync + 'static,
-{
-    fn fatal_error(self) -> Result<T, ProcessError> {
-        self.map_err(|err| ProcessError {
-            is_fatal: true,
-            error: anyhow::Error::new(err),
-        })
-    }
-
-    fn fatal_error_with_context<S>(self, context: S) -> Result<T, ProcessError>
-    where
-        S: std::fmt::Display + Send + Sync + 'static,
-    {
-        self.map_err(|err| ProcessError {
-            is_fatal: true,
-            error: anyhow::Error::new(err).context(context),
-        })
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_request_error() {
+        let error = ProcessError::IncompatibleLayout {
+            ix_data_len: 1,
+            placeholder_len: 2,
+        }
+        .to_request_error();
+        assert!(error.is_fatal);
+        assert_eq!(error.code, "incompatible_layout");
+        assert_eq!(error.program_error_code, None);
+        assert_eq!(error.message, "VrfResult incompatible layout: ix_data.len()=1, vrf_result.len()=2");
+
+        assert_eq!(ProcessError::BlockhashExpired.retry_policy(), RetryPolicy::Retry);
+
+        let simulation = |is_fatal| ProcessError::Simulation {
+            classification: Classification {
+                is_fatal,
+                program_error_code: Some(6000),
+                description: "program error 6000 InvalidStage".to_string(),
+            },
+            logs: Some(vec!["Program log: won".to_string()]),
+        };
+        assert_eq!(simulation(false).retry_policy(), RetryPolicy::Retry);
+        assert!(simulation(false).to_string().contains("Program log: won"));
+        let error = simulation(true).to_request_error();
+        assert!(error.is_fatal);
+        assert_eq!(error.code, "simulation");
+        assert_eq!(error.program_error_code, Some(6000));
+        assert_eq!(error.message, "Simulation failed: fatal, program error 6000 InvalidStage");
+
+        let error = ProcessError::Interrupted {
+            response_transaction: Some("response".to_string()),
+        }
+        .to_request_error();
+        assert!(!error.is_fatal);
+        assert_eq!(error.code, "interrupted");
+        assert_eq!(error.message, "Processing interrupted, fulfillment response did not land");
     }
 }
@@ -0,0 +1,135 @@
+//! Fee payers of the fulfillment transactions, used in turn so no single hot wallet needs to hold much SOL.
+//!
+//! Payers whose last known balance is below `fee-payer-min-balance` are skipped until a refresh shows them funded
+//! again, so a drained payer does not stop fulfillments while another one can pay.
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use anchor_client::{
+    solana_client::nonblocking::rpc_client::RpcClient,
+    solana_sdk::{pubkey::Pubkey, signer::Signer},
+};
+
+use crate::keys::KeySigner;
+
+#[derive(Debug)]
+struct FeePayer {
+    signer: KeySigner,
+    pubkey: Pubkey,
+    /// Lamports, `u64::MAX` until the first refresh
+    balance: AtomicU64,
+}
+
+#[derive(Debug)]
+pub struct FeePayers {
+    payers: Vec<FeePayer>,
+    next: AtomicUsize,
+    min_balance: u64,
+}
+
+impl FeePayers {
+    pub fn new(signers: Vec<KeySigner>, min_balance: u64) -> Self {
+        Self {
+            payers: signers
+                .into_iter()
+                .map(|signer| FeePayer {
+                    pubkey: signer.pubkey(),
+                    signer,
+                    balance: AtomicU64::new(u64::MAX),
+                })
+                .collect(),
+            next: AtomicUsize::new(0),
+            min_balance,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.payers.is_empty()
+    }
+
+    pub fn pubkeys(&self) -> impl Iterator<Item = &Pubkey> {
+        self.payers.iter().map(|payer| &payer.pubkey)
+    }
+
+    /// The next payer in turn with enough balance, `None` if all are drained
+    pub fn next(&self) -> Option<&KeySigner> {
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        (0..self.payers.len())
+            .map(|offset| &self.payers[(start + offset) % self.payers.len()])
+            .find(|payer| payer.balance.load(Ordering::Relaxed) >= self.min_balance)
+            .map(|payer| &payer.signer)
+    }
+
+    /// Skip `pubkey` until the next refresh, after a transaction failed for lack of funds
+    pub fn mark_drained(&self, pubkey: &Pubkey) {
+        if let Some(payer) = self.payers.iter().find(|payer| payer.pubkey == *pubkey) {
+            payer.balance.store(0, Ordering::Relaxed);
+        }
+    }
+
+    pub async fn refresh_balances(&self, rpc_client: &RpcClient) {
+        for payer in &self.payers {
+            match rpc_client.get_balance(&payer.pubkey).await {
+                Ok(balance) => {
+                    payer.balance.store(balance, Ordering::Relaxed);
+                    if balance < self.min_balance {
+                        tracing::warn!(
+                            "Fee payer {} balance {balance} is below {}, skipping it until funded",
+                            payer.pubkey,
+                            self.min_balance
+                        );
+                    }
+                }
+                Err(err) => tracing::warn!("Get fee payer {} balance error: {err:#}", payer.pubkey),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anchor_client::solana_sdk::signature::Keypair;
+
+    use super::*;
+
+    fn fee_payers(count: usize) -> FeePayers {
+        FeePayers::new((0..count).map(|_| KeySigner::Keypair(Keypair::new())).collect(), 100)
+    }
+
+    fn next_pubkey(payers: &FeePayers) -> Option<Pubkey> {
+        payers.next().map(|signer| signer.pubkey())
+    }
+
+    #[test]
+    fn test_round_robin() {
+        let payers = fee_payers(3);
+        let pubkeys = payers.pubkeys().copied().collect::<Vec<_>>();
+
+        for round in 0..2 {
+            for pubkey in &pubkeys {
+                assert_eq!(next_pubkey(&payers).as_ref(), Some(pubkey), "round {round}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_skip_drained() {
+        let payers = fee_payers(3);
+        let pubkeys = payers.pubkeys().copied().collect::<Vec<_>>();
+
+        payers.mark_drained(&pubkeys[1]);
+        payers.payers[2].balance.store(99, Ordering::Relaxed);
+        for _ in 0..3 {
+            assert_eq!(next_pubkey(&payers), Some(pubkeys[0]));
+        }
+
+        payers.payers[2].balance.store(100, Ordering::Relaxed);
+        let used = (0..4).filter_map(|_| next_pubkey(&payers)).collect::<Vec<_>>();
+        assert!(used.contains(&pubkeys[2]) && !used.contains(&pubkeys[1]));
+
+        payers.mark_drained(&pubkeys[0]);
+        payers.mark_drained(&pubkeys[2]);
+        assert_eq!(next_pubkey(&payers), None);
+        assert_eq!(next_pubkey(&fee_payers(0)), None);
+    }
+}
@@ -0,0 +1,120 @@
+//! Key bytes encrypted with ChaCha20-Poly1305 under a key derived from a passphrase with scrypt.
+
+use std::path::Path;
+
+use anyhow::Context;
+use chacha20poly1305::{
+    aead::{Aead, NewAead},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use rand::{rngs::OsRng, RngCore};
+
+const VERSION: u32 = 1;
+/// scrypt's recommended parameters
+const SCRYPT_LOG_N: u8 = 15;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct Keystore {
+    version: u32,
+    scrypt_log_n: u8,
+    scrypt_r: u32,
+    scrypt_p: u32,
+    /// Base64, as are the nonce and ciphertext
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct CreateArgs {
+    /// Solana keypair JSON file holding the key bytes to encrypt
+    #[arg(long)]
+    input: std::path::PathBuf,
+    #[arg(long)]
+    output: std::path::PathBuf,
+    /// Environment variable holding the passphrase
+    #[arg(long, default_value = "VRF_KEYSTORE_PASSPHRASE")]
+    passphrase_env: String,
+    /// scrypt cost, log2 of N
+    #[arg(long, default_value_t = SCRYPT_LOG_N)]
+    scrypt_log_n: u8,
+}
+
+pub fn create(args: CreateArgs) -> anyhow::Result<()> {
+    let passphrase = std::env::var(&args.passphrase_env).with_context(|| format!("read passphrase from {}", args.passphrase_env))?;
+    let key = super::read_keypair_file(&args.input)?;
+    write(&args.output, &key, &passphrase, args.scrypt_log_n)?;
+    println!("Keystore written to {}", args.output.display());
+    Ok(())
+}
+
+pub fn write(path: &Path, key: &[u8], passphrase: &str, scrypt_log_n: u8) -> anyhow::Result<()> {
+    let params = scrypt::Params::new(scrypt_log_n, SCRYPT_R, SCRYPT_P).map_err(|err| anyhow::anyhow!("invalid scrypt params: {err}"))?;
+    let mut salt = [0; 32];
+    OsRng.fill_bytes(&mut salt);
+    let mut nonce = Nonce::default();
+    OsRng.fill_bytes(&mut nonce);
+
+    let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, &salt, &params)?);
+    let ciphertext = cipher.encrypt(&nonce, key).map_err(|_| anyhow::anyhow!("encrypt key"))?;
+
+    let keystore = Keystore {
+        version: VERSION,
+        scrypt_log_n: params.log_n(),
+        scrypt_r: params.r(),
+        scrypt_p: params.p(),
+        salt: base64::encode(salt),
+        nonce: base64::encode(nonce),
+        ciphertext: base64::encode(ciphertext),
+    };
+    std::fs::write(path, serde_json::to_string_pretty(&keystore)?).with_context(|| format!("write {}", path.display()))
+}
+
+pub fn read(path: &Path, passphrase: &str) -> anyhow::Result<Vec<u8>> {
+    let content = std::fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
+    let keystore: Keystore = serde_json::from_str(&content).with_context(|| format!("parse keystore {}", path.display()))?;
+    anyhow::ensure!(keystore.version == VERSION, "unsupported keystore version {}", keystore.version);
+
+    let params = scrypt::Params::new(keystore.scrypt_log_n, keystore.scrypt_r, keystore.scrypt_p)
+        .map_err(|err| anyhow::anyhow!("invalid scrypt params: {err}"))?;
+    let nonce = base64::decode(&keystore.nonce).context("keystore nonce")?;
+    anyhow::ensure!(nonce.len() == 12, "invalid keystore nonce length {}", nonce.len());
+
+    let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, &base64::decode(&keystore.salt)?, &params)?);
+    cipher
+        .decrypt(Nonce::from_slice(&nonce), &base64::decode(&keystore.ciphertext)?[..])
+        .map_err(|_| anyhow::anyhow!("decrypt keystore {}, wrong passphrase?", path.display()))
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], params: &scrypt::Params) -> anyhow::Result<Key> {
+    let mut key = Key::default();
+    scrypt::scrypt(passphrase.as_bytes(), salt, params, &mut key).map_err(|err| anyhow::anyhow!("scrypt: {err}"))?;
+    Ok(key)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_keystore() {
+        let path = std::env::temp_dir().join(format!("vrf-keystore-{}.json", std::process::id()));
+        let key = [9; 64];
+        write(&path, &key, "passphrase", 10).unwrap();
+
+        assert_eq!(read(&path, "passphrase").unwrap(), key);
+        assert!(read(&path, "wrong").is_err());
+
+        let mut keystore: Keystore = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        let mut ciphertext = base64::decode(&keystore.ciphertext).unwrap();
+        ciphertext[0] ^= 1;
+        keystore.ciphertext = base64::encode(ciphertext);
+        std::fs::write(&path, serde_json::to_string(&keystore).unwrap()).unwrap();
+        assert!(read(&path, "passphrase").is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
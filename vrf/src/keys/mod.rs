@@ -0,0 +1,172 @@
+//! Where `owner` and `secret` in `vrf-config.toml` come from.
+//!
+//! A key is either an inline byte array, or a table naming its source:
+//!
+//! - `{ keypair-file = "owner.json" }`: Solana keypair JSON file, a byte array
+//! - `{ env = "VRF_OWNER_KEYPAIR" }`: same format, read from an environment variable
+//! - `{ keystore-file = "secret.keystore.json", passphrase-env = "VRF_KEYSTORE_PASSPHRASE" }`: encrypted with
+//!   `vrf-server keystore create`
+//! - `{ remote-signer = "127.0.0.1:9000" }`: sign over the `remote` protocol, only for `owner` since the VRF secret is
+//!   needed to prove
+
+use std::path::{Path, PathBuf};
+
+use anchor_client::solana_sdk::{
+    pubkey::Pubkey,
+    signature::{keypair_from_seed, Keypair, Signature},
+    signer::{Signer, SignerError},
+};
+use anyhow::Context;
+
+pub mod keystore;
+pub mod remote;
+
+pub use remote::RemoteSigner;
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(untagged)]
+pub enum KeySource {
+    Bytes(Vec<u8>),
+    #[serde(rename_all = "kebab-case")]
+    KeypairFile {
+        keypair_file: PathBuf,
+    },
+    Env {
+        env: String,
+    },
+    #[serde(rename_all = "kebab-case")]
+    Keystore {
+        keystore_file: PathBuf,
+        passphrase_env: String,
+    },
+    #[serde(rename_all = "kebab-case")]
+    RemoteSigner {
+        remote_signer: String,
+    },
+}
+
+impl KeySource {
+    /// Raw key bytes, not available from a remote signer
+    pub fn load_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        match self {
+            KeySource::Bytes(bytes) => Ok(bytes.clone()),
+            KeySource::KeypairFile { keypair_file } => read_keypair_file(keypair_file),
+            KeySource::Env { env } => {
+                let content = std::env::var(env).with_context(|| format!("read environment variable {env}"))?;
+                serde_json::from_str(&content).with_context(|| format!("parse key bytes from {env}"))
+            }
+            KeySource::Keystore {
+                keystore_file,
+                passphrase_env,
+            } => {
+                let passphrase =
+                    std::env::var(passphrase_env).with_context(|| format!("read keystore passphrase from {passphrase_env}"))?;
+                keystore::read(keystore_file, &passphrase)
+            }
+            KeySource::RemoteSigner { remote_signer } => {
+                anyhow::bail!("key held by remote signer {remote_signer} can not be read")
+            }
+        }
+    }
+
+    pub fn load_signer(&self) -> anyhow::Result<KeySigner> {
+        match self {
+            KeySource::RemoteSigner { remote_signer } => Ok(KeySigner::Remote(RemoteSigner::connect(remote_signer)?)),
+            source => {
+                let keypair = Keypair::from_bytes(&source.load_bytes()?).context("recover Keypair from bytes")?;
+                Ok(KeySigner::Keypair(keypair))
+            }
+        }
+    }
+}
+
+pub fn read_keypair_file(path: &Path) -> anyhow::Result<Vec<u8>> {
+    let content = std::fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
+    serde_json::from_str(&content).with_context(|| format!("parse keypair file {}", path.display()))
+}
+
+#[derive(Debug)]
+pub enum KeySigner {
+    Keypair(Keypair),
+    Remote(RemoteSigner),
+}
+
+impl Signer for KeySigner {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        match self {
+            KeySigner::Keypair(keypair) => keypair.try_pubkey(),
+            KeySigner::Remote(remote) => remote.try_pubkey(),
+        }
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        match self {
+            KeySigner::Keypair(keypair) => keypair.try_sign_message(message),
+            KeySigner::Remote(remote) => remote.try_sign_message(message),
+        }
+    }
+
+    fn is_interactive(&self) -> bool {
+        false
+    }
+}
+
+/// Refuse a VRF secret holding the private key of `signer`, whose first 32 bytes are its ed25519 seed
+pub fn check_secret_not_signer(secret: &[u8], signer: &Pubkey) -> anyhow::Result<()> {
+    if secret.len() < 32 {
+        return Ok(());
+    }
+
+    let keypair = keypair_from_seed(&secret[..32]).map_err(|err| anyhow::anyhow!("derive keypair from secret: {err}"))?;
+    anyhow::ensure!(
+        keypair.pubkey() != *signer,
+        "VRF secret is the private key of {signer}, use a dedicated VRF secret"
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_key_source() {
+        let keypair = Keypair::new();
+        let dir = std::env::temp_dir().join(format!("vrf-keys-{}", keypair.pubkey()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let keypair_file = dir.join("keypair.json");
+        std::fs::write(&keypair_file, serde_json::to_string(&keypair.to_bytes().to_vec()).unwrap()).unwrap();
+        let keystore_file = dir.join("keystore.json");
+        keystore::write(&keystore_file, &keypair.to_bytes(), "passphrase", 10).unwrap();
+        std::env::set_var("VRF_TEST_KEY_SOURCE", serde_json::to_string(&keypair.to_bytes().to_vec()).unwrap());
+        std::env::set_var("VRF_TEST_KEY_SOURCE_PASSPHRASE", "passphrase");
+
+        let sources: Vec<KeySource> = serde_json::from_value(serde_json::json!([
+            keypair.to_bytes().to_vec(),
+            { "keypair-file": keypair_file },
+            { "env": "VRF_TEST_KEY_SOURCE" },
+            { "keystore-file": keystore_file, "passphrase-env": "VRF_TEST_KEY_SOURCE_PASSPHRASE" },
+        ]))
+        .unwrap();
+        for source in &sources {
+            assert_eq!(source.load_bytes().unwrap(), keypair.to_bytes(), "{source:?}");
+            assert_eq!(source.load_signer().unwrap().pubkey(), keypair.pubkey(), "{source:?}");
+        }
+
+        let remote: KeySource = serde_json::from_value(serde_json::json!({ "remote-signer": "127.0.0.1:1" })).unwrap();
+        assert!(matches!(remote, KeySource::RemoteSigner { .. }));
+        assert!(remote.load_bytes().is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_check_secret_not_signer() {
+        let fee_payer = Keypair::new();
+        assert!(check_secret_not_signer(&fee_payer.to_bytes(), &fee_payer.pubkey()).is_err());
+        assert!(check_secret_not_signer(fee_payer.secret().as_bytes(), &fee_payer.pubkey()).is_err());
+        assert!(check_secret_not_signer(&Keypair::new().to_bytes(), &fee_payer.pubkey()).is_ok());
+        assert!(check_secret_not_signer(&[7; 32], &fee_payer.pubkey()).is_ok());
+    }
+}
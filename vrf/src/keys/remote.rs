@@ -0,0 +1,206 @@
+//! Remote signer protocol: one JSON request and one JSON response per line over TCP.
+//!
+//! ```text
+//! {"method":"get-pubkey"}                  -> {"pubkey":"<base58>"}
+//! {"method":"sign","message":"<base64>"}   -> {"signature":"<base58>"}
+//! any failure                              -> {"error":"<message>"}
+//! ```
+//!
+//! Signatures are checked against the public key fetched on connect, so a misbehaving signer can not make the server
+//! send transactions it did not sign for. `serve_stand_in` answers the protocol with a local keypair, for development
+//! and tests in place of a KMS.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    str::FromStr,
+    time::Duration,
+};
+
+use anchor_client::solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signature},
+    signer::{Signer, SignerError},
+};
+use anyhow::Context;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "method", rename_all = "kebab-case")]
+enum Request {
+    GetPubkey,
+    Sign { message: String },
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+struct Response {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pubkey: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signature: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct RemoteSigner {
+    address: String,
+    pubkey: Pubkey,
+}
+
+impl RemoteSigner {
+    pub fn connect(address: &str) -> anyhow::Result<Self> {
+        let response = call(address, &Request::GetPubkey).with_context(|| format!("get pubkey from remote signer {address}"))?;
+        let pubkey = response
+            .pubkey
+            .ok_or_else(|| anyhow::anyhow!("remote signer {address} returned no pubkey"))?;
+
+        Ok(Self {
+            address: address.to_string(),
+            pubkey: Pubkey::from_str(&pubkey)?,
+        })
+    }
+}
+
+impl Signer for RemoteSigner {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        Ok(self.pubkey)
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        let request = Request::Sign {
+            message: base64::encode(message),
+        };
+        let response = call(&self.address, &request).map_err(|err| SignerError::Connection(format!("{err:#}")))?;
+        let signature = response
+            .signature
+            .ok_or_else(|| SignerError::Protocol("remote signer returned no signature".to_string()))?;
+        let signature = Signature::from_str(&signature).map_err(|err| SignerError::Protocol(err.to_string()))?;
+
+        if !signature.verify(self.pubkey.as_ref(), message) {
+            return Err(SignerError::Protocol(format!(
+                "remote signer returned a signature not made by {}",
+                self.pubkey
+            )));
+        }
+        Ok(signature)
+    }
+
+    fn is_interactive(&self) -> bool {
+        false
+    }
+}
+
+fn call(address: &str, request: &Request) -> anyhow::Result<Response> {
+    let address = address
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("can not resolve {address}"))?;
+    let mut stream = TcpStream::connect_timeout(&address, TIMEOUT)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+
+    let mut line = serde_json::to_string(request)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    let response: Response = serde_json::from_str(&line).context("parse remote signer response")?;
+    if let Some(error) = response.error {
+        anyhow::bail!("remote signer: {error}");
+    }
+    Ok(response)
+}
+
+#[derive(Debug, clap::Args)]
+pub struct StandInArgs {
+    /// Solana keypair JSON file to sign with
+    #[arg(long)]
+    keypair_file: std::path::PathBuf,
+    #[arg(long, default_value = "127.0.0.1:9000")]
+    listen: String,
+}
+
+pub fn run_stand_in(args: StandInArgs) -> anyhow::Result<()> {
+    let keypair = Keypair::from_bytes(&super::read_keypair_file(&args.keypair_file)?).context("recover Keypair from bytes")?;
+    let listener = TcpListener::bind(&args.listen)?;
+    println!("Stand-in signer for {} listening on {}", keypair.pubkey(), listener.local_addr()?);
+    serve_stand_in(listener, keypair);
+    Ok(())
+}
+
+/// Answer requests with `keypair` until `listener` fails, one connection at a time
+pub fn serve_stand_in(listener: TcpListener, keypair: Keypair) {
+    for stream in listener.incoming() {
+        let result = stream
+            .map_err(anyhow::Error::from)
+            .and_then(|stream| handle_stand_in(stream, &keypair));
+        if let Err(err) = result {
+            tracing::warn!("Stand-in signer: {err:#}");
+        }
+    }
+}
+
+fn handle_stand_in(mut stream: TcpStream, keypair: &Keypair) -> anyhow::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+    let response = match serde_json::from_str(&line) {
+        Ok(Request::GetPubkey) => Response {
+            pubkey: Some(keypair.pubkey().to_string()),
+            ..Default::default()
+        },
+        Ok(Request::Sign { message }) => match base64::decode(message) {
+            Ok(message) => Response {
+                signature: Some(keypair.sign_message(&message).to_string()),
+                ..Default::default()
+            },
+            Err(err) => Response {
+                error: Some(format!("invalid message: {err}")),
+                ..Default::default()
+            },
+        },
+        Err(err) => Response {
+            error: Some(format!("invalid request: {err}")),
+            ..Default::default()
+        },
+    };
+
+    let mut line = serde_json::to_string(&response)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn stand_in(keypair: Keypair) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        std::thread::spawn(move || serve_stand_in(listener, keypair));
+        address
+    }
+
+    #[test]
+    fn test_remote_signer() {
+        let keypair = Keypair::new();
+        let pubkey = keypair.pubkey();
+        let signer = RemoteSigner::connect(&stand_in(keypair)).unwrap();
+        assert_eq!(signer.pubkey(), pubkey);
+
+        let signature = signer.try_sign_message(b"message").unwrap();
+        assert!(signature.verify(pubkey.as_ref(), b"message"));
+    }
+
+    #[test]
+    fn test_remote_signer_wrong_key() {
+        let mut signer = RemoteSigner::connect(&stand_in(Keypair::new())).unwrap();
+        signer.pubkey = Pubkey::new_unique();
+        assert!(matches!(signer.try_sign_message(b"message"), Err(SignerError::Protocol(_))));
+    }
+}
@@ -0,0 +1,54 @@
+//! Leader election among instances sharing a database, enabled with `leader-election` in `vrf-config.toml` to run a
+//! hot standby.
+//!
+//! The leader holds a lease row it renews: only the leader subscribes, backfills and retries, the standbys serve the
+//! API and wait for the lease to expire. A leader which can not renew its lease in time exits, rather than keep
+//! running next to the standby taking over. The requests it was processing keep their own lease, and are recovered by
+//! the new leader once it expires.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::{db::Storage, VrfConfig};
+
+/// Name of the lease row
+const LEADERSHIP: &str = "vrf-server";
+
+/// Wait until this instance is the leader
+pub async fn acquire(config: &VrfConfig, storage: &dyn Storage, lease: Duration) {
+    loop {
+        match storage.acquire_leadership(LEADERSHIP, &config.instance_id, lease) {
+            Ok(true) => return,
+            Ok(false) => {}
+            Err(err) => tracing::error!("[DB] Acquire leadership error: {err:#}"),
+        }
+
+        tokio::time::sleep(lease / 3).await;
+    }
+}
+
+/// Renew the leadership, exiting the process once it is lost
+pub async fn keep(config: Arc<VrfConfig>, storage: Arc<dyn Storage>, lease: Duration) -> ! {
+    let mut renewed_at = Instant::now();
+    loop {
+        tokio::time::sleep(lease / 3).await;
+
+        match storage.acquire_leadership(LEADERSHIP, &config.instance_id, lease) {
+            Ok(true) => renewed_at = Instant::now(),
+            Ok(false) => {
+                tracing::error!("Leadership taken over by another instance, exiting");
+                std::process::exit(1);
+            }
+            Err(err) => {
+                tracing::error!("[DB] Renew leadership error: {err:#}");
+                // The next attempt would come after the lease expired
+                if renewed_at.elapsed() + lease / 3 >= lease {
+                    tracing::error!("Leadership lease expiring, exiting");
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
+}
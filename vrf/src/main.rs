@@ -1,108 +1,394 @@
-use std::sync::Arc;
+use std::{collections::HashMap, net::SocketAddr, sync::Arc, time::Duration};
 
 use anchor_client::{
     solana_client::nonblocking::rpc_client::RpcClient,
     solana_sdk::{
         commitment_config::{CommitmentConfig, CommitmentLevel},
+        native_token::LAMPORTS_PER_SOL,
         pubkey::Pubkey,
-        signature::Keypair,
+        signer::Signer,
     },
     Cluster,
 };
 use anyhow::Context;
+use randomness::{ProviderKind, RandomnessProvider};
 use serde_with::{serde_as, DisplayFromStr};
 
+mod admin;
+mod api;
+mod backoff;
+mod compute_budget;
+mod cpi_event;
 mod db;
 mod error;
+mod fee_payer;
+mod keys;
+mod leader;
 mod parse_log;
+mod pending;
+mod program_error;
+mod randomness;
 mod task;
+mod verify;
+
+#[derive(clap::Parser)]
+#[command(name = "vrf-server")]
+struct Cli {
+    /// Run the VRF handler when no command is given
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(clap::Subcommand)]
+enum Command {
+    /// Check the proof and random value of a fulfilled request
+    Verify(verify::VerifyArgs),
+    /// Encrypted key files for `keystore-file` key sources
+    #[command(subcommand)]
+    Keystore(KeystoreCommand),
+    /// Serve the remote signer protocol with a local keypair, in place of a KMS
+    StandInSigner(keys::remote::StandInArgs),
+    /// Inspect and requeue failed requests
+    #[command(subcommand)]
+    Admin(admin::AdminCommand),
+}
+
+#[derive(clap::Subcommand)]
+enum KeystoreCommand {
+    /// Encrypt a keypair file with a passphrase
+    Create(keys::keystore::CreateArgs),
+}
 
 #[serde_as]
 #[derive(Debug, serde::Deserialize, Clone)]
 #[serde(rename_all = "kebab-case")]
 struct Config {
-    owner: Vec<u8>,
-    secret: Vec<u8>,
+    owner: keys::KeySource,
+    #[serde(default)]
+    fee_payers: Vec<keys::KeySource>,
+    #[serde(default = "default_fee_payer_min_balance")]
+    fee_payer_min_balance: u64,
+    secret: keys::KeySource,
     #[serde_as(as = "DisplayFromStr")]
     cluster: Cluster,
     #[serde_as(as = "DisplayFromStr")]
     commitment: CommitmentLevel,
     #[serde_as(as = "Vec<DisplayFromStr>")]
     program_ids: Vec<Pubkey>,
-    num_confirmed_block: usize,
+    #[serde(default)]
+    ingestion: task::source::IngestionConfig,
+    #[serde(default)]
+    #[serde_as(as = "HashMap<DisplayFromStr, _>")]
+    randomness_providers: HashMap<Pubkey, ProviderKind>,
+    /// Programs whose callbacks do not check the proof against the oracle registry, the only ones which may use another
+    /// backend than ecvrf-secp256k1
+    #[serde(default)]
+    #[serde_as(as = "Vec<DisplayFromStr>")]
+    unverified_programs: Vec<Pubkey>,
+    #[serde(default)]
+    #[serde_as(as = "HashMap<DisplayFromStr, _>")]
+    compute_budget: HashMap<Pubkey, compute_budget::ComputeBudgetPolicy>,
+    #[serde(default)]
+    #[serde_as(as = "HashMap<DisplayFromStr, _>")]
+    program_errors: HashMap<Pubkey, program_error::ProgramErrorPolicy>,
+    #[serde(default)]
+    #[serde_as(as = "HashMap<DisplayFromStr, _>")]
+    pending_check: HashMap<Pubkey, pending::PendingCheckPolicy>,
+    deterministic_seed: Option<Vec<u8>>,
     retry_interval_seconds: u64,
+    #[serde(default)]
+    retry: backoff::Backoff,
+    instance_id: Option<String>,
+    #[serde(default = "default_processing_lease_seconds")]
+    processing_lease_seconds: u64,
+    #[serde(default = "default_backfill_horizon_slots")]
+    backfill_horizon_slots: u64,
+    #[serde(default)]
+    leader_election: bool,
+    #[serde(default = "default_leader_lease_seconds")]
+    leader_lease_seconds: u64,
     database_url: String,
+    http_listen_address: Option<SocketAddr>,
+}
+
+fn default_fee_payer_min_balance() -> u64 {
+    LAMPORTS_PER_SOL / 100
+}
+
+fn default_processing_lease_seconds() -> u64 {
+    120
+}
+
+/// A fulfillment may land until its blockhash expires, about 90 seconds after it was fetched, and the lease must not
+/// expire before: the recovery would retry a request whose fulfillment can still land
+const MIN_PROCESSING_LEASE_SECONDS: u64 = 100;
+
+/// About a day
+fn default_backfill_horizon_slots() -> u64 {
+    216_000
+}
+
+fn default_leader_lease_seconds() -> u64 {
+    30
 }
 
 #[derive(Debug)]
 pub struct VrfConfig {
-    owner: Keypair,
-    secret: Vec<u8>,
+    /// Fulfillment authority, the signer account prepended to the callbacks
+    owner: keys::KeySigner,
+    /// `owner` pays the fees when empty
+    fee_payers: fee_payer::FeePayers,
     cluster: Cluster,
     commitment: CommitmentConfig,
     program_ids: Vec<Pubkey>,
-    num_confirmed_block: usize,
+    ingestion: task::source::IngestionConfig,
+    randomness_providers: HashMap<Pubkey, Arc<dyn RandomnessProvider>>,
+    compute_budgets: HashMap<Pubkey, compute_budget::ComputeBudgetPolicy>,
+    program_errors: HashMap<Pubkey, program_error::ProgramErrors>,
+    /// Programs without a check are always fulfilled
+    pending_checks: HashMap<Pubkey, Box<dyn pending::PendingCheck>>,
+    /// How often the retryable requests are polled
     retry_interval_seconds: u64,
+    backoff: backoff::Backoff,
+    /// Owner of the requests this instance processes
+    instance_id: String,
+    /// How long a request being processed stays claimed without a heartbeat of its instance
+    processing_lease: Duration,
+    /// How far back the backfill goes when the cursor of a program is missing or older
+    backfill_horizon_slots: u64,
+    /// Lease of the leadership among the instances sharing the database, `None` when every instance leads
+    leader_lease: Option<Duration>,
     database_url: String,
+    http_listen_address: Option<SocketAddr>,
 }
 
 impl TryFrom<Config> for VrfConfig {
     type Error = anyhow::Error;
 
     fn try_from(config: Config) -> Result<Self, Self::Error> {
-        let owner = Keypair::from_bytes(&config.owner).context("recover owner Keypair from bytes")?;
+        let owner = config.owner.load_signer().context("load owner")?;
+        let fee_payers = config
+            .fee_payers
+            .iter()
+            .enumerate()
+            .map(|(index, source)| source.load_signer().with_context(|| format!("load fee payer {index}")))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let secret = config.secret.load_bytes().context("load VRF secret")?;
+        for signer in std::iter::once(&owner).chain(&fee_payers) {
+            keys::check_secret_not_signer(&secret, &signer.pubkey())?;
+        }
         let commitment = CommitmentConfig {
             commitment: config.commitment,
         };
 
+        anyhow::ensure!(
+            config.processing_lease_seconds >= MIN_PROCESSING_LEASE_SECONDS,
+            "processing-lease-seconds must be at least {MIN_PROCESSING_LEASE_SECONDS}, longer than a blockhash lives"
+        );
+        anyhow::ensure!(config.leader_lease_seconds > 0, "leader-lease-seconds must be greater than 0");
+
+        if let Some(program_id) = config.randomness_providers.keys().find(|id| !config.program_ids.contains(id)) {
+            anyhow::bail!("randomness provider configured for {program_id} which is not in program-ids");
+        }
+        if let Some(program_id) = config.unverified_programs.iter().find(|id| !config.program_ids.contains(id)) {
+            anyhow::bail!("unverified program {program_id} is not in program-ids");
+        }
+        // The programs check the proof against the oracle registry, other backends send them a default `VrfProof`
+        if let Some((program_id, kind)) = config
+            .randomness_providers
+            .iter()
+            .find(|(program_id, kind)| **kind != ProviderKind::EcvrfSecp256k1 && !config.unverified_programs.contains(program_id))
+        {
+            anyhow::bail!(
+                "{kind} randomness provider configured for {program_id} can not be verified on-chain, \
+                 list the program in unverified-programs if its callback does not verify the proof"
+            );
+        }
+        if let Some(program_id) = config.compute_budget.keys().find(|id| !config.program_ids.contains(id)) {
+            anyhow::bail!("compute budget configured for {program_id} which is not in program-ids");
+        }
+        if let Some(program_id) = config.program_errors.keys().find(|id| !config.program_ids.contains(id)) {
+            anyhow::bail!("program errors configured for {program_id} which is not in program-ids");
+        }
+        if let Some(program_id) = config.pending_check.keys().find(|id| !config.program_ids.contains(id)) {
+            anyhow::bail!("pending check configured for {program_id} which is not in program-ids");
+        }
+
+        // Programs sharing a backend share one provider
+        let mut providers: HashMap<ProviderKind, Arc<dyn RandomnessProvider>> = HashMap::new();
+        let mut randomness_providers = HashMap::new();
+        for program_id in &config.program_ids {
+            let kind = config.randomness_providers.get(program_id).copied().unwrap_or_default();
+            let provider = match providers.get(&kind) {
+                Some(provider) => provider.clone(),
+                None => {
+                    let provider = kind
+                        .new_provider(&secret, config.deterministic_seed.as_deref())
+                        .with_context(|| format!("create {kind} randomness provider"))?;
+                    providers.insert(kind, provider.clone());
+                    provider
+                }
+            };
+
+            randomness_providers.insert(*program_id, provider);
+        }
+
+        let compute_budgets = config
+            .program_ids
+            .iter()
+            .map(|program_id| (*program_id, config.compute_budget.get(program_id).cloned().unwrap_or_default()))
+            .collect();
+        let program_errors = config
+            .program_ids
+            .iter()
+            .map(|program_id| {
+                let policy = config.program_errors.get(program_id).cloned().unwrap_or_default();
+                let errors = program_error::ProgramErrors::load(&policy).with_context(|| format!("load program errors of {program_id}"))?;
+                Ok((*program_id, errors))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        let pending_checks = config
+            .pending_check
+            .iter()
+            .map(|(program_id, policy)| (*program_id, policy.new_check()))
+            .collect();
+
         Ok(Self {
             owner,
-            secret: config.secret,
+            fee_payers: fee_payer::FeePayers::new(fee_payers, config.fee_payer_min_balance),
             cluster: config.cluster,
             commitment,
             program_ids: config.program_ids,
-            num_confirmed_block: config.num_confirmed_block,
+            ingestion: config.ingestion,
+            randomness_providers,
+            compute_budgets,
+            program_errors,
+            pending_checks,
             retry_interval_seconds: config.retry_interval_seconds,
+            backoff: config.retry,
+            instance_id: config.instance_id.unwrap_or_else(|| format!("{:016x}", rand::random::<u64>())),
+            processing_lease: Duration::from_secs(config.processing_lease_seconds),
+            backfill_horizon_slots: config.backfill_horizon_slots,
+            leader_lease: config.leader_election.then_some(Duration::from_secs(config.leader_lease_seconds)),
             database_url: config.database_url,
+            http_listen_address: config.http_listen_address,
         })
     }
 }
 
+fn read_config() -> anyhow::Result<config::Config> {
+    Ok(config::Config::builder()
+        .add_source(config::File::with_name("vrf-config.toml"))
+        .add_source(config::Environment::with_prefix("VRF"))
+        .build()?)
+}
+
+fn load_config() -> anyhow::Result<VrfConfig> {
+    let config: Config = read_config()?.try_deserialize()?;
+
+    VrfConfig::try_from(config)
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt::init();
 
-    let config: Config = config::Config::builder()
-        .add_source(config::File::with_name("vrf-config.toml"))
-        .add_source(config::Environment::with_prefix("VRF"))
-        .build()?
-        .try_deserialize()?;
+    let cli = <Cli as clap::Parser>::parse();
+    match cli.command {
+        Some(Command::Verify(args)) => {
+            if !verify::run(args, load_config).await? {
+                std::process::exit(1);
+            }
 
-    let config = Arc::new(VrfConfig::try_from(config)?);
+            return Ok(());
+        }
+        Some(Command::Keystore(KeystoreCommand::Create(args))) => return keys::keystore::create(args),
+        Some(Command::StandInSigner(args)) => return keys::remote::run_stand_in(args),
+        Some(Command::Admin(command)) => {
+            // Only the database is needed, not the keys
+            let storage = db::connect(&read_config()?.get_string("database-url")?)?;
+            storage.run_migration()?;
+            return admin::run(command, &*storage);
+        }
+        None => {}
+    }
+
+    let config = Arc::new(load_config()?);
 
     println!("---");
     println!("Running VRF handler with:");
     println!("Cluster: ({}) {}", &config.cluster, config.cluster.url());
     println!("Commitment: {}", &config.commitment.commitment);
+    println!("Ingestion: {:?}", &config.ingestion);
     println!("Database: {}", &config.database_url);
+    println!("Instance: {}", &config.instance_id);
+    println!("Fulfillment authority: {}", config.owner.pubkey());
+    if config.fee_payers.is_empty() {
+        println!("Fee payer: {}", config.owner.pubkey());
+    }
+    for fee_payer in config.fee_payers.pubkeys() {
+        println!("Fee payer: {fee_payer}");
+    }
+    for program_id in &config.program_ids {
+        let provider = &config.randomness_providers[program_id];
+        println!("Program {program_id}: {:?} public key {:?}", provider, provider.public_key()?);
+        if provider.kind() != ProviderKind::EcvrfSecp256k1 {
+            println!("Program {program_id}: proof not verified on-chain");
+        }
+    }
     println!("---");
 
-    db::init(&config.database_url);
-    db::run_migration()?;
+    let storage = db::connect(&config.database_url)?;
+    storage.run_migration()?;
 
     let rpc_client = Arc::new(RpcClient::new_with_commitment(config.cluster.url().to_string(), config.commitment));
 
-    for program_id in &config.program_ids {
-        tokio::spawn(task::logs_subscribe(
-            config.clone(),
-            Arc::new(program_id.to_string()),
-            rpc_client.clone(),
-        ));
+    // Standbys serve the API too
+    if let Some(address) = config.http_listen_address {
+        let config = config.clone();
+        let storage = storage.clone();
+        tokio::spawn(async move {
+            if let Err(err) = api::serve(config, storage, address).await {
+                tracing::error!("HTTP API stopped: {err:#}");
+            }
+        });
+    }
+
+    if let Some(leader_lease) = config.leader_lease {
+        tracing::info!("Waiting for leadership");
+        leader::acquire(&config, &*storage, leader_lease).await;
+        tracing::info!("Leading");
+        tokio::spawn(leader::keep(config.clone(), storage.clone(), leader_lease));
+    }
+
+    // Requests left processing by a previous run, before new ones are claimed
+    task::recover_stuck_transaction(&config, &*storage, &rpc_client).await;
+
+    let backfill_trigger = Arc::new(tokio::sync::Notify::new());
+    let source = config
+        .ingestion
+        .new_source(config.cluster.ws_url().to_string(), &config.program_ids, config.commitment);
+    tokio::spawn(task::ingest(
+        config.clone(),
+        storage.clone(),
+        rpc_client.clone(),
+        source,
+        backfill_trigger.clone(),
+    ));
+
+    if !config.fee_payers.is_empty() {
+        config.fee_payers.refresh_balances(&rpc_client).await;
+        tokio::spawn(task::monitor_fee_payers(config.clone(), rpc_client.clone()));
     }
 
     let _ = tokio::join!(
-        tokio::spawn(task::process_old_transaction(config.clone(), rpc_client.clone())),
-        tokio::spawn(task::retry_failed_transaction(config.clone(), rpc_client.clone())),
+        tokio::spawn(task::backfill(
+            config.clone(),
+            storage.clone(),
+            rpc_client.clone(),
+            backfill_trigger
+        )),
+        tokio::spawn(task::retry_failed_transaction(config.clone(), storage.clone(), rpc_client.clone())),
     );
 
     Ok(())
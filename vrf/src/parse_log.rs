@@ -1,214 +1,509 @@
-use std::collections::HashMap;
-
-use anchor_client::solana_sdk::pubkey::Pubkey;
-use once_cell::sync::Lazy;
-use regex::Regex;
-
-#[derive(Debug)]
-pub enum ParseLogError {
-    ProgramIdMismatch {
-        line: String,
-        current: Option<String>,
-        expect: String,
-    },
-    NoCurrentProgramId {
-        line: String,
-    },
-    Base64Decode {
-        line: String,
-        error: String,
-    },
-    ParseInvoke {
-        line: String,
-    },
-    ParseReturn {
-        line: String,
-    },
-}
-
-#[derive(Debug)]
-enum LogType<'a> {
-    Trivia,
-    Data(&'a str),
-    Invoke(&'a str),
-    Return(&'a str),
-    InProgram(&'a str),
-}
-
-fn parse_log_line<'a>(log: &'a str) -> Result<LogType<'a>, ParseLogError> {
-    static RE_INVOKE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^Program (.*) invoke.*$").unwrap());
-    static RE_RETURN: Lazy<Regex> = Lazy::new(|| Regex::new(r"^Program (.*) success*$").unwrap());
-
-    if let Some(log) = log.strip_prefix("Program log: ").or_else(|| log.strip_prefix("Program data: ")) {
-        if log
-            .find(|c: char| !c.is_ascii_alphanumeric() && c != '+' && c != '/' && c != '=')
-            .is_none()
-        {
-            return Ok(LogType::Data(log));
-        }
-
-        return Ok(LogType::Trivia);
-    }
-
-    if let Some(capture) = RE_INVOKE.captures(log) {
-        return Ok(LogType::Invoke(
-            capture
-                .get(1)
-                .ok_or_else(|| ParseLogError::ParseInvoke { line: log.to_string() })?
-                .as_str(),
-        ));
-    };
-
-    if let Some(capture) = RE_RETURN.captures(log) {
-        return Ok(LogType::Return(
-            capture
-                .get(1)
-                .ok_or_else(|| ParseLogError::ParseReturn { line: log.to_string() })?
-                .as_str(),
-        ));
-    }
-
-    if let Some(log) = log.strip_prefix("Program ") {
-        if let Some(end_index) = log.find(' ') {
-            return Ok(LogType::InProgram(&log[..end_index]));
-        }
-    }
-
-    return Ok(LogType::Trivia);
-}
-
-pub struct Event {
-    pub program_id: Pubkey,
-    pub data: Vec<u8>,
-}
-
-pub fn process<S: AsRef<str>>(logs: &[S], program_ids: &[Pubkey]) -> (Vec<Event>, Vec<ParseLogError>) {
-    let mut events = Vec::new();
-    let mut errors = Vec::new();
-    let mut stack = Vec::new();
-    let program_ids: HashMap<String, Pubkey> = program_ids.iter().map(|pubkey| (pubkey.to_string(), *pubkey)).collect();
-
-    for log in logs {
-        let log = log.as_ref();
-        match parse_log_line(log) {
-            Ok(LogType::Invoke(program_id)) => stack.push(program_id),
-            Ok(LogType::Return(program_id)) => {
-                if let Some(popped_program_id) = stack.pop() {
-                    if popped_program_id != program_id {
-                        errors.push(ParseLogError::ProgramIdMismatch {
-                            line: log.to_string(),
-                            current: Some(popped_program_id.to_string()),
-                            expect: program_id.to_string(),
-                        });
-                    }
-                }
-            }
-            Ok(LogType::InProgram(program_id)) => match stack.last() {
-                Some(current) if *current != program_id => {
-                    errors.push(ParseLogError::ProgramIdMismatch {
-                        line: log.to_string(),
-                        current: Some(current.to_string()),
-                        expect: program_id.to_string(),
-                    });
-
-                    continue;
-                }
-                Some(_) => {}
-                None => {
-                    errors.push(ParseLogError::ProgramIdMismatch {
-                        line: log.to_string(),
-                        current: None,
-                        expect: program_id.to_string(),
-                    });
-
-                    continue;
-                }
-            },
-            Ok(LogType::Data(data)) => {
-                let current_program_id = match stack.last() {
-                    Some(id) => id,
-                    None => {
-                        errors.push(ParseLogError::NoCurrentProgramId { line: log.to_string() });
-                        continue;
-                    }
-                };
-
-                if let Some(program_id) = program_ids.get(*current_program_id) {
-                    let bytes = match base64::decode_config(data, base64::STANDARD) {
-                        Ok(b) => b,
-                        Err(err) => {
-                            errors.push(ParseLogError::Base64Decode {
-                                line: log.to_string(),
-                                error: format!("{err:#}"),
-                            });
-
-                            continue;
-                        }
-                    };
-
-                    events.push(Event {
-                        program_id: *program_id,
-                        data: bytes.to_vec(),
-                    });
-                }
-            }
-            Ok(LogType::Trivia) => {}
-            Err(err) => {
-                errors.push(err);
-            }
-        }
-    }
-
-    (events, errors)
-}
-
-#[cfg(test)]
-mod tests {
-    use std::str::FromStr;
-
-    use super::*;
-
-    #[test]
-    fn test_parse_log_line() {
-        let logs = [
-            "Program DEoxdV1CCWvbeGp8PpwkUifmm3pV5AgtFwFaS4P7qZeZ invoke [1]",
-            "Program log: Instruction: Spin",
-            "Program log: Transfering stake to pool",
-            "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA invoke [2]",
-            "Program log: Instruction: Transfer",
-            "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA consumed 4645 of 182491 compute units",
-            "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA success",
-            "Program data: aGVsbG93b3JsZCE=",
-            "Program log: Transfering token to user",
-            "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA invoke [2]",
-            "Program log: Instruction: Transfer",
-            "Program data: bmVzdGVkIGRhdGE=",
-            "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA consumed 4740 of 159826 compute units",
-            "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA success",
-            "Program log: Transfering tax to treasury",
-            "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA invoke [2]",
-            "Program log: Instruction: Transfer",
-            "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA consumed 4740 of 152166 compute units",
-            "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA success",
-            "Program log: Burning part of the tax",
-            "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA invoke [2]",
-            "Program log: Instruction: Burn",
-            "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA consumed 4844 of 144508 compute units",
-            "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA success",
-            "Program DEoxdV1CCWvbeGp8PpwkUifmm3pV5AgtFwFaS4P7qZeZ consumed 63281 of 200000 compute units",
-            "Program DEoxdV1CCWvbeGp8PpwkUifmm3pV5AgtFwFaS4P7qZeZ success",
-        ];
-
-        let pubkey_a = Pubkey::from_str("DEoxdV1CCWvbeGp8PpwkUifmm3pV5AgtFwFaS4P7qZeZ").unwrap();
-        let pubkey_b = Pubkey::from_str("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA").unwrap();
-
-        let (events, errors) = process(&logs, &[pubkey_a, pubkey_b]);
-        assert_eq!(events.len(), 2);
-        assert_eq!(events[0].program_id, pubkey_a);
-        assert_eq!(events[0].data, b"helloworld!");
-        assert_eq!(events[1].program_id, pubkey_b);
-        assert_eq!(events[1].data, b"nested data");
-
-        assert!(errors.is_empty());
-    }
-}
+//! Grammar of the runtime logs of a transaction, parsed into the tree of the program invocations.
+//!
+//! The runtime writes:
+//! - `Program <id> invoke [<depth>]`, `Program <id> success` and `Program <id> failed: <error>` around an invocation
+//! - `Program <id> consumed <n> of <m> compute units` before it returns
+//! - `Program log: <message>`, `Program data: <base64> ...` and `Program return: <id> <base64>` from the program
+//! - `Log truncated` once the log size limit is reached, nothing is logged after it
+
+use std::collections::HashMap;
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+
+#[derive(Debug)]
+pub enum ParseLogError {
+    ProgramIdMismatch {
+        line: String,
+        current: Option<String>,
+        expect: String,
+    },
+    NoCurrentProgramId {
+        line: String,
+    },
+    Base64Decode {
+        line: String,
+        error: String,
+    },
+    /// An invocation not one level deeper than the current one
+    DepthMismatch {
+        line: String,
+        current: usize,
+    },
+    Malformed {
+        line: String,
+    },
+}
+
+impl std::fmt::Display for ParseLogError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseLogError::ProgramIdMismatch { line, current, expect } => write!(
+                f,
+                "Program {expect} is not the current one ({}): {line}",
+                current.as_deref().unwrap_or("none")
+            ),
+            ParseLogError::NoCurrentProgramId { line } => write!(f, "No current program: {line}"),
+            ParseLogError::Base64Decode { line, error } => write!(f, "Base64 decode error {error}: {line}"),
+            ParseLogError::DepthMismatch { line, current } => write!(f, "Invocation not at depth {}: {line}", current + 1),
+            ParseLogError::Malformed { line } => write!(f, "Malformed log: {line}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogLine<'a> {
+    Invoke {
+        program_id: &'a str,
+        depth: usize,
+    },
+    Success {
+        program_id: &'a str,
+    },
+    Failed {
+        program_id: &'a str,
+        error: &'a str,
+    },
+    Consumed {
+        program_id: &'a str,
+        compute_units: ComputeUnits,
+    },
+    Log(&'a str),
+    /// Space separated base64 fields
+    Data(&'a str),
+    Return {
+        program_id: &'a str,
+        data: &'a str,
+    },
+    Truncated,
+    /// e.g. `Program consumption: <n> units remaining`
+    Other(&'a str),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComputeUnits {
+    pub consumed: u64,
+    pub budget: u64,
+}
+
+pub fn parse_log_line(line: &str) -> Result<LogLine<'_>, ParseLogError> {
+    let malformed = || ParseLogError::Malformed { line: line.to_string() };
+
+    if line == "Log truncated" {
+        return Ok(LogLine::Truncated);
+    }
+
+    let rest = match line.strip_prefix("Program ") {
+        Some(rest) => rest,
+        None => return Ok(LogLine::Other(line)),
+    };
+    if let Some(message) = rest.strip_prefix("log: ") {
+        return Ok(LogLine::Log(message));
+    }
+    if let Some(data) = rest.strip_prefix("data: ") {
+        return Ok(LogLine::Data(data));
+    }
+    if let Some(rest) = rest.strip_prefix("return: ") {
+        let (program_id, data) = rest.split_once(' ').ok_or_else(malformed)?;
+        return Ok(LogLine::Return { program_id, data });
+    }
+
+    let (program_id, rest) = match rest.split_once(' ') {
+        Some((program_id, rest)) if program_id.parse::<Pubkey>().is_ok() => (program_id, rest),
+        _ => return Ok(LogLine::Other(line)),
+    };
+
+    if let Some(depth) = rest.strip_prefix("invoke [").and_then(|rest| rest.strip_suffix(']')) {
+        let depth = depth.parse().map_err(|_| malformed())?;
+        return Ok(LogLine::Invoke { program_id, depth });
+    }
+    if rest == "success" {
+        return Ok(LogLine::Success { program_id });
+    }
+    if let Some(error) = rest.strip_prefix("failed: ") {
+        return Ok(LogLine::Failed { program_id, error });
+    }
+    if let Some(units) = rest.strip_prefix("consumed ").and_then(|rest| rest.strip_suffix(" compute units")) {
+        let (consumed, budget) = units.split_once(" of ").ok_or_else(malformed)?;
+        let compute_units = ComputeUnits {
+            consumed: consumed.parse().map_err(|_| malformed())?,
+            budget: budget.parse().map_err(|_| malformed())?,
+        };
+        return Ok(LogLine::Consumed { program_id, compute_units });
+    }
+
+    Ok(LogLine::Other(line))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvocationResult {
+    Success,
+    Failed(String),
+    /// The logs were truncated before the invocation returned
+    Unfinished,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invocation {
+    pub program_id: String,
+    /// 1 for an instruction of the transaction
+    pub depth: usize,
+    /// `Program log:` messages
+    pub logs: Vec<String>,
+    /// Decoded `Program data:` fields
+    pub data: Vec<Vec<u8>>,
+    pub return_data: Option<Vec<u8>>,
+    pub compute_units: Option<ComputeUnits>,
+    pub result: InvocationResult,
+    /// Invoked by this program, in order
+    pub children: Vec<Invocation>,
+}
+
+impl Invocation {
+    fn new(program_id: &str, depth: usize) -> Self {
+        Self {
+            program_id: program_id.to_string(),
+            depth,
+            logs: Vec::new(),
+            data: Vec::new(),
+            return_data: None,
+            compute_units: None,
+            result: InvocationResult::Unfinished,
+            children: Vec::new(),
+        }
+    }
+
+    /// This invocation and the ones below it, parents first
+    pub fn iter(&self) -> Box<dyn Iterator<Item = &Invocation> + '_> {
+        Box::new(std::iter::once(self).chain(self.children.iter().flat_map(Invocation::iter)))
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ParsedLogs {
+    /// The instructions of the transaction
+    pub invocations: Vec<Invocation>,
+    pub truncated: bool,
+}
+
+impl ParsedLogs {
+    /// Every invocation, parents first
+    pub fn iter(&self) -> impl Iterator<Item = &Invocation> {
+        self.invocations.iter().flat_map(Invocation::iter)
+    }
+}
+
+/// Parse what can be parsed, lines that do not fit the invocation they are in are reported and skipped
+pub fn parse<S: AsRef<str>>(logs: &[S]) -> (ParsedLogs, Vec<ParseLogError>) {
+    let mut parsed = ParsedLogs::default();
+    let mut errors = Vec::new();
+    // Invocations not returned yet, innermost last
+    let mut stack: Vec<Invocation> = Vec::new();
+
+    for line in logs {
+        let line = line.as_ref();
+        let log_line = match parse_log_line(line) {
+            Ok(log_line) => log_line,
+            Err(err) => {
+                errors.push(err);
+                continue;
+            }
+        };
+
+        match log_line {
+            LogLine::Invoke { program_id, depth } => {
+                if depth != stack.len() + 1 {
+                    errors.push(ParseLogError::DepthMismatch {
+                        line: line.to_string(),
+                        current: stack.len(),
+                    });
+                }
+                stack.push(Invocation::new(program_id, stack.len() + 1));
+            }
+            LogLine::Success { program_id } | LogLine::Failed { program_id, .. } => {
+                if let Err(err) = check_current(&mut stack, program_id, line) {
+                    errors.push(err);
+                    continue;
+                }
+
+                let mut invocation = stack.pop().expect("checked current invocation");
+                invocation.result = match log_line {
+                    LogLine::Failed { error, .. } => InvocationResult::Failed(error.to_string()),
+                    _ => InvocationResult::Success,
+                };
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(invocation),
+                    None => parsed.invocations.push(invocation),
+                }
+            }
+            LogLine::Consumed { program_id, compute_units } => match check_current(&mut stack, program_id, line) {
+                Ok(invocation) => invocation.compute_units = Some(compute_units),
+                Err(err) => errors.push(err),
+            },
+            LogLine::Return { program_id, data } => {
+                let return_data = match decode_base64(data, line) {
+                    Ok(return_data) => return_data,
+                    Err(err) => {
+                        errors.push(err);
+                        continue;
+                    }
+                };
+                match check_current(&mut stack, program_id, line) {
+                    Ok(invocation) => invocation.return_data = Some(return_data),
+                    Err(err) => errors.push(err),
+                }
+            }
+            LogLine::Log(message) => {
+                // Outside of an invocation it is not from a program
+                if let Some(invocation) = stack.last_mut() {
+                    invocation.logs.push(message.to_string());
+                }
+            }
+            LogLine::Data(data) => {
+                let invocation = match stack.last_mut() {
+                    Some(invocation) => invocation,
+                    None => {
+                        errors.push(ParseLogError::NoCurrentProgramId { line: line.to_string() });
+                        continue;
+                    }
+                };
+                for field in data.split(' ') {
+                    match decode_base64(field, line) {
+                        Ok(field) => invocation.data.push(field),
+                        Err(err) => errors.push(err),
+                    }
+                }
+            }
+            LogLine::Truncated => {
+                parsed.truncated = true;
+                break;
+            }
+            LogLine::Other(_) => {}
+        }
+    }
+
+    // Unfinished, when the logs were truncated
+    while let Some(invocation) = stack.pop() {
+        match stack.last_mut() {
+            Some(parent) => parent.children.push(invocation),
+            None => parsed.invocations.push(invocation),
+        }
+    }
+
+    (parsed, errors)
+}
+
+fn check_current<'a>(stack: &'a mut [Invocation], program_id: &str, line: &str) -> Result<&'a mut Invocation, ParseLogError> {
+    match stack.last_mut() {
+        Some(current) if current.program_id == program_id => Ok(current),
+        current => Err(ParseLogError::ProgramIdMismatch {
+            line: line.to_string(),
+            current: current.map(|current| current.program_id.clone()),
+            expect: program_id.to_string(),
+        }),
+    }
+}
+
+fn decode_base64(data: &str, line: &str) -> Result<Vec<u8>, ParseLogError> {
+    base64::decode_config(data, base64::STANDARD).map_err(|err| ParseLogError::Base64Decode {
+        line: line.to_string(),
+        error: format!("{err:#}"),
+    })
+}
+
+pub struct Event {
+    pub program_id: Pubkey,
+    pub data: Vec<u8>,
+}
+
+/// Events of `program_ids`, parents first
+pub fn process<S: AsRef<str>>(logs: &[S], program_ids: &[Pubkey]) -> (Vec<Event>, Vec<ParseLogError>) {
+    let (parsed, errors) = parse(logs);
+    let program_ids: HashMap<String, Pubkey> = program_ids.iter().map(|pubkey| (pubkey.to_string(), *pubkey)).collect();
+
+    let mut events = Vec::new();
+    for invocation in parsed.iter() {
+        let program_id = match program_ids.get(&invocation.program_id) {
+            Some(program_id) => *program_id,
+            None => continue,
+        };
+
+        // Older Anchor versions emitted events as base64 `Program log:` messages, text which is not valid base64 of
+        // at least a discriminator is a plain message
+        let logged = invocation
+            .logs
+            .iter()
+            .filter_map(|message| base64::decode_config(message, base64::STANDARD).ok().filter(|data| data.len() >= 8));
+        events.extend(invocation.data.iter().cloned().chain(logged).map(|data| Event { program_id, data }));
+    }
+
+    (events, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use anchor_client::anchor_lang::{AnchorDeserialize, Discriminator};
+
+    use super::*;
+
+    const SPINNER: &str = "DEoxdV1CCWvbeGp8PpwkUifmm3pV5AgtFwFaS4P7qZeZ";
+    const TOKEN: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+
+    fn fixture(content: &str) -> Vec<&str> {
+        content.lines().map(str::trim_end).filter(|line| !line.is_empty()).collect()
+    }
+
+    #[test]
+    fn test_parse_log_line() {
+        assert_eq!(
+            parse_log_line(&format!("Program {SPINNER} invoke [2]")).unwrap(),
+            LogLine::Invoke {
+                program_id: SPINNER,
+                depth: 2
+            }
+        );
+        assert_eq!(
+            parse_log_line(&format!("Program {TOKEN} consumed 4645 of 182491 compute units")).unwrap(),
+            LogLine::Consumed {
+                program_id: TOKEN,
+                compute_units: ComputeUnits {
+                    consumed: 4645,
+                    budget: 182491
+                }
+            }
+        );
+        assert_eq!(
+            parse_log_line(&format!("Program {TOKEN} failed: custom program error: 0x1")).unwrap(),
+            LogLine::Failed {
+                program_id: TOKEN,
+                error: "custom program error: 0x1"
+            }
+        );
+        // Not a program id
+        assert_eq!(
+            parse_log_line("Program is not deployed").unwrap(),
+            LogLine::Other("Program is not deployed")
+        );
+        assert_eq!(parse_log_line("Program log: success").unwrap(), LogLine::Log("success"));
+        assert!(parse_log_line(&format!("Program {SPINNER} invoke [x]")).is_err());
+        assert!(parse_log_line(&format!("Program {SPINNER} consumed a of 1 compute units")).is_err());
+    }
+
+    #[test]
+    fn test_process() {
+        let logs = fixture(include_str!("../fixtures/logs/nested-data.log"));
+        let pubkey_a = Pubkey::from_str(SPINNER).unwrap();
+        let pubkey_b = Pubkey::from_str(TOKEN).unwrap();
+
+        let (events, errors) = process(&logs, &[pubkey_a, pubkey_b]);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].program_id, pubkey_a);
+        assert_eq!(events[0].data, b"helloworld!");
+        assert_eq!(events[1].program_id, pubkey_b);
+        assert_eq!(events[1].data, b"nested data");
+
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_request() {
+        let logs = fixture(include_str!("../fixtures/logs/lucky-spinner-spin.log"));
+        let (parsed, errors) = parse(&logs);
+        assert!(errors.is_empty(), "{errors:?}");
+        assert!(!parsed.truncated);
+
+        let invocations = parsed.iter().map(|invocation| invocation.program_id.as_str()).collect::<Vec<_>>();
+        assert_eq!(invocations, ["ComputeBudget111111111111111111111111111111", SPINNER, TOKEN, TOKEN]);
+        let spin = &parsed.invocations[1];
+        assert_eq!(spin.result, InvocationResult::Success);
+        assert_eq!(spin.logs, ["Instruction: Spin", "Transfering stake to pool"]);
+        assert_eq!(
+            spin.compute_units,
+            Some(ComputeUnits {
+                consumed: 41508,
+                budget: 200000
+            })
+        );
+        assert_eq!(spin.children.iter().map(|child| child.depth).collect::<Vec<_>>(), [2, 2]);
+
+        let (events, errors) = process(&logs, &[Pubkey::from_str(SPINNER).unwrap()]);
+        assert!(errors.is_empty());
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].data[..8], vrf_lib::RequestVrf::discriminator());
+        let request = vrf_lib::RequestVrf::deserialize(&mut &events[0].data[8..]).unwrap();
+        assert_eq!(request.accounts.len(), 2);
+    }
+
+    #[test]
+    fn test_failed() {
+        let logs = fixture(include_str!("../fixtures/logs/failed.log"));
+        let (parsed, errors) = parse(&logs);
+        assert!(errors.is_empty(), "{errors:?}");
+
+        let spin = &parsed.invocations[1];
+        assert_eq!(spin.result, InvocationResult::Failed("custom program error: 0x1".to_string()));
+        let transfer = &spin.children[0];
+        assert_eq!(transfer.result, spin.result);
+        assert_eq!(transfer.logs, ["Instruction: Transfer", "Error: insufficient funds"]);
+    }
+
+    #[test]
+    fn test_truncated() {
+        let logs = fixture(include_str!("../fixtures/logs/truncated.log"));
+        let (parsed, errors) = parse(&logs);
+        assert!(errors.is_empty(), "{errors:?}");
+        assert!(parsed.truncated);
+
+        let spin = &parsed.invocations[0];
+        assert_eq!(spin.result, InvocationResult::Unfinished);
+        assert_eq!(spin.children[0].result, InvocationResult::Success);
+        assert_eq!(spin.children[1].result, InvocationResult::Unfinished);
+        assert_eq!(spin.logs.len(), 3);
+    }
+
+    #[test]
+    fn test_return_data() {
+        let logs = fixture(include_str!("../fixtures/logs/return-data.log"));
+        let (parsed, errors) = parse(&logs);
+        assert!(errors.is_empty(), "{errors:?}");
+
+        let program = &parsed.invocations[0];
+        assert_eq!(program.return_data, None);
+        assert_eq!(program.children[0].return_data, Some(vec![1, 2, 3]));
+        assert_eq!(program.children[0].data, [b"event".to_vec(), b"fields".to_vec()]);
+    }
+
+    #[test]
+    fn test_plain_messages() {
+        // Alphanumeric messages are not events
+        let logs = fixture(include_str!("../fixtures/logs/plain-messages.log"));
+        let (events, errors) = process(&logs, &[Pubkey::from_str(SPINNER).unwrap()]);
+        assert!(errors.is_empty(), "{errors:?}");
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn test_mismatch() {
+        let logs = fixture(include_str!("../fixtures/logs/mismatch.log"));
+        let (parsed, errors) = parse(&logs);
+        assert!(matches!(
+            &errors[..],
+            [
+                ParseLogError::DepthMismatch { current: 1, .. },
+                ParseLogError::ProgramIdMismatch { current: Some(_), .. },
+                ParseLogError::NoCurrentProgramId { .. }
+            ]
+        ));
+        assert_eq!(
+            errors[0].to_string(),
+            "Invocation not at depth 2: Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA invoke [3]"
+        );
+        assert_eq!(
+            errors[1].to_string(),
+            "Program DEoxdV1CCWvbeGp8PpwkUifmm3pV5AgtFwFaS4P7qZeZ is not the current one (TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA): \
+             Program DEoxdV1CCWvbeGp8PpwkUifmm3pV5AgtFwFaS4P7qZeZ success"
+        );
+        assert_eq!(parsed.iter().count(), 2);
+    }
+}
@@ -0,0 +1,238 @@
+//! Whether a request still waits for its fulfillment, set per program with `pending-check` in `vrf-config.toml`.
+//!
+//! Checked before the fulfillment is simulated and before every send, so a request whose fulfillment landed in an
+//! attempt the server did not see complete, e.g. before a crash, is not fulfilled twice. Programs without a check are
+//! always fulfilled, their callback has to reject a second fulfillment itself.
+
+use anchor_client::{
+    anchor_lang::AnchorDeserialize,
+    solana_client::nonblocking::rpc_client::RpcClient,
+    solana_sdk::{account::Account, commitment_config::CommitmentConfig, pubkey::Pubkey},
+};
+
+#[async_trait::async_trait]
+pub trait PendingCheck: Send + Sync + std::fmt::Debug {
+    /// `false` when `request` of `program_id` was fulfilled already
+    async fn is_pending(&self, rpc_client: &RpcClient, program_id: &Pubkey, request: &vrf_lib::RequestVrf) -> anyhow::Result<bool>;
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PendingCheckPolicy {
+    /// The callback account at this index exists, e.g. a request account closed by the callback
+    AccountExists(usize),
+    /// The PDA of the program with the seeds `seed` and the key of the callback account at `account-index` exists, e.g.
+    /// the `vrf-lock` of lucky-spinner and jackpot-lottery, and is the lock of the request when `matches` is set
+    #[serde(rename_all = "kebab-case")]
+    PdaExists {
+        seed: String,
+        account_index: usize,
+        #[serde(default)]
+        matches: Option<LockMatch>,
+    },
+}
+
+/// How a lock PDA tells which request it is for. A lock of another request, e.g. a later one of the same user, means
+/// this one was fulfilled
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LockMatch {
+    /// The lock stores the `vrf_lib::VrfRequest` after its discriminator, as the `vrf-lock` of jackpot-lottery
+    VrfRequest,
+    /// `len` bytes of the lock from `lock-offset` after its discriminator are those of the callback arguments from
+    /// `args-offset` after the `VrfResult`, as the amount and slot of a lucky-spinner `vrf-lock`
+    #[serde(rename_all = "kebab-case")]
+    Bytes {
+        lock_offset: usize,
+        args_offset: usize,
+        len: usize,
+    },
+}
+
+impl LockMatch {
+    fn matches(&self, program_id: &Pubkey, data: &[u8], request: &vrf_lib::RequestVrf) -> bool {
+        let data = match data.get(8..) {
+            Some(data) => data,
+            None => return false,
+        };
+
+        match self {
+            LockMatch::VrfRequest => {
+                vrf_lib::VrfRequest::deserialize(&mut &data[..]).is_ok_and(|lock| lock.alpha(program_id) == request.alpha(program_id))
+            }
+            LockMatch::Bytes {
+                lock_offset,
+                args_offset,
+                len,
+            } => {
+                let lock = data.get(*lock_offset..lock_offset + len);
+                let args = request
+                    .ix_data
+                    .get(vrf_lib::VrfResult::PLACEHOLDER_LEN + args_offset..)
+                    .and_then(|args| args.get(..*len));
+                lock.is_some() && lock == args
+            }
+        }
+    }
+}
+
+impl PendingCheckPolicy {
+    pub fn new_check(&self) -> Box<dyn PendingCheck> {
+        match self {
+            PendingCheckPolicy::AccountExists(account_index) => Box::new(AccountExists {
+                account_index: *account_index,
+            }),
+            PendingCheckPolicy::PdaExists {
+                seed,
+                account_index,
+                matches,
+            } => Box::new(PdaExists {
+                seed: seed.as_bytes().to_vec(),
+                account_index: *account_index,
+                matches: matches.clone(),
+            }),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct AccountExists {
+    account_index: usize,
+}
+
+#[async_trait::async_trait]
+impl PendingCheck for AccountExists {
+    async fn is_pending(&self, rpc_client: &RpcClient, _program_id: &Pubkey, request: &vrf_lib::RequestVrf) -> anyhow::Result<bool> {
+        Ok(get_account(rpc_client, &callback_account(request, self.account_index)?)
+            .await?
+            .is_some())
+    }
+}
+
+#[derive(Debug)]
+pub struct PdaExists {
+    seed: Vec<u8>,
+    account_index: usize,
+    matches: Option<LockMatch>,
+}
+
+impl PdaExists {
+    fn address(&self, program_id: &Pubkey, request: &vrf_lib::RequestVrf) -> anyhow::Result<Pubkey> {
+        let account = callback_account(request, self.account_index)?;
+        Ok(Pubkey::find_program_address(&[&self.seed, account.as_ref()], program_id).0)
+    }
+}
+
+#[async_trait::async_trait]
+impl PendingCheck for PdaExists {
+    async fn is_pending(&self, rpc_client: &RpcClient, program_id: &Pubkey, request: &vrf_lib::RequestVrf) -> anyhow::Result<bool> {
+        let account = get_account(rpc_client, &self.address(program_id, request)?).await?;
+        Ok(match (account, &self.matches) {
+            (Some(account), Some(matches)) => matches.matches(program_id, &account.data, request),
+            (account, _) => account.is_some(),
+        })
+    }
+}
+
+fn callback_account(request: &vrf_lib::RequestVrf, account_index: usize) -> anyhow::Result<Pubkey> {
+    request
+        .accounts
+        .get(account_index)
+        .map(|account| account.pubkey)
+        .ok_or_else(|| anyhow::anyhow!("No callback account {account_index}, the request has {}", request.accounts.len()))
+}
+
+/// At the confirmed commitment, a fulfillment which landed recently is not final yet
+async fn get_account(rpc_client: &RpcClient, address: &Pubkey) -> anyhow::Result<Option<Account>> {
+    let account = rpc_client
+        .get_account_with_commitment(address, CommitmentConfig::confirmed())
+        .await?
+        .value;
+    Ok(account.filter(|account| account.lamports > 0))
+}
+
+#[cfg(test)]
+mod test {
+    use anchor_client::anchor_lang::AnchorSerialize;
+
+    use super::*;
+
+    #[test]
+    fn test_pda_address() {
+        let program_id = Pubkey::new_unique();
+        let user = Pubkey::new_unique();
+        let request = vrf_lib::RequestVrf {
+            ix_sighash: [0; 8],
+            ix_data: vec![],
+            accounts: vec![vrf_lib::AccountMetaRef {
+                pubkey: user,
+                is_writable: true,
+            }],
+        };
+
+        let policies: Vec<PendingCheckPolicy> = serde_json::from_value(serde_json::json!([
+            { "account-exists": 0 },
+            { "pda-exists": { "seed": "vrf-lock", "account-index": 0 } },
+        ]))
+        .unwrap();
+        assert!(matches!(policies[0], PendingCheckPolicy::AccountExists(0)));
+
+        let check = match &policies[1] {
+            PendingCheckPolicy::PdaExists {
+                seed,
+                account_index,
+                matches: None,
+            } => PdaExists {
+                seed: seed.as_bytes().to_vec(),
+                account_index: *account_index,
+                matches: None,
+            },
+            policy => panic!("{policy:?}"),
+        };
+        let (vrf_lock, _) = Pubkey::find_program_address(&[b"vrf-lock", user.as_ref()], &program_id);
+        assert_eq!(check.address(&program_id, &request).unwrap(), vrf_lock);
+
+        let check = PdaExists { account_index: 1, ..check };
+        assert!(check.address(&program_id, &request).is_err());
+    }
+
+    #[test]
+    fn test_lock_match() {
+        let program_id = Pubkey::new_unique();
+        let request = |amount: u64, slot: u64| {
+            let mut ix_data = vrf_lib::VrfResult::default().try_to_vec().unwrap();
+            ix_data.extend(amount.to_le_bytes());
+            ix_data.extend(slot.to_le_bytes());
+            vrf_lib::RequestVrf {
+                ix_sighash: [1; 8],
+                ix_data,
+                accounts: vec![vrf_lib::AccountMetaRef {
+                    pubkey: Pubkey::new_unique(),
+                    is_writable: true,
+                }],
+            }
+        };
+
+        // Discriminator, amount, slot and timestamp of a lucky-spinner lock
+        let matches: LockMatch = serde_json::from_value(serde_json::json!({
+            "bytes": { "lock-offset": 0, "args-offset": 0, "len": 16 }
+        }))
+        .unwrap();
+        let lock = [[2; 8], 5u64.to_le_bytes(), 100u64.to_le_bytes(), 0i64.to_le_bytes()].concat();
+        assert!(matches.matches(&program_id, &lock, &request(5, 100)));
+        assert!(!matches.matches(&program_id, &lock, &request(5, 99)));
+        assert!(!matches.matches(&program_id, &lock[..8], &request(5, 100)));
+
+        let request = request(5, 100);
+        let stored = vrf_lib::VrfRequest {
+            ix_sighash: request.ix_sighash,
+            ix_data: request.ix_data.clone(),
+            accounts: request.accounts.clone(),
+        };
+        let lock = [vec![2; 8], stored.try_to_vec().unwrap()].concat();
+        assert!(LockMatch::VrfRequest.matches(&program_id, &lock, &request));
+        let other = vrf_lib::VrfRequest { ix_data: vec![], ..stored };
+        let lock = [vec![2; 8], other.try_to_vec().unwrap()].concat();
+        assert!(!LockMatch::VrfRequest.matches(&program_id, &lock, &request));
+    }
+}
@@ -0,0 +1,236 @@
+//! Whether a fulfillment failing in simulation is worth retrying, from the error codes in the games' Anchor IDL.
+//!
+//! A request is fatal when it can never be fulfilled, e.g. the game moved to another stage, and retryable when the
+//! game can recover on its own, e.g. once its pool is funded again. Each program lists in `program-errors` the names of
+//! its IDL errors to retry, any other error of the program is fatal.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+};
+
+use anchor_client::solana_sdk::{instruction::InstructionError, pubkey::Pubkey, transaction::TransactionError};
+use anyhow::Context;
+
+/// Anchor numbers the `#[error_code]` variants of a program from here
+const ANCHOR_ERROR_CODE_OFFSET: u32 = 6000;
+const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+/// `spl_token::error::TokenError::InsufficientFunds`, a pool too low to pay out. Retried with the proof of the first
+/// attempt, so the outcome stays the same until the pool is funded
+const TOKEN_INSUFFICIENT_FUNDS: u32 = 1;
+
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ProgramErrorPolicy {
+    /// Anchor IDL JSON of the program
+    pub idl_file: Option<PathBuf>,
+    /// Names of the IDL errors to retry
+    #[serde(default)]
+    pub retryable: Vec<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct Idl {
+    #[serde(default)]
+    errors: Vec<IdlErrorCode>,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+struct IdlErrorCode {
+    code: u32,
+    name: String,
+    msg: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct ProgramErrors {
+    errors: HashMap<u32, IdlErrorCode>,
+    retryable: HashSet<String>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Classification {
+    pub is_fatal: bool,
+    /// Custom error code of the failed instruction
+    pub program_error_code: Option<u32>,
+    pub description: String,
+}
+
+impl std::fmt::Display for Classification {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = if self.is_fatal { "fatal" } else { "retryable" };
+        write!(f, "{kind}, {}", self.description)
+    }
+}
+
+impl ProgramErrors {
+    pub fn load(policy: &ProgramErrorPolicy) -> anyhow::Result<Self> {
+        let idl = match &policy.idl_file {
+            Some(path) => std::fs::read_to_string(path).with_context(|| format!("read IDL {}", path.display()))?,
+            None if policy.retryable.is_empty() => return Ok(Self::default()),
+            None => anyhow::bail!("retryable errors configured without idl-file"),
+        };
+
+        Self::from_idl(&idl, &policy.retryable)
+    }
+
+    fn from_idl(idl: &str, retryable: &[String]) -> anyhow::Result<Self> {
+        let idl: Idl = serde_json::from_str(idl).context("parse IDL")?;
+        let errors = idl.errors.into_iter().map(|error| (error.code, error)).collect::<HashMap<_, _>>();
+
+        if let Some(name) = retryable.iter().find(|name| !errors.values().any(|error| &error.name == *name)) {
+            anyhow::bail!("retryable error {name} is not in the IDL");
+        }
+
+        Ok(Self {
+            errors,
+            retryable: retryable.iter().cloned().collect(),
+        })
+    }
+
+    /// Classify the simulation error of a fulfillment of `program_id`
+    pub fn classify<S: AsRef<str>>(&self, program_id: &Pubkey, err: &TransactionError, logs: &[S]) -> Classification {
+        let code = match err {
+            TransactionError::InstructionError(_, InstructionError::Custom(code)) => *code,
+            TransactionError::InstructionError(_, InstructionError::ComputationalBudgetExceeded) => {
+                return Classification {
+                    is_fatal: false,
+                    program_error_code: None,
+                    description: "compute budget exceeded".to_string(),
+                }
+            }
+            TransactionError::InstructionError(_, err) => {
+                return Classification {
+                    is_fatal: true,
+                    program_error_code: None,
+                    description: format!("instruction error: {err}"),
+                }
+            }
+            // Not about the request itself: fees, blockhash, account locks...
+            err => {
+                return Classification {
+                    is_fatal: false,
+                    program_error_code: None,
+                    description: format!("transaction error: {err}"),
+                }
+            }
+        };
+
+        // The first program to fail is where the error comes from, the programs invoking it fail after
+        let failed_program = logs.iter().find_map(|log| {
+            let log = log.as_ref().strip_prefix("Program ")?;
+            let (program, message) = log.split_once(' ')?;
+            message.starts_with("failed: ").then_some(program)
+        });
+
+        match failed_program {
+            Some(TOKEN_PROGRAM_ID) if code == TOKEN_INSUFFICIENT_FUNDS => Classification {
+                is_fatal: false,
+                program_error_code: Some(code),
+                description: "token program error: insufficient funds".to_string(),
+            },
+            Some(failed) if failed == program_id.to_string() && code >= ANCHOR_ERROR_CODE_OFFSET => match self.errors.get(&code) {
+                Some(error) => Classification {
+                    is_fatal: !self.retryable.contains(&error.name),
+                    program_error_code: Some(code),
+                    description: format!("program error {code} {}: {}", error.name, error.msg.as_deref().unwrap_or_default()),
+                },
+                None => Classification {
+                    is_fatal: true,
+                    program_error_code: Some(code),
+                    description: format!("program error {code}"),
+                },
+            },
+            Some(failed) => Classification {
+                is_fatal: true,
+                program_error_code: Some(code),
+                description: format!("program {failed} error {code}"),
+            },
+            None => Classification {
+                is_fatal: true,
+                program_error_code: Some(code),
+                description: format!("custom program error {code}"),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const IDL: &str = r#"{
+        "version": "0.1.0",
+        "name": "lucky_spinner",
+        "instructions": [],
+        "errors": [
+            { "code": 6000, "name": "InvalidStage", "msg": "Program is not in an expected stage" },
+            { "code": 6009, "name": "StaleRequest", "msg": "Fulfillment does not match the pending spin" }
+        ]
+    }"#;
+
+    fn failed_logs(programs: &[&str], code: u32) -> Vec<String> {
+        programs
+            .iter()
+            .map(|program| format!("Program {program} failed: custom program error: {code:#x}"))
+            .collect()
+    }
+
+    #[test]
+    fn test_classify() {
+        let program_id = Pubkey::new_unique();
+        let program = program_id.to_string();
+        let errors = ProgramErrors::from_idl(IDL, &["StaleRequest".to_string()]).unwrap();
+        let custom = |code| TransactionError::InstructionError(1, InstructionError::Custom(code));
+
+        let classification = errors.classify(&program_id, &custom(6000), &failed_logs(&[&program], 6000));
+        assert_eq!(
+            classification,
+            Classification {
+                is_fatal: true,
+                program_error_code: Some(6000),
+                description: "program error 6000 InvalidStage: Program is not in an expected stage".to_string(),
+            }
+        );
+        assert!(
+            !errors
+                .classify(&program_id, &custom(6009), &failed_logs(&[&program], 6009))
+                .is_fatal
+        );
+        assert!(
+            errors
+                .classify(&program_id, &custom(6010), &failed_logs(&[&program], 6010))
+                .is_fatal
+        );
+        // Anchor constraint error
+        assert!(
+            errors
+                .classify(&program_id, &custom(2003), &failed_logs(&[&program], 2003))
+                .is_fatal
+        );
+
+        let classification = errors.classify(&program_id, &custom(1), &failed_logs(&[TOKEN_PROGRAM_ID, &program], 1));
+        assert!(!classification.is_fatal);
+        assert!(errors.classify(&program_id, &custom(1), &failed_logs(&[&program], 1)).is_fatal);
+        assert!(errors.classify(&program_id, &custom(6009), &Vec::<String>::new()).is_fatal);
+
+        assert!(
+            !errors
+                .classify(&program_id, &TransactionError::BlockhashNotFound, &failed_logs(&[], 0))
+                .is_fatal
+        );
+        let exceeded = TransactionError::InstructionError(1, InstructionError::ComputationalBudgetExceeded);
+        assert!(!errors.classify(&program_id, &exceeded, &failed_logs(&[], 0)).is_fatal);
+    }
+
+    #[test]
+    fn test_unknown_retryable() {
+        assert!(ProgramErrors::from_idl(IDL, &["InvalidStage".to_string()]).is_ok());
+        assert!(ProgramErrors::from_idl(IDL, &["NoSuchError".to_string()]).is_err());
+        assert!(ProgramErrors::load(&ProgramErrorPolicy {
+            idl_file: None,
+            retryable: vec!["InvalidStage".to_string()],
+        })
+        .is_err());
+    }
+}
@@ -0,0 +1,51 @@
+//! Randomness derived from a seed in the config, anyone knowing the seed can predict every result.
+//! The "public key" is the seed itself and the "proof" is `sha256(seed || alpha)`.
+
+use anchor_client::solana_sdk::hash::hashv;
+
+use super::{ProviderKind, RandomnessProvider};
+
+pub struct DeterministicProvider {
+    seed: Vec<u8>,
+}
+
+impl DeterministicProvider {
+    pub fn new(seed: Vec<u8>) -> Self {
+        Self { seed }
+    }
+}
+
+impl RandomnessProvider for DeterministicProvider {
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Deterministic
+    }
+
+    fn public_key(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(self.seed.clone())
+    }
+
+    fn prove(&self, alpha: &[u8]) -> anyhow::Result<Vec<u8>> {
+        Ok(hashv(&[&self.seed, alpha]).to_bytes().to_vec())
+    }
+}
+
+pub fn verify(public_key: &[u8], proof: &[u8], alpha: &[u8]) -> anyhow::Result<Vec<u8>> {
+    anyhow::ensure!(hashv(&[public_key, alpha]).as_ref() == proof, "Deterministic proof mismatch");
+    Ok(proof.to_vec())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_deterministic() {
+        let provider = DeterministicProvider::new(b"seed".to_vec());
+        let proof = provider.prove(b"alpha").unwrap();
+        assert_eq!(proof, DeterministicProvider::new(b"seed".to_vec()).prove(b"alpha").unwrap());
+        assert_ne!(proof, provider.prove(b"other").unwrap());
+
+        assert_eq!(verify(b"seed", &proof, b"alpha").unwrap(), proof);
+        assert!(verify(b"other seed", &proof, b"alpha").is_err());
+    }
+}
@@ -0,0 +1,226 @@
+//! ECVRF-EDWARDS25519-SHA512-TAI as specified in RFC 9381
+
+use curve25519_dalek::{
+    constants::ED25519_BASEPOINT_POINT,
+    edwards::{CompressedEdwardsY, EdwardsPoint},
+    scalar::Scalar,
+};
+use sha2::{Digest, Sha512};
+
+use super::{ProviderKind, RandomnessProvider};
+
+const SUITE_STRING: u8 = 0x03;
+const PROOF_LEN: usize = 32 + 16 + 32;
+
+pub struct Edwards25519Provider {
+    /// SHA512 of the 32 byte secret key
+    hashed_secret: [u8; 64],
+    x: Scalar,
+    public_key: [u8; 32],
+}
+
+impl Edwards25519Provider {
+    /// Uses the first 32 bytes of `secret` as the RFC 8032 secret key, so a keypair in the `secret || public` format works
+    pub fn new(secret: &[u8]) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            secret.len() >= 32,
+            "ecvrf-edwards25519 needs a 32 bytes secret, got {}",
+            secret.len()
+        );
+
+        let mut hashed_secret = [0u8; 64];
+        hashed_secret.copy_from_slice(&Sha512::digest(&secret[..32]));
+        let mut x = [0u8; 32];
+        x.copy_from_slice(&hashed_secret[..32]);
+        x[0] &= 248;
+        x[31] &= 127;
+        x[31] |= 64;
+        let x = Scalar::from_bytes_mod_order(x);
+
+        Ok(Self {
+            hashed_secret,
+            x,
+            public_key: (x * ED25519_BASEPOINT_POINT).compress().to_bytes(),
+        })
+    }
+}
+
+impl RandomnessProvider for Edwards25519Provider {
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::EcvrfEdwards25519
+    }
+
+    fn public_key(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(self.public_key.to_vec())
+    }
+
+    fn prove(&self, alpha: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let h = hash_to_curve(&self.public_key, alpha)?;
+        let h_string = h.compress().to_bytes();
+        let gamma = self.x * h;
+
+        let k = Scalar::from_hash(Sha512::new().chain(&self.hashed_secret[32..]).chain(h_string));
+
+        let c = challenge(&self.public_key, &h, &gamma, &(k * ED25519_BASEPOINT_POINT), &(k * h));
+        let s = k + scalar_from_challenge(&c) * self.x;
+
+        let mut proof = Vec::with_capacity(PROOF_LEN);
+        proof.extend_from_slice(gamma.compress().as_bytes());
+        proof.extend_from_slice(&c);
+        proof.extend_from_slice(s.as_bytes());
+        Ok(proof)
+    }
+}
+
+pub fn proof_to_hash(proof: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let (gamma, _, _) = decode_proof(proof)?;
+    Ok(gamma_to_hash(&gamma))
+}
+
+pub fn verify(public_key: &[u8], proof: &[u8], alpha: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let public_key: [u8; 32] = public_key
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("Invalid public key length: {}", public_key.len()))?;
+    let y = decode_point(&public_key).ok_or_else(|| anyhow::anyhow!("Invalid public key"))?;
+    anyhow::ensure!(!y.is_small_order(), "Invalid public key: small order point");
+
+    let (gamma, c, s) = decode_proof(proof)?;
+    let h = hash_to_curve(&public_key, alpha)?;
+
+    let c_scalar = scalar_from_challenge(&c);
+    let u = s * ED25519_BASEPOINT_POINT - c_scalar * y;
+    let v = s * h - c_scalar * gamma;
+
+    anyhow::ensure!(challenge(&public_key, &h, &gamma, &u, &v) == c, "VRF proof verification failed");
+    Ok(gamma_to_hash(&gamma))
+}
+
+/// RFC 8032 decoding, rejects the non canonical encodings `CompressedEdwardsY::decompress` accepts
+fn decode_point(bytes: &[u8; 32]) -> Option<EdwardsPoint> {
+    let point = CompressedEdwardsY(*bytes).decompress()?;
+    (point.compress().as_bytes() == bytes).then_some(point)
+}
+
+fn decode_proof(proof: &[u8]) -> anyhow::Result<(EdwardsPoint, [u8; 16], Scalar)> {
+    anyhow::ensure!(proof.len() == PROOF_LEN, "Invalid VRF proof length: {}", proof.len());
+
+    let gamma = decode_point(proof[..32].try_into().unwrap()).ok_or_else(|| anyhow::anyhow!("Invalid VRF proof gamma"))?;
+    let c = proof[32..48].try_into().unwrap();
+    let s = Scalar::from_canonical_bytes(proof[48..].try_into().unwrap()).ok_or_else(|| anyhow::anyhow!("Invalid VRF proof s"))?;
+    Ok((gamma, c, s))
+}
+
+/// ECVRF_encode_to_curve_try_and_increment
+fn hash_to_curve(public_key: &[u8; 32], alpha: &[u8]) -> anyhow::Result<EdwardsPoint> {
+    for ctr in 0..=u8::MAX {
+        let hash = Sha512::new()
+            .chain([SUITE_STRING, 0x01])
+            .chain(public_key)
+            .chain(alpha)
+            .chain([ctr, 0x00])
+            .finalize();
+
+        if let Some(point) = decode_point(hash[..32].try_into().unwrap()) {
+            return Ok(point.mul_by_cofactor());
+        }
+    }
+
+    anyhow::bail!("Can not hash VRF input to a curve point")
+}
+
+/// ECVRF_challenge_generation, truncated to the 16 byte challenge length of the suite
+fn challenge(public_key: &[u8; 32], h: &EdwardsPoint, gamma: &EdwardsPoint, u: &EdwardsPoint, v: &EdwardsPoint) -> [u8; 16] {
+    let mut hasher = Sha512::new().chain([SUITE_STRING, 0x02]).chain(public_key);
+    for point in [h, gamma, u, v] {
+        hasher.update(point.compress().as_bytes());
+    }
+
+    let hash = hasher.chain([0x00]).finalize();
+    hash[..16].try_into().unwrap()
+}
+
+fn scalar_from_challenge(c: &[u8; 16]) -> Scalar {
+    let mut bytes = [0u8; 32];
+    bytes[..16].copy_from_slice(c);
+    Scalar::from_bytes_mod_order(bytes)
+}
+
+fn gamma_to_hash(gamma: &EdwardsPoint) -> Vec<u8> {
+    Sha512::new()
+        .chain([SUITE_STRING, 0x03])
+        .chain(gamma.mul_by_cofactor().compress().as_bytes())
+        .chain([0x00])
+        .finalize()
+        .to_vec()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn decode_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_rfc9381_vectors() {
+        // RFC 9381 appendix B.3, examples 16 to 18
+        let vectors = [
+            (
+                "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+                "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+                "",
+                "8657106690b5526245a92b003bb079ccd1a92130477671f6fc01ad16f26f723f26f8a57ccaed74ee1b190bed1f479d9727d2d0f9b005a6e456a35d4fb0daab1268a1b0db10836d9826a528ca76567805",
+                "90cf1df3b703cce59e2a35b925d411164068269d7b2d29f3301c03dd757876ff66b71dda49d2de59d03450451af026798e8f81cd2e333de5cdf4f3e140fdd8ae",
+            ),
+            (
+                "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+                "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+                "72",
+                "f3141cd382dc42909d19ec5110469e4feae18300e94f304590abdced48aed5933bf0864a62558b3ed7f2fea45c92a465301b3bbf5e3e54ddf2d935be3b67926da3ef39226bbc355bdc9850112c8f4b02",
+                "eb4440665d3891d668e7e0fcaf587f1b4bd7fbfe99d0eb2211ccec90496310eb5e33821bc613efb94db5e5b54c70a848a0bef4553a41befc57663b56373a5031",
+            ),
+            (
+                "c5aa8df43f9f837bedb7442f31dcb7b166d38535076f094b85ce3a2e0b4458f7",
+                "fc51cd8e6218a1a38da47ed00230f0580816ed13ba3303ac5deb911548908025",
+                "af82",
+                "9bc0f79119cc5604bf02d23b4caede71393cedfbb191434dd016d30177ccbf8096bb474e53895c362d8628ee9f9ea3c0e52c7a5c691b6c18c9979866568add7a2d41b00b05081ed0f58ee5e31b3a970e",
+                "645427e5d00c62a23fb703732fa5d892940935942101e456ecca7bb217c61c452118fec1219202a0edcf038bb6373241578be7217ba85a2687f7a0310b2df19f",
+            ),
+        ];
+
+        for (secret, public_key, alpha, proof, beta) in vectors {
+            let provider = Edwards25519Provider::new(&decode_hex(secret)).unwrap();
+            let alpha = decode_hex(alpha);
+
+            assert_eq!(provider.public_key().unwrap(), decode_hex(public_key));
+            assert_eq!(provider.prove(&alpha).unwrap(), decode_hex(proof));
+            assert_eq!(proof_to_hash(&decode_hex(proof)).unwrap(), decode_hex(beta));
+            assert_eq!(
+                verify(&decode_hex(public_key), &decode_hex(proof), &alpha).unwrap(),
+                decode_hex(beta)
+            );
+        }
+    }
+
+    #[test]
+    fn test_verify_tampered() {
+        let provider = Edwards25519Provider::new(&[9; 32]).unwrap();
+        let public_key = provider.public_key().unwrap();
+        let proof = provider.prove(b"sample").unwrap();
+        assert!(verify(&public_key, &proof, b"sample").is_ok());
+        assert!(verify(&public_key, &proof, b"other").is_err());
+
+        for index in [0, 40, 60] {
+            let mut tampered = proof.clone();
+            tampered[index] ^= 1;
+            assert!(verify(&public_key, &tampered, b"sample").is_err());
+        }
+
+        let other = Edwards25519Provider::new(&[8; 32]).unwrap();
+        assert!(verify(&other.public_key().unwrap(), &proof, b"sample").is_err());
+    }
+}
@@ -0,0 +1,85 @@
+//! Randomness backends, selected per program id with `randomness-providers` in `vrf-config.toml`.
+//!
+//! Only `ecvrf-secp256k1` proofs can be checked on-chain by `vrf_lib::registry::OracleRegistry`, the other backends
+//! would send a default `VrfProof` and are rejected by the config. They are kept to verify exported records.
+
+use std::{fmt, sync::Arc};
+
+mod deterministic;
+mod edwards25519;
+mod secp256k1;
+
+pub use deterministic::DeterministicProvider;
+pub use edwards25519::Edwards25519Provider;
+pub use secp256k1::Secp256k1Provider;
+
+pub trait RandomnessProvider: Send + Sync {
+    fn kind(&self) -> ProviderKind;
+
+    fn public_key(&self) -> anyhow::Result<Vec<u8>>;
+
+    fn prove(&self, alpha: &[u8]) -> anyhow::Result<Vec<u8>>;
+
+    /// `proof` as carried in `VrfResult`
+    fn on_chain_proof(&self, _proof: &[u8]) -> anyhow::Result<vrf_lib::VrfProof> {
+        Ok(vrf_lib::VrfProof::default())
+    }
+}
+
+impl fmt::Debug for dyn RandomnessProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind())
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProviderKind {
+    /// ECVRF-SECP256K1-SHA256-TAI
+    #[default]
+    EcvrfSecp256k1,
+    /// ECVRF-EDWARDS25519-SHA512-TAI (RFC 9381)
+    EcvrfEdwards25519,
+    /// Hash of a seed from the config, testing only
+    Deterministic,
+}
+
+impl fmt::Display for ProviderKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ProviderKind::EcvrfSecp256k1 => "ecvrf-secp256k1",
+            ProviderKind::EcvrfEdwards25519 => "ecvrf-edwards25519",
+            ProviderKind::Deterministic => "deterministic",
+        })
+    }
+}
+
+impl ProviderKind {
+    pub fn proof_to_hash(self, proof: &[u8]) -> anyhow::Result<Vec<u8>> {
+        match self {
+            ProviderKind::EcvrfSecp256k1 => secp256k1::proof_to_hash(proof),
+            ProviderKind::EcvrfEdwards25519 => edwards25519::proof_to_hash(proof),
+            ProviderKind::Deterministic => Ok(proof.to_vec()),
+        }
+    }
+
+    /// Check `proof` of `alpha` under `public_key`, returning its hash
+    pub fn verify(self, public_key: &[u8], proof: &[u8], alpha: &[u8]) -> anyhow::Result<Vec<u8>> {
+        match self {
+            ProviderKind::EcvrfSecp256k1 => secp256k1::verify(public_key, proof, alpha),
+            ProviderKind::EcvrfEdwards25519 => edwards25519::verify(public_key, proof, alpha),
+            ProviderKind::Deterministic => deterministic::verify(public_key, proof, alpha),
+        }
+    }
+
+    pub fn new_provider(self, secret: &[u8], deterministic_seed: Option<&[u8]>) -> anyhow::Result<Arc<dyn RandomnessProvider>> {
+        Ok(match self {
+            ProviderKind::EcvrfSecp256k1 => Arc::new(Secp256k1Provider::new(secret.to_vec())),
+            ProviderKind::EcvrfEdwards25519 => Arc::new(Edwards25519Provider::new(secret)?),
+            ProviderKind::Deterministic => {
+                let seed = deterministic_seed.ok_or_else(|| anyhow::anyhow!("deterministic provider needs `deterministic-seed`"))?;
+                Arc::new(DeterministicProvider::new(seed.to_vec()))
+            }
+        })
+    }
+}
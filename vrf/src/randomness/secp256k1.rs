@@ -0,0 +1,84 @@
+use std::cell::RefCell;
+
+use once_cell::unsync::Lazy;
+use vrf::{
+    openssl::{CipherSuite, ECVRF},
+    VRF,
+};
+
+use super::{ProviderKind, RandomnessProvider};
+
+thread_local! {
+    static VRF: RefCell<Lazy<ECVRF>> = RefCell::new(Lazy::new(|| {
+        ECVRF::from_suite(CipherSuite::SECP256K1_SHA256_TAI).unwrap()
+    }));
+}
+
+pub struct Secp256k1Provider {
+    secret: Vec<u8>,
+}
+
+impl Secp256k1Provider {
+    pub fn new(secret: Vec<u8>) -> Self {
+        Self { secret }
+    }
+}
+
+impl RandomnessProvider for Secp256k1Provider {
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::EcvrfSecp256k1
+    }
+
+    fn public_key(&self) -> anyhow::Result<Vec<u8>> {
+        VRF.with(|vrf| {
+            vrf.borrow_mut()
+                .derive_public_key(&self.secret)
+                .map_err(|err| anyhow::anyhow!("Derive VRF public key: {err:?}"))
+        })
+    }
+
+    fn prove(&self, alpha: &[u8]) -> anyhow::Result<Vec<u8>> {
+        VRF.with(|vrf| {
+            vrf.borrow_mut()
+                .prove(&self.secret, alpha)
+                .map_err(|err| anyhow::anyhow!("VRF prove: {err:?}"))
+        })
+    }
+
+    fn on_chain_proof(&self, proof: &[u8]) -> anyhow::Result<vrf_lib::VrfProof> {
+        vrf_lib::VrfProof::from_bytes(proof).ok_or_else(|| anyhow::anyhow!("Invalid VRF proof length: {}", proof.len()))
+    }
+}
+
+pub fn proof_to_hash(proof: &[u8]) -> anyhow::Result<Vec<u8>> {
+    VRF.with(|vrf| {
+        vrf.borrow_mut()
+            .proof_to_hash(proof)
+            .map_err(|err| anyhow::anyhow!("VRF proof to hash: {err:?}"))
+    })
+}
+
+pub fn verify(public_key: &[u8], proof: &[u8], alpha: &[u8]) -> anyhow::Result<Vec<u8>> {
+    VRF.with(|vrf| {
+        vrf.borrow_mut()
+            .verify(public_key, proof, alpha)
+            .map_err(|err| anyhow::anyhow!("VRF verify: {err:?}"))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_on_chain_verify() {
+        let provider = Secp256k1Provider::new(vec![7; 32]);
+        let alpha = b"sample";
+        let proof = provider.prove(alpha).unwrap();
+        let hash = verify(&provider.public_key().unwrap(), &proof, alpha).unwrap();
+
+        let public_key = vrf_lib::VrfPublicKey::from_bytes(&provider.public_key().unwrap()).unwrap();
+        let on_chain_hash = vrf_lib::ecvrf::verify(&public_key, &provider.on_chain_proof(&proof).unwrap(), alpha).unwrap();
+        assert_eq!(hash, on_chain_hash);
+    }
+}
@@ -0,0 +1,204 @@
+//! Transactions the ingestion source missed, e.g. while it reconnected or before the server started, fetched with
+//! `getSignaturesForAddress` from a cursor persisted per program.
+//!
+//! Each pass pages back from the finalized tip `until` the cursor, then processes the signatures oldest first and moves
+//! the cursor past each of them, so a restart resumes where the last pass stopped. A program without a cursor, or whose
+//! cursor is older than `backfill-horizon-slots`, is only backfilled that far back.
+
+use std::{str::FromStr, sync::Arc, time::Duration};
+
+use anchor_client::{
+    solana_client::{
+        nonblocking::rpc_client::RpcClient, rpc_client::GetConfirmedSignaturesForAddress2Config,
+        rpc_response::RpcConfirmedTransactionStatusWithSignature,
+    },
+    solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature},
+};
+use solana_transaction_status::{UiTransactionEncoding, UiTransactionStatusMeta};
+use tokio::sync::Notify;
+
+use super::{process, PreviousAttempts};
+use crate::{
+    db::{BackfillCursor, Storage},
+    VrfConfig,
+};
+
+/// Most signatures returned by a `getSignaturesForAddress` call
+const PAGE_LIMIT: usize = 1000;
+const BACKFILL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Backfill every program each `BACKFILL_INTERVAL`, or right away when `trigger` is notified
+pub async fn backfill(config: Arc<VrfConfig>, storage: Arc<dyn Storage>, rpc_client: Arc<RpcClient>, trigger: Arc<Notify>) -> ! {
+    loop {
+        for program_id in &config.program_ids {
+            if let Err(err) = backfill_program(&config, &*storage, &rpc_client, program_id).await {
+                tracing::error!("Backfill ({program_id}) error: {err:#}");
+            }
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(BACKFILL_INTERVAL) => {}
+            _ = trigger.notified() => {}
+        }
+    }
+}
+
+async fn backfill_program(config: &VrfConfig, storage: &dyn Storage, rpc_client: &RpcClient, program_id: &Pubkey) -> anyhow::Result<()> {
+    let program = program_id.to_string();
+    let tip = rpc_client.get_slot_with_commitment(CommitmentConfig::finalized()).await?;
+    let horizon = tip.saturating_sub(config.backfill_horizon_slots);
+
+    let until = match storage.get_backfill_cursor(&program)? {
+        Some(cursor) if u64::try_from(cursor.slot)? >= horizon => Some(Signature::from_str(&cursor.signature)?),
+        Some(cursor) => {
+            tracing::warn!(
+                "Backfill ({program}) cursor at slot {} is beyond the horizon, transactions before slot {horizon} are skipped",
+                cursor.slot
+            );
+            None
+        }
+        None => None,
+    };
+
+    let signatures = signatures_since(rpc_client, program_id, until, horizon).await?;
+    if signatures.is_empty() {
+        return Ok(());
+    }
+
+    tracing::info!("Backfill ({program}): {} transactions", signatures.len());
+    let mut gap = Gap::default();
+    for status in signatures.into_iter().rev() {
+        let RpcConfirmedTransactionStatusWithSignature { signature, slot, err, .. } = status;
+
+        if err.is_none() {
+            if backfill_transaction(config, storage, rpc_client, &program, &signature).await? {
+                gap.extend(slot);
+            } else {
+                gap.report(&program);
+            }
+        }
+
+        storage.set_backfill_cursor(
+            &program,
+            &BackfillCursor {
+                slot: i64::try_from(slot)?,
+                signature,
+            },
+        )?;
+    }
+    gap.report(&program);
+
+    Ok(())
+}
+
+/// Signatures of `program_id` after `until`, or back to the `horizon` slot, newest first
+async fn signatures_since(
+    rpc_client: &RpcClient,
+    program_id: &Pubkey,
+    until: Option<Signature>,
+    horizon: u64,
+) -> anyhow::Result<Vec<RpcConfirmedTransactionStatusWithSignature>> {
+    let mut signatures = Vec::new();
+    let mut before = None;
+    loop {
+        let page = rpc_client
+            .get_signatures_for_address_with_config(
+                program_id,
+                GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    until,
+                    limit: Some(PAGE_LIMIT),
+                    commitment: Some(CommitmentConfig::finalized()),
+                },
+            )
+            .await?;
+
+        let page_len = page.len();
+        let reached_horizon = page.iter().any(|status| status.slot < horizon);
+        before = page.last().map(|status| Signature::from_str(&status.signature)).transpose()?;
+        signatures.extend(page.into_iter().filter(|status| status.slot >= horizon));
+
+        if page_len < PAGE_LIMIT || reached_horizon {
+            return Ok(signatures);
+        }
+    }
+}
+
+/// Store and process a transaction, `true` when the ingestion source had not stored it
+async fn backfill_transaction(
+    config: &VrfConfig,
+    storage: &dyn Storage,
+    rpc_client: &RpcClient,
+    program_id: &str,
+    signature: &str,
+) -> anyhow::Result<bool> {
+    // Most were stored by the ingestion source, no need to fetch them
+    if storage
+        .get_requests(signature)?
+        .iter()
+        .any(|request| request.program_id == program_id)
+    {
+        return Ok(false);
+    }
+
+    let encoded_transaction = rpc_client
+        .get_transaction(&Signature::from_str(signature)?, UiTransactionEncoding::Json)
+        .await?;
+    let logs = match encoded_transaction.transaction.meta {
+        Some(UiTransactionStatusMeta {
+            err: None,
+            log_messages: Some(logs),
+            ..
+        }) => logs,
+        _ => return Ok(false),
+    };
+
+    if !storage.new_transaction(program_id, signature, &logs.join("\n"))? {
+        return Ok(false);
+    }
+
+    let span = tracing::info_span!("Process old transaction", program_id, transaction = signature);
+    process(
+        config,
+        storage,
+        rpc_client,
+        program_id,
+        signature,
+        0,
+        &span,
+        &logs,
+        PreviousAttempts::default(),
+    )
+    .await;
+    Ok(true)
+}
+
+/// Consecutive transactions found by the backfill only
+#[derive(Debug, Default)]
+struct Gap {
+    count: usize,
+    first_slot: u64,
+    last_slot: u64,
+}
+
+impl Gap {
+    fn extend(&mut self, slot: u64) {
+        if self.count == 0 {
+            self.first_slot = slot;
+        }
+        self.last_slot = slot;
+        self.count += 1;
+    }
+
+    fn report(&mut self, program_id: &str) {
+        if self.count > 0 {
+            tracing::warn!(
+                "Ingestion of {program_id} missed {} transactions in slots {}..={}",
+                self.count,
+                self.first_slot,
+                self.last_slot
+            );
+        }
+        *self = Gap::default();
+    }
+}
@@ -1,138 +1,303 @@
-use std::{collections::HashMap, str::FromStr, sync::Arc, time::Duration};
-
-use anchor_client::{
-    solana_client::{
-        nonblocking::{pubsub_client::PubsubClient, rpc_client::RpcClient},
-        rpc_client::GetConfirmedSignaturesForAddress2Config,
-        rpc_config::{RpcTransactionLogsConfig, RpcTransactionLogsFilter},
-        rpc_response::{RpcConfirmedTransactionStatusWithSignature, RpcLogsResponse},
-    },
-    solana_sdk::{commitment_config::CommitmentConfig, signature::Signature},
-};
-use futures_util::stream::StreamExt;
-use solana_transaction_status::{UiTransactionEncoding, UiTransactionStatusMeta};
+use std::{sync::Arc, time::Duration};
+
+use anchor_client::solana_client::nonblocking::rpc_client::RpcClient;
+use tokio::sync::{mpsc, Notify};
 
 use crate::{
-    db::{self, RetryableTransaction},
-    task::vrf::{process_log_response, VrfResponse},
+    db::{RetryableTransaction, Storage},
+    error::ProcessError,
+    task::vrf::{process_log_response, request_events, Outcome, VrfResponse},
     VrfConfig,
 };
 
+mod backfill;
+mod recovery;
+pub mod source;
 mod vrf;
 
+pub use backfill::backfill;
+pub use recovery::recover_stuck_transaction;
+
+/// Failed attempts of a request before this one
+#[derive(Debug, Default)]
+struct PreviousAttempts {
+    count: u32,
+    compute_unit_price: Option<u64>,
+    /// Of the first attempt, reused so a failed fulfillment is never re-rolled
+    seeds: Option<Vec<u8>>,
+    proof: Option<Vec<u8>>,
+}
+
+/// Process the request at `event_index` of a transaction. The first request stores the others the transaction made,
+/// which are processed alongside
+#[allow(clippy::too_many_arguments)]
 async fn process<S: AsRef<str>>(
     config: &VrfConfig,
+    storage: &dyn Storage,
     rpc_client: &RpcClient,
     program_id: &str,
     signature: &str,
+    event_index: i32,
     span: &tracing::Span,
     logs: &[S],
+    previous: PreviousAttempts,
 ) {
-    match db::process_transaction(program_id, signature) {
+    let new_events = process_event(
+        config,
+        storage,
+        rpc_client,
+        program_id,
+        signature,
+        event_index,
+        span,
+        logs,
+        previous,
+    )
+    .await;
+
+    let new_events = new_events.into_iter().map(|event_index| async move {
+        let span = tracing::info_span!(parent: span, "Process event", event_index);
+        process_event(
+            config,
+            storage,
+            rpc_client,
+            program_id,
+            signature,
+            event_index,
+            &span,
+            logs,
+            PreviousAttempts::default(),
+        )
+        .await
+    });
+    futures_util::future::join_all(new_events).await;
+}
+
+/// Returns the indexes of the requests after the first which were not stored yet
+#[allow(clippy::too_many_arguments)]
+async fn process_event<S: AsRef<str>>(
+    config: &VrfConfig,
+    storage: &dyn Storage,
+    rpc_client: &RpcClient,
+    program_id: &str,
+    signature: &str,
+    event_index: i32,
+    span: &tracing::Span,
+    logs: &[S],
+    previous: PreviousAttempts,
+) -> Vec<i32> {
+    match storage.process_transaction(program_id, signature, event_index, &config.instance_id, config.processing_lease) {
         Ok(true) => {}
-        Ok(false) => return,
+        Ok(false) => return Vec::new(),
         Err(err) => {
             span.in_scope(|| tracing::info!("[DB] Start process transaction error: {err:#}"));
-            return;
+            return Vec::new();
         }
     }
 
-    let result = process_log_response(config, rpc_client, signature, span, logs).await;
+    // Keep the claim while processing, it expires if this instance stops and the request is recovered
+    let heartbeat = async {
+        loop {
+            tokio::time::sleep(config.processing_lease / 3).await;
+            if let Err(err) = storage.renew_lease(program_id, signature, event_index, &config.instance_id, config.processing_lease) {
+                span.in_scope(|| tracing::warn!("[DB] Renew lease error: {err:#}"));
+            }
+        }
+    };
+
+    let mut new_events = Vec::new();
+    let result = tokio::select! {
+        result = async {
+            let events = request_events(config, rpc_client, signature, span, logs).await?;
+            if event_index == 0 && events.len() > 1 {
+                let log_messages = logs.iter().map(|log| log.as_ref()).collect::<Vec<_>>().join("\n");
+                new_events = storage
+                    .new_events(program_id, signature, &log_messages, events.len())
+                    .map_err(ProcessError::Db)?;
+            }
+
+            let event = usize::try_from(event_index).ok().and_then(|index| events.get(index));
+            process_log_response(
+                config,
+                storage,
+                rpc_client,
+                program_id,
+                signature,
+                event_index,
+                span,
+                event,
+                &previous,
+            )
+            .await
+        } => result,
+        _ = heartbeat => unreachable!("heartbeat never ends"),
+    };
 
     let _enter = span.enter();
     let db_result = match result {
-        Ok(resp) => {
+        Ok(outcome) => {
             tracing::info!("Processed ({signature})");
-            if let Some(VrfResponse {
-                response_transaction,
-                seeds,
-                proof,
-            }) = resp
-            {
-                db::complete_processing(&program_id, &signature, response_transaction, seeds, proof)
-            } else {
-                db::complete_none_vrf_processing(&program_id, &signature)
+            match outcome {
+                Outcome::Fulfilled(VrfResponse {
+                    response_transaction,
+                    seeds,
+                    proof,
+                }) => storage.complete_processing(program_id, signature, event_index, response_transaction, seeds, proof),
+                Outcome::NoRequest => storage.complete_none_vrf_processing(program_id, signature, event_index),
+                Outcome::AlreadyFulfilled => storage.complete_already_fulfilled(program_id, signature, event_index),
             }
         }
         Err(err) => {
-            tracing::warn!("Error process log ({signature}):\n {:#}", err.error);
-            db::error_processing(&program_id, &signature, err.is_fatal, format!("{:#}", err.error))
+            tracing::warn!("Error process log ({signature}):\n {err}");
+            let retry_after = config.backoff.next_attempt_delay(previous.count + 1);
+            if !err.is_fatal() && retry_after.is_none() {
+                tracing::error!("Giving up ({signature}) after {} attempts", previous.count + 1);
+            }
+            storage.error_processing(program_id, signature, event_index, err.to_request_error(), retry_after)
         }
     };
 
     if let Err(err) = db_result {
         tracing::error!("[DB] Complete transaction error: {err:#}");
     }
+
+    new_events
 }
 
-pub async fn logs_subscribe(config: Arc<VrfConfig>, program_id: Arc<String>, rpc_client: Arc<RpcClient>) -> ! {
-    loop {
-        let pubsub_client = PubsubClient::new(config.cluster.ws_url()).await.unwrap();
-
-        let (mut recv_stream, _) = pubsub_client
-            .logs_subscribe(
-                RpcTransactionLogsFilter::Mentions(vec![(*program_id).clone()]),
-                RpcTransactionLogsConfig {
-                    commitment: Some(config.commitment),
-                },
-            )
-            .await
-            .unwrap();
-
-        tracing::info!("Listening for logs from: {}", &program_id);
-        while let Some(response) = recv_stream.next().await {
-            let config = config.clone();
-            let rpc_client = rpc_client.clone();
-            let program_id = program_id.clone();
-
-            tokio::spawn(async move {
-                let program_id: &str = &program_id;
-
-                let RpcLogsResponse { signature, err, logs } = response.value;
-                let span = tracing::info_span!("Process transaction", program_id, transaction = signature);
-                let enter = span.enter();
-                if let Some(err) = err {
-                    tracing::info!("Skipping error transaction ({signature}):\n{err:#}");
-                    return;
-                }
+/// A successful transaction mentioning one of the configured programs
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+pub struct IngestedTransaction {
+    pub program_id: String,
+    pub signature: String,
+    pub logs: Vec<String>,
+}
 
-                let str_logs = logs.join("\n");
-                match db::new_transaction(&program_id, &signature, &str_logs) {
-                    Ok(true) => {
-                        tracing::info!("New transaction added: {}\n{}", &signature, &str_logs);
-                        drop(enter);
-
-                        process(&config, &rpc_client, &program_id, &signature, &span, &logs).await;
-                    }
-                    Ok(false) => {}
-                    Err(err) => {
-                        tracing::error!("[DB] Add new transaction error: {err:#}");
-                    }
-                }
-            });
+/// Where new transactions come from, set with `ingestion` in `vrf-config.toml`
+#[async_trait::async_trait]
+pub trait IngestionSource: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Send the transactions of the configured programs until the source stops, it is run again after a stop
+    async fn run(&self, sender: &mpsc::Sender<IngestedTransaction>) -> anyhow::Result<()>;
+}
+
+/// Store and process what `source` sends. `backfill_trigger` is notified when the source stops, to fetch what it missed
+/// meanwhile
+pub async fn ingest(
+    config: Arc<VrfConfig>,
+    storage: Arc<dyn Storage>,
+    rpc_client: Arc<RpcClient>,
+    source: Arc<dyn IngestionSource>,
+    backfill_trigger: Arc<Notify>,
+) -> ! {
+    let (sender, mut receiver) = mpsc::channel(1024);
+    tokio::spawn(async move {
+        loop {
+            tracing::info!("Ingesting transactions from {}", source.name());
+            if let Err(err) = source.run(&sender).await {
+                tracing::error!("Ingestion source {} error: {err:#}", source.name());
+            }
+
+            tracing::warn!("Ingestion source {} stopped, retrying after 2s", source.name());
+            backfill_trigger.notify_one();
+            tokio::time::sleep(Duration::from_secs(2)).await;
         }
+    });
 
-        tracing::warn!("Logs subscribe stream ({}) stopped, retrying after 2s", &program_id);
-        tokio::time::sleep(Duration::from_secs(2)).await;
+    loop {
+        // The sender lives as long as the loop above, which never ends
+        let transaction = receiver.recv().await.expect("ingestion sender dropped");
+        tokio::spawn(process_new_transaction(
+            config.clone(),
+            storage.clone(),
+            rpc_client.clone(),
+            transaction,
+        ));
+    }
+}
+
+async fn process_new_transaction(
+    config: Arc<VrfConfig>,
+    storage: Arc<dyn Storage>,
+    rpc_client: Arc<RpcClient>,
+    transaction: IngestedTransaction,
+) {
+    let IngestedTransaction {
+        program_id,
+        signature,
+        logs,
+    } = transaction;
+    let program_id: &str = &program_id;
+
+    let span = tracing::info_span!("Process transaction", program_id, transaction = signature);
+    let enter = span.enter();
+
+    let str_logs = logs.join("\n");
+    match storage.new_transaction(program_id, &signature, &str_logs) {
+        Ok(true) => {
+            tracing::info!("New transaction added: {}\n{}", &signature, &str_logs);
+            drop(enter);
+
+            process(
+                &config,
+                &*storage,
+                &rpc_client,
+                program_id,
+                &signature,
+                0,
+                &span,
+                &logs,
+                PreviousAttempts::default(),
+            )
+            .await;
+        }
+        Ok(false) => {}
+        Err(err) => {
+            tracing::error!("[DB] Add new transaction error: {err:#}");
+        }
     }
 }
 
-pub async fn retry_failed_transaction(config: Arc<VrfConfig>, rpc_client: Arc<RpcClient>) -> ! {
+pub async fn retry_failed_transaction(config: Arc<VrfConfig>, storage: Arc<dyn Storage>, rpc_client: Arc<RpcClient>) -> ! {
     loop {
-        match db::get_retryable_transaction(Duration::from_secs(config.retry_interval_seconds * 2)) {
+        // Leases of a stopped instance may expire after the startup recovery
+        recover_stuck_transaction(&config, &*storage, &rpc_client).await;
+
+        match storage.get_retryable_transaction() {
             Ok(transactions) => {
                 for trans in transactions {
                     let RetryableTransaction {
                         program_id,
                         transaction,
+                        event_index,
                         log_messages,
-                        ..
+                        compute_unit_price,
+                        vrf_seeds,
+                        vrf_proof,
+                        attempts,
                     } = trans;
 
-                    let span = tracing::info_span!("Retry transaction", program_id, transaction);
+                    let span = tracing::info_span!("Retry transaction", program_id, transaction, event_index);
                     let logs = log_messages.split("\n").collect::<Vec<_>>();
 
-                    process(&config, &rpc_client, &program_id, &transaction, &span, &logs).await;
+                    let previous = PreviousAttempts {
+                        count: u32::try_from(attempts).unwrap_or_default(),
+                        compute_unit_price: compute_unit_price.and_then(|price| u64::try_from(price).ok()),
+                        seeds: vrf_seeds,
+                        proof: vrf_proof,
+                    };
+                    process(
+                        &config,
+                        &*storage,
+                        &rpc_client,
+                        &program_id,
+                        &transaction,
+                        event_index,
+                        &span,
+                        &logs,
+                        previous,
+                    )
+                    .await;
                 }
             }
             Err(err) => {
@@ -144,98 +309,9 @@ pub async fn retry_failed_transaction(config: Arc<VrfConfig>, rpc_client: Arc<Rp
     }
 }
 
-pub async fn process_old_transaction(config: Arc<VrfConfig>, rpc_client: Arc<RpcClient>) {
-    const CACHE_SIZE: usize = 5000;
-    let processed_transaction_cache = &mut HashMap::new();
-    let programs = config
-        .program_ids
-        .iter()
-        .map(|program_id| (program_id, program_id.to_string()))
-        .collect::<Vec<_>>();
-
+pub async fn monitor_fee_payers(config: Arc<VrfConfig>, rpc_client: Arc<RpcClient>) -> ! {
     loop {
-        for (program_pubkey, program_id) in programs.iter() {
-            let transactions = processed_transaction_cache
-                .entry(program_id)
-                .or_insert_with(|| indexmap::IndexSet::new());
-
-            let mut before = None;
-            if let Ok(trans) = db::get_transactions(program_id, 1000) {
-                if transactions.len() + trans.len() > CACHE_SIZE {
-                    transactions.drain(0..(transactions.len() + trans.len() - CACHE_SIZE));
-                }
-
-                transactions.extend(trans);
-                before = transactions.last().map(|str| Signature::from_str(str).unwrap());
-            }
-
-            if let Ok(signatures) = rpc_client
-                .get_signatures_for_address_with_config(
-                    program_pubkey,
-                    GetConfirmedSignaturesForAddress2Config {
-                        before,
-                        until: None,
-                        limit: None,
-                        commitment: Some(CommitmentConfig::finalized()),
-                    },
-                )
-                .await
-            {
-                let fetched_len = signatures.len();
-                let signatures = signatures
-                    .into_iter()
-                    .filter(|sig| sig.err.is_none())
-                    .filter(|sig| !transactions.contains(&sig.signature))
-                    .collect::<Vec<_>>();
-
-                if signatures.is_empty() {
-                    continue;
-                }
-
-                tracing::info!(
-                    "Process old transaction: processing {} in {} fetched transactions",
-                    signatures.len(),
-                    fetched_len
-                );
-
-                for trans_sig in signatures {
-                    let RpcConfirmedTransactionStatusWithSignature { signature, .. } = trans_sig;
-
-                    if let Ok(encoded_transaction) = rpc_client
-                        .get_transaction(
-                            &Signature::from_str(&signature).expect("invalid signature return from get_signatures"),
-                            UiTransactionEncoding::Json,
-                        )
-                        .await
-                    {
-                        if let Some(UiTransactionStatusMeta {
-                            err: None,
-                            log_messages: Some(logs),
-                            ..
-                        }) = encoded_transaction.transaction.meta
-                        {
-                            let str_logs = logs.join("\n");
-                            match db::new_transaction(&program_id, &signature, &str_logs) {
-                                Ok(true) => {
-                                    let span = tracing::info_span!("Process old transaction", program_id, transaction = signature);
-                                    process(&config, &rpc_client, program_id, &signature, &span, &logs).await;
-                                }
-                                Ok(false) => {}
-                                Err(err) => {
-                                    tracing::event!(
-                                        tracing::Level::ERROR,
-                                        program_id,
-                                        transaction = signature,
-                                        "[DB] Add old transaction error: {err:#}",
-                                    );
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-
-        tokio::time::sleep(Duration::from_secs(30)).await;
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        config.fee_payers.refresh_balances(&rpc_client).await;
     }
 }
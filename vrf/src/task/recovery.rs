@@ -0,0 +1,130 @@
+//! Requests left `Status::Processing` by an instance which stopped mid-flight, e.g. after sending the fulfillment but
+//! before completing the request. Once the lease of the instance expired, the fulfillment it recorded before sending is
+//! looked up on-chain: the request is completed when it landed, and queued for a new attempt otherwise.
+//!
+//! The lease outlives the blockhash of a fulfillment, so one not found on-chain when the lease expired never lands.
+
+use std::str::FromStr;
+
+use anchor_client::{solana_client::nonblocking::rpc_client::RpcClient, solana_sdk::signature::Signature};
+
+use crate::{
+    db::{Storage, StuckTransaction},
+    error::ProcessError,
+    VrfConfig,
+};
+
+enum ResponseStatus {
+    Landed,
+    /// Landed but not yet at the configured commitment, checked again once the lease taken over expires
+    Pending,
+    Lost(ProcessError),
+}
+
+pub async fn recover_stuck_transaction(config: &VrfConfig, storage: &dyn Storage, rpc_client: &RpcClient) {
+    let transactions = match storage.get_stuck_transaction() {
+        Ok(transactions) => transactions,
+        Err(err) => {
+            tracing::error!("[DB] Get stuck transaction error {err:#}");
+            return;
+        }
+    };
+
+    for trans in transactions {
+        let StuckTransaction {
+            program_id,
+            transaction,
+            event_index,
+            response_transaction,
+            vrf_seeds,
+            vrf_proof,
+            attempts,
+        } = trans;
+
+        let span = tracing::info_span!("Recover transaction", program_id, transaction, event_index);
+        match storage.take_over_processing(&program_id, &transaction, event_index, &config.instance_id, config.processing_lease) {
+            Ok(true) => {}
+            Ok(false) => continue,
+            Err(err) => {
+                span.in_scope(|| tracing::error!("[DB] Take over transaction error: {err:#}"));
+                continue;
+            }
+        }
+
+        let status = match response_status(config, rpc_client, response_transaction.as_deref()).await {
+            Ok(status) => status,
+            Err(err) => {
+                span.in_scope(|| tracing::warn!("Check response transaction error: {err}"));
+                continue;
+            }
+        };
+
+        let _enter = span.enter();
+        let db_result = match (status, response_transaction) {
+            (ResponseStatus::Landed, Some(response_transaction)) => {
+                tracing::info!("Recovered ({transaction}), response {response_transaction} landed");
+                storage.complete_processing(
+                    &program_id,
+                    &transaction,
+                    event_index,
+                    response_transaction,
+                    vrf_seeds.unwrap_or_default(),
+                    vrf_proof.unwrap_or_default(),
+                )
+            }
+            (ResponseStatus::Lost(err), _) => {
+                tracing::warn!("Recovered ({transaction}): {err}");
+                let attempts = u32::try_from(attempts).unwrap_or_default() + 1;
+                let retry_after = config.backoff.next_attempt_delay(attempts);
+                if retry_after.is_none() {
+                    tracing::error!("Giving up ({transaction}) after {attempts} attempts");
+                }
+                storage.error_processing(&program_id, &transaction, event_index, err.to_request_error(), retry_after)
+            }
+            _ => {
+                tracing::info!("Response of ({transaction}) not yet confirmed");
+                continue;
+            }
+        };
+
+        if let Err(err) = db_result {
+            tracing::error!("[DB] Recover transaction error: {err:#}");
+        }
+    }
+}
+
+async fn response_status(
+    config: &VrfConfig,
+    rpc_client: &RpcClient,
+    response_transaction: Option<&str>,
+) -> Result<ResponseStatus, ProcessError> {
+    let signature = match response_transaction.and_then(|signature| Signature::from_str(signature).ok()) {
+        Some(signature) => signature,
+        None => {
+            return Ok(ResponseStatus::Lost(ProcessError::Interrupted {
+                response_transaction: None,
+            }))
+        }
+    };
+
+    let status = rpc_client
+        .get_signature_statuses_with_history(&[signature])
+        .await?
+        .value
+        .pop()
+        .flatten();
+
+    Ok(match status {
+        None => ResponseStatus::Lost(ProcessError::Interrupted {
+            response_transaction: Some(signature.to_string()),
+        }),
+        Some(status) => match status.err {
+            Some(error) => ResponseStatus::Lost(ProcessError::ResponseFailed {
+                response_transaction: signature.to_string(),
+                error,
+            }),
+            None if status.satisfies_commitment(config.commitment) => ResponseStatus::Landed,
+            None => ResponseStatus::Pending,
+        },
+    })
+}
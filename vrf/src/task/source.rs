@@ -0,0 +1,298 @@
+//! Ingestion sources, selected with `ingestion` in `vrf-config.toml`. The Yellowstone (Geyser gRPC) source needs the
+//! `yellowstone` feature.
+
+use std::{path::PathBuf, sync::Arc};
+
+use anchor_client::{
+    solana_client::{
+        nonblocking::pubsub_client::PubsubClient,
+        rpc_config::{RpcBlockSubscribeConfig, RpcBlockSubscribeFilter, RpcTransactionLogsConfig, RpcTransactionLogsFilter},
+        rpc_response::RpcLogsResponse,
+    },
+    solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey},
+};
+use anyhow::Context;
+use futures_util::stream::{self, StreamExt};
+use solana_transaction_status::{
+    EncodedTransaction, EncodedTransactionWithStatusMeta, TransactionDetails, UiTransactionEncoding, UiTransactionStatusMeta,
+};
+use tokio::sync::mpsc;
+
+use super::{IngestedTransaction, IngestionSource};
+
+#[cfg(feature = "yellowstone")]
+mod yellowstone;
+
+#[derive(Debug, Default, Clone, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IngestionConfig {
+    /// `logsSubscribe` mentioning each program
+    #[default]
+    LogsSubscribe,
+    /// `blockSubscribe` mentioning each program, the RPC node needs `--rpc-pubsub-enable-block-subscription`
+    BlockSubscribe,
+    /// Transactions of a JSON lines file, for tests
+    ReplayFile(PathBuf),
+    /// Yellowstone (Geyser gRPC) transaction subscription mentioning each program
+    #[cfg(feature = "yellowstone")]
+    Yellowstone(yellowstone::YellowstoneConfig),
+}
+
+impl IngestionConfig {
+    pub fn new_source(&self, ws_url: String, program_ids: &[Pubkey], commitment: CommitmentConfig) -> Arc<dyn IngestionSource> {
+        let program_ids = program_ids.to_vec();
+        match self {
+            IngestionConfig::LogsSubscribe => Arc::new(LogsSubscribe {
+                ws_url,
+                program_ids,
+                commitment,
+            }),
+            IngestionConfig::BlockSubscribe => Arc::new(BlockSubscribe {
+                ws_url,
+                program_ids,
+                commitment,
+            }),
+            IngestionConfig::ReplayFile(path) => Arc::new(ReplayFile {
+                path: path.clone(),
+                program_ids,
+            }),
+            #[cfg(feature = "yellowstone")]
+            IngestionConfig::Yellowstone(config) => Arc::new(yellowstone::Yellowstone {
+                config: config.clone(),
+                program_ids,
+                commitment,
+            }),
+        }
+    }
+}
+
+pub struct LogsSubscribe {
+    ws_url: String,
+    program_ids: Vec<Pubkey>,
+    commitment: CommitmentConfig,
+}
+
+#[async_trait::async_trait]
+impl IngestionSource for LogsSubscribe {
+    fn name(&self) -> &'static str {
+        "logs-subscribe"
+    }
+
+    async fn run(&self, sender: &mpsc::Sender<IngestedTransaction>) -> anyhow::Result<()> {
+        let pubsub_client = PubsubClient::new(&self.ws_url).await?;
+
+        // One subscription per program, `Mentions` takes a single address
+        let mut streams = Vec::with_capacity(self.program_ids.len());
+        for program_id in &self.program_ids {
+            let (stream, _) = pubsub_client
+                .logs_subscribe(
+                    RpcTransactionLogsFilter::Mentions(vec![program_id.to_string()]),
+                    RpcTransactionLogsConfig {
+                        commitment: Some(self.commitment),
+                    },
+                )
+                .await?;
+            tracing::info!("Listening for logs from: {program_id}");
+            streams.push(stream.map(move |response| (program_id, response.value)));
+        }
+
+        let mut responses = stream::select_all(streams);
+        while let Some((program_id, response)) = responses.next().await {
+            let RpcLogsResponse { signature, err, logs } = response;
+            if let Some(err) = err {
+                tracing::info!(program_id = %program_id, "Skipping error transaction ({signature}):\n{err:#}");
+                continue;
+            }
+
+            send(
+                sender,
+                IngestedTransaction {
+                    program_id: program_id.to_string(),
+                    signature,
+                    logs,
+                },
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+}
+
+pub struct BlockSubscribe {
+    ws_url: String,
+    program_ids: Vec<Pubkey>,
+    commitment: CommitmentConfig,
+}
+
+#[async_trait::async_trait]
+impl IngestionSource for BlockSubscribe {
+    fn name(&self) -> &'static str {
+        "block-subscribe"
+    }
+
+    async fn run(&self, sender: &mpsc::Sender<IngestedTransaction>) -> anyhow::Result<()> {
+        let pubsub_client = PubsubClient::new(&self.ws_url).await?;
+
+        let mut streams = Vec::with_capacity(self.program_ids.len());
+        for program_id in &self.program_ids {
+            let (stream, _) = pubsub_client
+                .block_subscribe(
+                    RpcBlockSubscribeFilter::MentionsAccountOrProgram(program_id.to_string()),
+                    Some(RpcBlockSubscribeConfig {
+                        commitment: Some(self.commitment),
+                        encoding: Some(UiTransactionEncoding::Json),
+                        transaction_details: Some(TransactionDetails::Full),
+                        show_rewards: Some(false),
+                        ..Default::default()
+                    }),
+                )
+                .await?;
+            tracing::info!("Listening for blocks mentioning: {program_id}");
+            streams.push(stream.map(move |response| (program_id, response.value)));
+        }
+
+        let mut updates = stream::select_all(streams);
+        while let Some((program_id, update)) = updates.next().await {
+            if let Some(err) = update.err {
+                tracing::warn!(program_id = %program_id, "Block update error at slot {}: {err:?}", update.slot);
+                continue;
+            }
+
+            let transactions = update.block.and_then(|block| block.transactions).unwrap_or_default();
+            for transaction in transactions {
+                if let Some((signature, logs)) = successful_transaction(transaction) {
+                    send(
+                        sender,
+                        IngestedTransaction {
+                            program_id: program_id.to_string(),
+                            signature,
+                            logs,
+                        },
+                    )
+                    .await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Signature and logs of a transaction of a block which succeeded
+fn successful_transaction(transaction: EncodedTransactionWithStatusMeta) -> Option<(String, Vec<String>)> {
+    let signature = match transaction.transaction {
+        EncodedTransaction::Json(transaction) => transaction.signatures.into_iter().next()?,
+        _ => return None,
+    };
+
+    match transaction.meta {
+        Some(UiTransactionStatusMeta {
+            err: None,
+            log_messages: Some(logs),
+            ..
+        }) => Some((signature, logs)),
+        _ => None,
+    }
+}
+
+/// One `IngestedTransaction` JSON object per line, the transactions of programs not configured are skipped
+pub struct ReplayFile {
+    path: PathBuf,
+    program_ids: Vec<Pubkey>,
+}
+
+impl ReplayFile {
+    fn read(&self) -> anyhow::Result<Vec<IngestedTransaction>> {
+        let content = std::fs::read_to_string(&self.path).with_context(|| format!("read {}", self.path.display()))?;
+        let program_ids = self.program_ids.iter().map(|program_id| program_id.to_string()).collect::<Vec<_>>();
+
+        let mut transactions = Vec::new();
+        for (index, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let transaction: IngestedTransaction =
+                serde_json::from_str(line).with_context(|| format!("{} line {}", self.path.display(), index + 1))?;
+            if program_ids.contains(&transaction.program_id) {
+                transactions.push(transaction);
+            }
+        }
+
+        Ok(transactions)
+    }
+}
+
+#[async_trait::async_trait]
+impl IngestionSource for ReplayFile {
+    fn name(&self) -> &'static str {
+        "replay-file"
+    }
+
+    async fn run(&self, sender: &mpsc::Sender<IngestedTransaction>) -> anyhow::Result<()> {
+        for transaction in self.read()? {
+            send(sender, transaction).await?;
+        }
+
+        // Stopping would replay the file again
+        std::future::pending().await
+    }
+}
+
+async fn send(sender: &mpsc::Sender<IngestedTransaction>, transaction: IngestedTransaction) -> anyhow::Result<()> {
+    sender
+        .send(transaction)
+        .await
+        .map_err(|_| anyhow::anyhow!("Ingestion receiver dropped"))
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::*;
+
+    #[test]
+    fn test_replay_file() {
+        let program_id = Pubkey::new_unique();
+        let path = std::env::temp_dir().join(format!("vrf-replay-{program_id}.jsonl"));
+        let line = |program_id: &str, signature: &str| {
+            serde_json::json!({ "program_id": program_id, "signature": signature, "logs": ["Program log: a"] }).to_string()
+        };
+        std::fs::write(
+            &path,
+            [
+                line(&program_id.to_string(), "tx1"),
+                String::new(),
+                line("other", "tx2"),
+                line(&program_id.to_string(), "tx3"),
+            ]
+            .join("\n"),
+        )
+        .unwrap();
+
+        let source = ReplayFile {
+            path: path.clone(),
+            program_ids: vec![program_id],
+        };
+        let transactions = source.read().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(transactions.len(), 2);
+        assert_eq!(
+            transactions[0],
+            IngestedTransaction {
+                program_id: program_id.to_string(),
+                signature: "tx1".to_string(),
+                logs: vec!["Program log: a".to_string()],
+            }
+        );
+        assert_eq!(transactions[1].signature, "tx3");
+
+        let config: IngestionConfig = serde_json::from_value(serde_json::json!({ "replay-file": "replay.jsonl" })).unwrap();
+        assert!(matches!(config, IngestionConfig::ReplayFile(path) if path == Path::new("replay.jsonl")));
+        let config: IngestionConfig = serde_json::from_value(serde_json::json!("block-subscribe")).unwrap();
+        assert!(matches!(config, IngestionConfig::BlockSubscribe));
+    }
+}
@@ -0,0 +1,348 @@
+//! Yellowstone (Geyser gRPC) transaction subscription, built with the `yellowstone` feature.
+//!
+//! The messages below are the subset of `geyser.proto` and `solana-storage.proto` this source reads, written by hand so
+//! the build needs neither `protoc` nor the Yellowstone client crates, which depend on a newer Solana SDK. Fields not
+//! declared here are skipped when decoding.
+
+use std::collections::HashMap;
+
+use anchor_client::solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
+use anyhow::Context;
+use futures_util::stream;
+use tokio::sync::mpsc;
+use tonic::{
+    codec::ProstCodec,
+    codegen::http::uri::PathAndQuery,
+    transport::{ClientTlsConfig, Endpoint},
+};
+
+use super::send;
+use crate::task::{IngestedTransaction, IngestionSource};
+
+const SUBSCRIBE_PATH: &str = "/geyser.Geyser/Subscribe";
+
+#[derive(Clone, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct YellowstoneConfig {
+    /// gRPC endpoint, e.g. `https://example.rpcpool.com:443`
+    pub endpoint: String,
+    /// Sent as the `x-token` header, required by most providers
+    pub x_token: Option<String>,
+}
+
+/// Without the token, printed at startup
+impl std::fmt::Debug for YellowstoneConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("YellowstoneConfig")
+            .field("endpoint", &self.endpoint)
+            .field("x_token", &self.x_token.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+pub struct Yellowstone {
+    pub(super) config: YellowstoneConfig,
+    pub(super) program_ids: Vec<Pubkey>,
+    pub(super) commitment: CommitmentConfig,
+}
+
+impl Yellowstone {
+    /// One filter per program, named after it so the updates tell which program they matched
+    fn subscribe_request(&self) -> SubscribeRequest {
+        let transactions = self
+            .program_ids
+            .iter()
+            .map(|program_id| {
+                let filter = SubscribeRequestFilterTransactions {
+                    vote: Some(false),
+                    failed: Some(false),
+                    account_include: vec![program_id.to_string()],
+                };
+                (program_id.to_string(), filter)
+            })
+            .collect();
+
+        let commitment = if self.commitment.is_finalized() {
+            CommitmentLevel::Finalized
+        } else if self.commitment.is_confirmed() {
+            CommitmentLevel::Confirmed
+        } else {
+            CommitmentLevel::Processed
+        };
+
+        SubscribeRequest {
+            transactions,
+            commitment: Some(commitment as i32),
+            ..Default::default()
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl IngestionSource for Yellowstone {
+    fn name(&self) -> &'static str {
+        "yellowstone"
+    }
+
+    async fn run(&self, sender: &mpsc::Sender<IngestedTransaction>) -> anyhow::Result<()> {
+        let mut endpoint = Endpoint::from_shared(self.config.endpoint.clone()).context("parse endpoint")?;
+        if self.config.endpoint.starts_with("https://") {
+            endpoint = endpoint.tls_config(ClientTlsConfig::new())?;
+        }
+        let channel = endpoint.connect().await.context("connect")?;
+        let mut client = tonic::client::Grpc::new(channel);
+
+        // The request stream stays open to answer the pings of the server, some providers close idle streams
+        let (requests, receiver) = mpsc::channel(16);
+        requests.send(self.subscribe_request()).await?;
+        let receiver = stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|request| (request, receiver))
+        });
+
+        let mut request = tonic::Request::new(receiver);
+        if let Some(x_token) = &self.config.x_token {
+            request.metadata_mut().insert("x-token", x_token.parse().context("x-token")?);
+        }
+
+        client.ready().await.context("connect")?;
+        let mut updates = client
+            .streaming(
+                request,
+                PathAndQuery::from_static(SUBSCRIBE_PATH),
+                ProstCodec::<SubscribeRequest, SubscribeUpdate>::default(),
+            )
+            .await?
+            .into_inner();
+        for program_id in &self.program_ids {
+            tracing::info!("Listening for transactions of: {program_id}");
+        }
+
+        while let Some(update) = updates.message().await? {
+            match update.update_oneof {
+                Some(UpdateOneof::Transaction(transaction)) => {
+                    for transaction in ingested_transactions(&update.filters, transaction) {
+                        send(sender, transaction).await?;
+                    }
+                }
+                Some(UpdateOneof::Ping(_)) => {
+                    let ping = SubscribeRequest {
+                        ping: Some(SubscribeRequestPing { id: 1 }),
+                        ..Default::default()
+                    };
+                    requests.send(ping).await?;
+                }
+                Some(UpdateOneof::Pong(_)) | None => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The transaction once per program filter it matched, when it succeeded
+fn ingested_transactions(filters: &[String], update: SubscribeUpdateTransaction) -> Vec<IngestedTransaction> {
+    let info = match update.transaction {
+        Some(info) => info,
+        None => return Vec::new(),
+    };
+    let signature = bs58::encode(&info.signature).into_string();
+
+    let logs = match info.meta {
+        Some(TransactionStatusMeta {
+            err: None,
+            log_messages,
+            log_messages_none: false,
+        }) => log_messages,
+        Some(TransactionStatusMeta { err: Some(_), .. }) => {
+            tracing::info!("Skipping error transaction ({signature})");
+            return Vec::new();
+        }
+        _ => {
+            tracing::warn!("Skipping transaction without logs ({signature}) at slot {}", update.slot);
+            return Vec::new();
+        }
+    };
+
+    filters
+        .iter()
+        .map(|program_id| IngestedTransaction {
+            program_id: program_id.clone(),
+            signature: signature.clone(),
+            logs: logs.clone(),
+        })
+        .collect()
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct SubscribeRequest {
+    #[prost(map = "string, message", tag = "3")]
+    transactions: HashMap<String, SubscribeRequestFilterTransactions>,
+    #[prost(enumeration = "CommitmentLevel", optional, tag = "6")]
+    commitment: Option<i32>,
+    #[prost(message, optional, tag = "9")]
+    ping: Option<SubscribeRequestPing>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct SubscribeRequestFilterTransactions {
+    #[prost(bool, optional, tag = "1")]
+    vote: Option<bool>,
+    #[prost(bool, optional, tag = "2")]
+    failed: Option<bool>,
+    #[prost(string, repeated, tag = "3")]
+    account_include: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct SubscribeRequestPing {
+    #[prost(int32, tag = "1")]
+    id: i32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+enum CommitmentLevel {
+    Processed = 0,
+    Confirmed = 1,
+    Finalized = 2,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct SubscribeUpdate {
+    /// Names of the filters the update matched
+    #[prost(string, repeated, tag = "1")]
+    filters: Vec<String>,
+    #[prost(oneof = "UpdateOneof", tags = "4, 6, 9")]
+    update_oneof: Option<UpdateOneof>,
+}
+
+#[derive(Clone, PartialEq, prost::Oneof)]
+enum UpdateOneof {
+    #[prost(message, tag = "4")]
+    Transaction(SubscribeUpdateTransaction),
+    #[prost(message, tag = "6")]
+    Ping(SubscribeUpdatePing),
+    #[prost(message, tag = "9")]
+    Pong(SubscribeUpdatePong),
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct SubscribeUpdateTransaction {
+    #[prost(message, optional, tag = "1")]
+    transaction: Option<SubscribeUpdateTransactionInfo>,
+    #[prost(uint64, tag = "2")]
+    slot: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct SubscribeUpdateTransactionInfo {
+    #[prost(bytes = "vec", tag = "1")]
+    signature: Vec<u8>,
+    #[prost(message, optional, tag = "4")]
+    meta: Option<TransactionStatusMeta>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct SubscribeUpdatePing {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct SubscribeUpdatePong {
+    #[prost(int32, tag = "1")]
+    id: i32,
+}
+
+/// `solana.storage.ConfirmedBlock.TransactionStatusMeta`
+#[derive(Clone, PartialEq, prost::Message)]
+struct TransactionStatusMeta {
+    #[prost(message, optional, tag = "1")]
+    err: Option<TransactionError>,
+    #[prost(string, repeated, tag = "6")]
+    log_messages: Vec<String>,
+    #[prost(bool, tag = "11")]
+    log_messages_none: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct TransactionError {
+    /// Bincode of the `TransactionError`
+    #[prost(bytes = "vec", tag = "1")]
+    err: Vec<u8>,
+}
+
+#[cfg(test)]
+mod test {
+    use prost::Message;
+
+    use super::*;
+    use crate::task::source::IngestionConfig;
+
+    #[test]
+    fn test_config() {
+        let config: IngestionConfig =
+            serde_json::from_value(serde_json::json!({ "yellowstone": { "endpoint": "https://grpc.example", "x-token": "token" } }))
+                .unwrap();
+        assert_eq!(
+            format!("{config:?}"),
+            r#"Yellowstone(YellowstoneConfig { endpoint: "https://grpc.example", x_token: Some("<redacted>") })"#
+        );
+        assert!(matches!(config, IngestionConfig::Yellowstone(config) if config.x_token.as_deref() == Some("token")));
+    }
+
+    #[test]
+    fn test_subscribe_request() {
+        let program_id = Pubkey::new_unique();
+        let source = Yellowstone {
+            config: YellowstoneConfig {
+                endpoint: "http://localhost:10000".to_string(),
+                x_token: None,
+            },
+            program_ids: vec![program_id],
+            commitment: CommitmentConfig::confirmed(),
+        };
+
+        let request = SubscribeRequest::decode(&source.subscribe_request().encode_to_vec()[..]).unwrap();
+        assert_eq!(request.commitment, Some(CommitmentLevel::Confirmed as i32));
+        let filter = &request.transactions[&program_id.to_string()];
+        assert_eq!(filter.vote, Some(false));
+        assert_eq!(filter.failed, Some(false));
+        assert_eq!(filter.account_include, [program_id.to_string()]);
+    }
+
+    #[test]
+    fn test_ingested_transactions() {
+        let update = |err: Option<TransactionError>| SubscribeUpdate {
+            filters: vec!["program1".to_string(), "program2".to_string()],
+            update_oneof: Some(UpdateOneof::Transaction(SubscribeUpdateTransaction {
+                transaction: Some(SubscribeUpdateTransactionInfo {
+                    signature: vec![7; 64],
+                    meta: Some(TransactionStatusMeta {
+                        err,
+                        log_messages: vec!["Program log: a".to_string()],
+                        log_messages_none: false,
+                    }),
+                }),
+                slot: 1,
+            })),
+        };
+        let transaction = |update: SubscribeUpdate| match SubscribeUpdate::decode(&update.encode_to_vec()[..]).unwrap() {
+            SubscribeUpdate {
+                filters,
+                update_oneof: Some(UpdateOneof::Transaction(transaction)),
+            } => ingested_transactions(&filters, transaction),
+            _ => panic!("not a transaction update"),
+        };
+
+        let transactions = transaction(update(None));
+        assert_eq!(transactions.len(), 2);
+        assert_eq!(
+            transactions[1],
+            IngestedTransaction {
+                program_id: "program2".to_string(),
+                signature: bs58::encode([7; 64]).into_string(),
+                logs: vec!["Program log: a".to_string()],
+            }
+        );
+
+        assert!(transaction(update(Some(TransactionError { err: vec![1] }))).is_empty());
+    }
+}
@@ -1,102 +1,121 @@
-use std::cell::RefCell;
-
 use anchor_client::{
     anchor_lang::{AnchorDeserialize, AnchorSerialize, Discriminator},
     solana_client::{
         client_error::ClientErrorKind,
         nonblocking::rpc_client::RpcClient,
+        rpc_config::RpcSimulateTransactionConfig,
         rpc_request::{RpcError, RpcResponseErrorData},
         rpc_response::RpcSimulateTransactionResult,
     },
     solana_sdk::{
         instruction::{AccountMeta, Instruction},
+        pubkey::Pubkey,
         signature::Signer,
         transaction::{Transaction, TransactionError},
     },
 };
-use once_cell::unsync::Lazy;
-use vrf::{
-    openssl::{CipherSuite, ECVRF},
-    VRF,
-};
-
-use crate::{
-    error::{FatalProcessError, ProcessError},
-    VrfConfig,
-};
 
-thread_local! {
-    static VRF: RefCell<Lazy<ECVRF>> = RefCell::new(Lazy::new(|| {
-        ECVRF::from_suite(CipherSuite::SECP256K1_SHA256_TAI).unwrap()
-    }));
-}
+use super::PreviousAttempts;
+use crate::{cpi_event, db::Storage, error::ProcessError, parse_log::Event, VrfConfig};
 
 pub struct VrfResponse {
     pub response_transaction: String,
+    /// VRF input of the request
     pub seeds: Vec<u8>,
     pub proof: Vec<u8>,
 }
 
-pub async fn process_log_response<S: AsRef<str>>(
+pub enum Outcome {
+    /// The transaction made no VRF request
+    NoRequest,
+    /// The pending check of the program found the request fulfilled, by an attempt which was not completed
+    AlreadyFulfilled,
+    Fulfilled(VrfResponse),
+}
+
+/// `RequestVrf` events of a transaction, in the order they were emitted
+pub async fn request_events<S: AsRef<str>>(
     config: &VrfConfig,
     rpc_client: &RpcClient,
     transaction: &str,
     span: &tracing::Span,
     logs: &[S],
-) -> Result<Option<VrfResponse>, ProcessError> {
-    let (events, errors) = crate::parse_log::process(&logs, &config.program_ids);
+) -> Result<Vec<Event>, ProcessError> {
+    let mut events = Vec::new();
+    if cpi_event::may_be_emitted(logs, &config.program_ids) {
+        match cpi_event::fetch(rpc_client, transaction, &config.program_ids, config.commitment).await {
+            Ok(cpi_events) => events = cpi_events,
+            Err(err) => span.in_scope(|| tracing::warn!("Event instructions not fetched, using the logs: {err:#}")),
+        }
+    }
 
-    if !errors.is_empty() {
-        return Err(ProcessError {
-            is_fatal: true,
-            error: anyhow::Error::msg(errors.into_iter().map(|err| format!("{err:?}\n")).collect::<String>()),
-        });
+    // Requests emitted with `emit!`
+    if events.is_empty() {
+        let (log_events, errors) = crate::parse_log::process(logs, &config.program_ids);
+        if !errors.is_empty() {
+            return Err(ProcessError::ParseLog(errors));
+        }
+        events = log_events;
     }
 
-    let event = {
-        let event = events
-            .into_iter()
-            .filter(|event| vrf_lib::RequestVrf::discriminator() == event.data[0..8])
-            .next();
+    Ok(events
+        .into_iter()
+        .filter(|event| vrf_lib::RequestVrf::discriminator() == event.data[0..8])
+        .collect())
+}
 
-        match event {
-            Some(event) => event,
-            None => return Ok(None),
-        }
+/// Fulfill `event`, the request at `event_index` of `transaction`
+#[allow(clippy::too_many_arguments)]
+pub async fn process_log_response(
+    config: &VrfConfig,
+    storage: &dyn Storage,
+    rpc_client: &RpcClient,
+    program_id: &str,
+    transaction: &str,
+    event_index: i32,
+    span: &tracing::Span,
+    event: Option<&Event>,
+    previous: &PreviousAttempts,
+) -> Result<Outcome, ProcessError> {
+    let event = match event {
+        Some(event) => event,
+        None => return Ok(Outcome::NoRequest),
     };
 
-    let request_vrf = vrf_lib::RequestVrf::deserialize(&mut &event.data[8..]).fatal_error_with_context("Deserialize RequestVrf Event")?;
+    let request_vrf = vrf_lib::RequestVrf::deserialize(&mut &event.data[8..]).map_err(ProcessError::DecodeEvent)?;
+    let provider = config
+        .randomness_providers
+        .get(&event.program_id)
+        .ok_or(ProcessError::NoRandomnessProvider(event.program_id))?;
     if !request_vrf.ix_data.starts_with(&[0; vrf_lib::VrfResult::RANDOM_BYTE_LEN]) {
         span.in_scope(|| tracing::warn!("Random byte slice not match, data lost may occur"));
     }
+    if !is_pending(config, rpc_client, &event.program_id, &request_vrf).await? {
+        span.in_scope(|| tracing::info!("Request already fulfilled"));
+        return Ok(Outcome::AlreadyFulfilled);
+    }
 
-    span.in_scope(|| tracing::info!("Gathering transaction hash.."));
-    let seeds = {
-        let mut seeds = Vec::with_capacity(config.num_confirmed_block * anchor_client::solana_sdk::hash::HASH_BYTES);
-
-        let mut block_hash = rpc_client.get_latest_blockhash().await?;
-        seeds.extend_from_slice(block_hash.as_ref());
-
-        span.in_scope(|| tracing::info!("Gathering transaction hash 1/{}", config.num_confirmed_block));
-
-        for index in 1..config.num_confirmed_block {
-            let next_block_hash = rpc_client.get_new_latest_blockhash(&block_hash).await?;
-            seeds.extend_from_slice(next_block_hash.as_ref());
-            block_hash = next_block_hash;
-
-            span.in_scope(|| tracing::info!("Gathering transaction hash {}/{}", index + 1, config.num_confirmed_block));
-        }
+    // Fixed by the request, see `vrf_lib::VrfRequest::alpha`
+    let seeds = request_vrf.alpha(&event.program_id).to_vec();
 
-        seeds
-    };
+    let mut request_transaction = [0; vrf_lib::VrfResult::SIGNATURE_BYTE_LEN];
+    bs58::decode(transaction)
+        .into(&mut request_transaction)
+        .expect("Pubkey::from_str transaction signature");
 
     let (proof, random) = {
-        let (proof, hash) = VRF.with(|vrf| {
-            let mut vrf = vrf.borrow_mut();
-            let proof = vrf.prove(&config.secret, &seeds).unwrap();
-            let hash = vrf.proof_to_hash(&proof).unwrap();
-            (proof, hash)
-        });
+        // Rows proven before the input was fixed by the request have other seeds
+        let proof = match (&previous.seeds, &previous.proof) {
+            (Some(previous_seeds), Some(proof)) if *previous_seeds == seeds => proof.clone(),
+            _ => {
+                let proof = provider.prove(&seeds).map_err(ProcessError::Randomness)?;
+                storage
+                    .set_proof(program_id, transaction, event_index, seeds.clone(), proof.clone())
+                    .map_err(ProcessError::Db)?;
+                proof
+            }
+        };
+        let hash = provider.kind().proof_to_hash(&proof).map_err(ProcessError::Randomness)?;
 
         let mut random = [0u8; vrf_lib::VrfResult::RANDOM_BYTE_LEN];
         random.copy_from_slice(&hash[..vrf_lib::VrfResult::RANDOM_BYTE_LEN]);
@@ -105,34 +124,52 @@ pub async fn process_log_response<S: AsRef<str>>(
 
     span.in_scope(|| tracing::info!("Random value: {:?}", &random));
 
-    let mut trans = {
+    let fee_payer = if config.fee_payers.is_empty() {
+        &config.owner
+    } else {
+        config.fee_payers.next().ok_or(ProcessError::FeePayersDrained)?
+    };
+    let signers = [&config.owner, fee_payer];
+    span.in_scope(|| tracing::info!("Fee payer: {}", fee_payer.pubkey()));
+
+    let compute_budget = &config.compute_budgets[&event.program_id];
+    let compute_unit_price = {
+        let writable_accounts = request_vrf
+            .accounts
+            .iter()
+            .filter(|acc| acc.is_writable)
+            .map(|acc| acc.pubkey)
+            .collect::<Vec<_>>();
+
+        compute_budget
+            .price(rpc_client, &writable_accounts, previous.compute_unit_price)
+            .await?
+    };
+    span.in_scope(|| tracing::info!("Compute unit price: {compute_unit_price}"));
+    storage
+        .set_compute_unit_price(program_id, transaction, event_index, compute_unit_price)
+        .map_err(ProcessError::Db)?;
+
+    let instruction = {
         let mut ix_data = request_vrf.ix_sighash.to_vec();
         {
-            let mut request_transaction = [0; vrf_lib::VrfResult::SIGNATURE_BYTE_LEN];
-            bs58::decode(transaction)
-                .into(&mut request_transaction)
-                .expect("Pubkey::from_str transaction signature");
-
             let result = vrf_lib::VrfResult {
                 random,
                 request_transaction,
+                proof: provider.on_chain_proof(&proof).map_err(ProcessError::Randomness)?,
             };
 
-            let result = result.try_to_vec().unwrap();
-            ix_data.extend_from_slice(&result);
-            if request_vrf.ix_data.len() < result.len() {
-                // Incompatible layout
-                return Err(ProcessError {
-                    is_fatal: true,
-                    error: anyhow::anyhow!(
-                        "VrfResult incompatible layout: ix_data.len()={}, vrf_result.len()={}",
-                        request_vrf.ix_data.len(),
-                        result.len()
-                    ),
+            // The program emits a default VrfResult as placeholder, replaced by the filled one
+            let placeholder_len = vrf_lib::VrfResult::PLACEHOLDER_LEN;
+            if request_vrf.ix_data.len() < placeholder_len {
+                return Err(ProcessError::IncompatibleLayout {
+                    ix_data_len: request_vrf.ix_data.len(),
+                    placeholder_len,
                 });
             }
 
-            ix_data.extend_from_slice(&request_vrf.ix_data[result.len()..]);
+            ix_data.extend_from_slice(&result.try_to_vec().unwrap());
+            ix_data.extend_from_slice(&request_vrf.ix_data[placeholder_len..]);
         }
 
         let mut accounts = Vec::with_capacity(request_vrf.accounts.len() + 1);
@@ -141,28 +178,87 @@ pub async fn process_log_response<S: AsRef<str>>(
             is_signer: true,
             is_writable: false,
         });
-        accounts.extend(request_vrf.accounts.into_iter().map(|acc| AccountMeta {
+        accounts.extend(request_vrf.accounts.iter().map(|acc| AccountMeta {
             pubkey: acc.pubkey,
             is_signer: false,
             is_writable: acc.is_writable,
         }));
 
-        let instruction = Instruction {
+        Instruction {
             program_id: event.program_id,
             data: ix_data,
             accounts,
-        };
+        }
+    };
+
+    span.in_scope(|| tracing::info!("Simulating request..."));
+    let unit_limit = {
+        let trans = build_transaction(
+            compute_budget.instructions(compute_budget.unit_limit, compute_unit_price),
+            &instruction,
+            &fee_payer.pubkey(),
+        );
+        // Unsigned, so a remote signer only signs what is sent
+        let simulation = rpc_client
+            .simulate_transaction_with_config(
+                &trans,
+                RpcSimulateTransactionConfig {
+                    sig_verify: false,
+                    replace_recent_blockhash: true,
+                    commitment: Some(config.commitment),
+                    ..Default::default()
+                },
+            )
+            .await?
+            .value;
+
+        let simulation_logs = simulation.logs.unwrap_or_default();
+        storage
+            .set_simulation(
+                program_id,
+                transaction,
+                event_index,
+                simulation_logs.join("\n"),
+                simulation.units_consumed,
+            )
+            .map_err(ProcessError::Db)?;
 
+        if let Some(err) = simulation.err {
+            return Err(ProcessError::Simulation {
+                classification: config.program_errors[&event.program_id].classify(&event.program_id, &err, &simulation_logs),
+                logs: None,
+            });
+        }
+
+        compute_budget.estimated_unit_limit(simulation.units_consumed)
+    };
+    span.in_scope(|| tracing::info!("Compute unit limit: {unit_limit}"));
+
+    let mut trans = {
         let latest_hash = rpc_client.get_latest_blockhash().await?;
-        Transaction::new_signed_with_payer(&[instruction], Some(&config.owner.pubkey()), &[&config.owner], latest_hash)
+        let mut trans = build_transaction(
+            compute_budget.instructions(unit_limit, compute_unit_price),
+            &instruction,
+            &fee_payer.pubkey(),
+        );
+        // Not `new_signed_with_payer`, which panics when a remote signer fails
+        trans.try_sign(&signers, latest_hash)?;
+        trans
     };
+    record_response(storage, program_id, transaction, event_index, &trans, &seeds, &proof).map_err(ProcessError::Db)?;
 
     for _ in 0..2 {
+        // A previous send may have landed although it reported an error
+        if !is_pending(config, rpc_client, &event.program_id, &request_vrf).await? {
+            span.in_scope(|| tracing::info!("Request already fulfilled"));
+            return Ok(Outcome::AlreadyFulfilled);
+        }
+
         span.in_scope(|| tracing::info!("Sending request..."));
 
         match rpc_client.send_and_confirm_transaction(&trans).await {
             Ok(signature) => {
-                return Ok(Some(VrfResponse {
+                return Ok(Outcome::Fulfilled(VrfResponse {
                     response_transaction: signature.to_string(),
                     seeds,
                     proof,
@@ -171,34 +267,86 @@ pub async fn process_log_response<S: AsRef<str>>(
             Err(err) => match err.kind() {
                 ClientErrorKind::RpcError(RpcError::RpcResponseError { data, .. }) => {
                     if let RpcResponseErrorData::SendTransactionPreflightFailure(RpcSimulateTransactionResult {
-                        logs: Some(logs), ..
+                        err: Some(TransactionError::InsufficientFundsForFee | TransactionError::AccountNotFound),
+                        ..
                     }) = data
                     {
-                        let mut errors = "Simulation error logs:".to_string();
-                        for log in logs {
-                            errors.push('\t');
-                            errors.push_str(log);
-                            errors.push('\n');
-                        }
-
-                        return Err(err).fatal_error_with_context(errors);
+                        // Not the request's fault, retry it with another payer
+                        config.fee_payers.mark_drained(&fee_payer.pubkey());
+                        return Err(ProcessError::FeePayerCanNotPay {
+                            fee_payer: fee_payer.pubkey(),
+                            error: err,
+                        });
                     }
 
-                    return Err(err).fatal_error();
+                    if let RpcResponseErrorData::SendTransactionPreflightFailure(RpcSimulateTransactionResult {
+                        err: Some(transaction_err),
+                        logs: Some(logs),
+                        ..
+                    }) = data
+                    {
+                        // The accounts changed since the simulation
+                        return Err(ProcessError::Simulation {
+                            classification: config.program_errors[&event.program_id].classify(&event.program_id, transaction_err, logs),
+                            logs: Some(logs.clone()),
+                        });
+                    }
+
+                    return Err(ProcessError::SendTransaction(err));
                 }
                 ClientErrorKind::TransactionError(TransactionError::BlockhashNotFound)
                 | ClientErrorKind::TransactionError(TransactionError::AlreadyProcessed) => {
                     let new_blockhash = rpc_client.get_new_latest_blockhash(&trans.message.recent_blockhash).await?;
-                    trans.message.recent_blockhash = new_blockhash;
+                    trans.try_sign(&signers, new_blockhash)?;
+                    record_response(storage, program_id, transaction, event_index, &trans, &seeds, &proof).map_err(ProcessError::Db)?;
                     continue;
                 }
-                _ => return Err(err).fatal_error(),
+                ClientErrorKind::Io(_) | ClientErrorKind::Reqwest(_) => return Err(ProcessError::Rpc(err)),
+                _ => return Err(ProcessError::SendTransaction(err)),
             },
         }
     }
 
-    Err(ProcessError {
-        is_fatal: false,
-        error: anyhow::Error::msg("Failed to send transaction"),
-    })
+    Err(ProcessError::BlockhashExpired)
+}
+
+/// Programs without a pending check are always fulfilled
+async fn is_pending(
+    config: &VrfConfig,
+    rpc_client: &RpcClient,
+    program_id: &Pubkey,
+    request_vrf: &vrf_lib::RequestVrf,
+) -> Result<bool, ProcessError> {
+    match config.pending_checks.get(program_id) {
+        Some(check) => check
+            .is_pending(rpc_client, program_id, request_vrf)
+            .await
+            .map_err(ProcessError::PendingCheck),
+        None => Ok(true),
+    }
+}
+
+/// Before sending, so the recovery finds the fulfillment if this instance stops before completing the request
+fn record_response(
+    storage: &dyn Storage,
+    program_id: &str,
+    transaction: &str,
+    event_index: i32,
+    trans: &Transaction,
+    seeds: &[u8],
+    proof: &[u8],
+) -> anyhow::Result<()> {
+    storage.set_response_transaction(
+        program_id,
+        transaction,
+        event_index,
+        trans.signatures[0].to_string(),
+        seeds.to_vec(),
+        proof.to_vec(),
+    )
+}
+
+fn build_transaction(mut instructions: Vec<Instruction>, instruction: &Instruction, fee_payer: &Pubkey) -> Transaction {
+    instructions.push(instruction.clone());
+    Transaction::new_with_payer(&instructions, Some(fee_payer))
 }
@@ -0,0 +1,390 @@
+//! `vrf-server verify`: check a fulfilled request against its stored proof.
+//!
+//! A request is first exported from the database and the response transaction into a JSON record, which can then be
+//! verified without any access to the server, its database or the cluster.
+
+use std::{path::PathBuf, str::FromStr};
+
+use anchor_client::{
+    anchor_lang::{AnchorDeserialize, AnchorSerialize},
+    solana_client::nonblocking::rpc_client::RpcClient,
+    solana_sdk::{pubkey::Pubkey, signature::Signature},
+};
+use anyhow::Context;
+use solana_transaction_status::UiTransactionEncoding;
+
+use crate::{db, randomness::ProviderKind, VrfConfig};
+
+#[derive(Debug, clap::Args)]
+pub struct VerifyArgs {
+    /// Request transaction signature to export from the database and the cluster
+    #[arg(long, conflicts_with = "file", required_unless_present = "file")]
+    transaction: Option<String>,
+    /// Program of the request, required when several programs are configured
+    #[arg(long, requires = "transaction")]
+    program_id: Option<String>,
+    /// Request among the `RequestVrf` events of the transaction
+    #[arg(long, default_value_t = 0)]
+    event_index: i32,
+    /// Verify a previously exported record, fully offline
+    #[arg(long)]
+    file: Option<PathBuf>,
+    /// Print the exported record as JSON instead of verifying it
+    #[arg(long, requires = "transaction")]
+    export: bool,
+}
+
+/// Everything needed to check one fulfillment, binary fields are base64 encoded
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct VerifyRecord {
+    pub program_id: String,
+    pub provider: ProviderKind,
+    pub public_key: String,
+    pub request_transaction: String,
+    /// Request among the `RequestVrf` events of the request transaction
+    #[serde(default)]
+    pub event_index: i32,
+    /// VRF input, the hash of the request (see `vrf_lib::VrfRequest::alpha`)
+    pub vrf_seeds: String,
+    pub vrf_proof: String,
+    pub response_transaction: Option<String>,
+    /// Fulfillment instruction data found in the response transaction
+    pub response_instruction_data: Option<String>,
+    /// Accounts of the fulfillment instruction, the oracle signer first
+    #[serde(default)]
+    pub response_instruction_accounts: Vec<String>,
+}
+
+pub async fn run(args: VerifyArgs, load_config: impl FnOnce() -> anyhow::Result<VrfConfig>) -> anyhow::Result<bool> {
+    let record = match (&args.transaction, &args.file) {
+        (Some(transaction), _) => export(&load_config()?, args.program_id.as_deref(), transaction, args.event_index).await?,
+        (None, Some(file)) => {
+            let content = std::fs::read_to_string(file).with_context(|| format!("read {}", file.display()))?;
+            serde_json::from_str(&content).context("parse verify record")?
+        }
+        (None, None) => unreachable!("clap requires one of --transaction and --file"),
+    };
+
+    if args.export {
+        println!("{}", serde_json::to_string_pretty(&record)?);
+        return Ok(true);
+    }
+
+    let checks = verify(&record);
+    println!(
+        "Request {}#{} ({})",
+        record.request_transaction, record.event_index, record.program_id
+    );
+    for (name, result) in &checks {
+        match result {
+            Ok(()) => println!("  PASS  {name}"),
+            Err(err) => println!("  FAIL  {name}: {err}"),
+        }
+    }
+
+    let passed = checks.iter().all(|(_, result)| result.is_ok());
+    println!("{}", if passed { "PASS" } else { "FAIL" });
+    Ok(passed)
+}
+
+async fn export(config: &VrfConfig, program_id: Option<&str>, transaction: &str, event_index: i32) -> anyhow::Result<VerifyRecord> {
+    let program_id = match (program_id, config.program_ids.as_slice()) {
+        (Some(program_id), _) => Pubkey::from_str(program_id).context("program id")?,
+        (None, [program_id]) => *program_id,
+        (None, _) => anyhow::bail!("--program-id is required when several programs are configured"),
+    };
+
+    let row = db::connect(&config.database_url)?
+        .get_processed_transaction(&program_id.to_string(), transaction, event_index)?
+        .ok_or_else(|| anyhow::anyhow!("No processed request {transaction}#{event_index} of {program_id}"))?;
+
+    let provider = config
+        .randomness_providers
+        .get(&program_id)
+        .ok_or_else(|| anyhow::anyhow!("Program {program_id} is not in program-ids"))?;
+
+    let (response_instruction_data, response_instruction_accounts) = match &row.response_transaction {
+        Some(response_transaction) => {
+            let rpc_client = RpcClient::new_with_commitment(config.cluster.url().to_string(), config.commitment);
+            let response = rpc_client
+                .get_transaction(&Signature::from_str(response_transaction)?, UiTransactionEncoding::Base64)
+                .await
+                .context("get response transaction")?;
+            let response = response
+                .transaction
+                .transaction
+                .decode()
+                .ok_or_else(|| anyhow::anyhow!("Can not decode response transaction"))?;
+
+            let keys = response.message.static_account_keys();
+            match response
+                .message
+                .instructions()
+                .iter()
+                .find(|ix| keys.get(ix.program_id_index as usize) == Some(&program_id))
+            {
+                Some(ix) => (
+                    Some(base64::encode(&ix.data)),
+                    ix.accounts.iter().map(|index| keys[*index as usize].to_string()).collect(),
+                ),
+                None => (None, Vec::new()),
+            }
+        }
+        None => (None, Vec::new()),
+    };
+
+    Ok(VerifyRecord {
+        program_id: row.program_id,
+        provider: provider.kind(),
+        public_key: base64::encode(provider.public_key()?),
+        request_transaction: row.transaction,
+        event_index: row.event_index,
+        vrf_seeds: base64::encode(row.vrf_seeds.unwrap_or_default()),
+        vrf_proof: base64::encode(row.vrf_proof.unwrap_or_default()),
+        response_transaction: row.response_transaction,
+        response_instruction_data,
+        response_instruction_accounts,
+    })
+}
+
+/// Named checks of `record`, in order
+pub fn verify(record: &VerifyRecord) -> Vec<(&'static str, Result<(), String>)> {
+    let mut checks = Vec::new();
+
+    let decoded = (|| -> anyhow::Result<_> {
+        let mut request_transaction = [0; vrf_lib::VrfResult::SIGNATURE_BYTE_LEN];
+        bs58::decode(&record.request_transaction)
+            .into(&mut request_transaction)
+            .context("request transaction signature")?;
+
+        Ok((
+            request_transaction,
+            base64::decode(&record.public_key).context("public key")?,
+            base64::decode(&record.vrf_seeds).context("vrf seeds")?,
+            base64::decode(&record.vrf_proof).context("vrf proof")?,
+        ))
+    })();
+    let (request_transaction, public_key, seeds, proof) = match decoded {
+        Ok(decoded) => decoded,
+        Err(err) => {
+            checks.push(("decode record", Err(format!("{err:#}"))));
+            return checks;
+        }
+    };
+
+    let hash = match record.provider.verify(&public_key, &proof, &seeds) {
+        Ok(hash) => {
+            checks.push(("proof", Ok(())));
+            hash
+        }
+        Err(err) => {
+            checks.push(("proof", Err(format!("{err:#}"))));
+            return checks;
+        }
+    };
+
+    let data = match &record.response_instruction_data {
+        Some(data) => data,
+        None => {
+            checks.push(("response instruction", Err("not found in the response transaction".to_string())));
+            return checks;
+        }
+    };
+
+    let result = base64::decode(data)
+        .map_err(anyhow::Error::from)
+        .and_then(|data| {
+            anyhow::ensure!(data.len() >= 8, "instruction data too short");
+            Ok(vrf_lib::VrfResult::deserialize(&mut &data[8..])?)
+        })
+        .map_err(|err| format!("{err:#}"));
+    let result = match result {
+        Ok(result) => result,
+        Err(err) => {
+            checks.push(("response instruction", Err(err)));
+            return checks;
+        }
+    };
+
+    checks.push((
+        "random",
+        check(
+            result.random[..] == hash[..vrf_lib::VrfResult::RANDOM_BYTE_LEN],
+            format!(
+                "response has {:?}, proof hash gives {:?}",
+                result.random,
+                &hash[..vrf_lib::VrfResult::RANDOM_BYTE_LEN]
+            ),
+        ),
+    ));
+    checks.push((
+        "request transaction",
+        check(
+            result.request_transaction == request_transaction,
+            "response answers another request".to_string(),
+        ),
+    ));
+    checks.push((
+        "request",
+        request_alpha(record, data).and_then(|alpha| {
+            check(
+                alpha[..] == seeds[..],
+                "proven input is not the hash of the answered request".to_string(),
+            )
+        }),
+    ));
+    if record.provider == ProviderKind::EcvrfSecp256k1 {
+        checks.push((
+            "on-chain proof",
+            check(
+                result.proof.to_bytes()[..] == proof[..],
+                "response proof differs from the stored one".to_string(),
+            ),
+        ));
+    }
+
+    checks
+}
+
+/// VRF input of the request answered by the response instruction: its data with the result replaced by the
+/// placeholder, and its accounts without the oracle signer
+fn request_alpha(record: &VerifyRecord, data: &str) -> Result<[u8; 32], String> {
+    (|| -> anyhow::Result<_> {
+        let program_id = Pubkey::from_str(&record.program_id).context("program id")?;
+        let data = base64::decode(data)?;
+        let placeholder_len = vrf_lib::VrfResult::PLACEHOLDER_LEN;
+        anyhow::ensure!(data.len() >= 8 + placeholder_len, "instruction data too short");
+        anyhow::ensure!(!record.response_instruction_accounts.is_empty(), "no instruction accounts");
+
+        let mut ix_data = vrf_lib::VrfResult::default().try_to_vec()?;
+        ix_data.extend_from_slice(&data[8 + placeholder_len..]);
+        let accounts = record.response_instruction_accounts[1..]
+            .iter()
+            .map(|account| {
+                Ok(vrf_lib::AccountMetaRef {
+                    pubkey: Pubkey::from_str(account).context("instruction account")?,
+                    is_writable: false,
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        let request = vrf_lib::RequestVrf {
+            ix_sighash: data[0..8].try_into().unwrap(),
+            ix_data,
+            accounts,
+        };
+        Ok(request.alpha(&program_id))
+    })()
+    .map_err(|err| format!("{err:#}"))
+}
+
+fn check(passed: bool, error: String) -> Result<(), String> {
+    if passed {
+        Ok(())
+    } else {
+        Err(error)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn record(provider: ProviderKind) -> VerifyRecord {
+        let program_id = Pubkey::new_unique();
+        let accounts = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let mut ix_data = vrf_lib::VrfResult::default().try_to_vec().unwrap();
+        ix_data.extend(42u64.to_le_bytes());
+        let request = vrf_lib::RequestVrf {
+            ix_sighash: [0; 8],
+            ix_data,
+            accounts: vec![vrf_lib::account_meta(&accounts[1]).mutable()],
+        };
+        let seeds = request.alpha(&program_id);
+
+        let request_transaction = [7; vrf_lib::VrfResult::SIGNATURE_BYTE_LEN];
+        let provider_impl = provider.new_provider(&[5; 32], Some(b"seed")).unwrap();
+        let proof = provider_impl.prove(&seeds).unwrap();
+        let hash = provider.proof_to_hash(&proof).unwrap();
+
+        let result = vrf_lib::VrfResult {
+            random: hash[..vrf_lib::VrfResult::RANDOM_BYTE_LEN].try_into().unwrap(),
+            request_transaction,
+            proof: provider_impl.on_chain_proof(&proof).unwrap(),
+        };
+        let mut data = vec![0; 8];
+        data.extend(result.try_to_vec().unwrap());
+        data.extend(42u64.to_le_bytes());
+
+        VerifyRecord {
+            program_id: program_id.to_string(),
+            provider,
+            public_key: base64::encode(provider_impl.public_key().unwrap()),
+            request_transaction: bs58::encode(request_transaction).into_string(),
+            event_index: 0,
+            vrf_seeds: base64::encode(seeds),
+            vrf_proof: base64::encode(proof),
+            response_transaction: None,
+            response_instruction_data: Some(base64::encode(data)),
+            response_instruction_accounts: accounts.iter().map(Pubkey::to_string).collect(),
+        }
+    }
+
+    fn passed(record: &VerifyRecord) -> bool {
+        verify(record).iter().all(|(_, result)| result.is_ok())
+    }
+
+    #[test]
+    fn test_verify() {
+        for provider in [
+            ProviderKind::EcvrfSecp256k1,
+            ProviderKind::EcvrfEdwards25519,
+            ProviderKind::Deterministic,
+        ] {
+            let record = record(provider);
+            assert!(passed(&record), "{:?}", verify(&record));
+
+            let json = serde_json::to_string(&record).unwrap();
+            assert!(passed(&serde_json::from_str(&json).unwrap()));
+        }
+    }
+
+    #[test]
+    fn test_verify_tampered() {
+        let mut tampered = record(ProviderKind::EcvrfEdwards25519);
+        let mut data = base64::decode(tampered.response_instruction_data.as_ref().unwrap()).unwrap();
+        data[8] ^= 1;
+        tampered.response_instruction_data = Some(base64::encode(data));
+        assert_eq!(verify(&tampered).iter().find(|(_, result)| result.is_err()).unwrap().0, "random");
+
+        let mut tampered = record(ProviderKind::EcvrfEdwards25519);
+        tampered.vrf_seeds = base64::encode(vec![4; 32]);
+        assert_eq!(verify(&tampered).iter().find(|(_, result)| result.is_err()).unwrap().0, "proof");
+
+        // Proven for another request
+        let mut tampered = record(ProviderKind::EcvrfEdwards25519);
+        tampered.response_instruction_accounts[1] = Pubkey::new_unique().to_string();
+        assert_eq!(verify(&tampered).iter().find(|(_, result)| result.is_err()).unwrap().0, "request");
+
+        let mut tampered = record(ProviderKind::EcvrfEdwards25519);
+        tampered.public_key = record(ProviderKind::Deterministic).public_key;
+        assert!(!passed(&tampered));
+
+        // Verified on-chain, the proof sent in the response must be the stored one
+        let mut tampered = record(ProviderKind::EcvrfSecp256k1);
+        let mut data = base64::decode(tampered.response_instruction_data.as_ref().unwrap()).unwrap();
+        data[8 + vrf_lib::VrfResult::PLACEHOLDER_LEN - 1] ^= 1;
+        tampered.response_instruction_data = Some(base64::encode(data));
+        let failed = verify(&tampered)
+            .into_iter()
+            .filter(|(_, result)| result.is_err())
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        assert_eq!(failed, ["on-chain proof"]);
+
+        let mut missing = record(ProviderKind::Deterministic);
+        missing.response_instruction_data = None;
+        assert!(!passed(&missing));
+    }
+}